@@ -52,189 +52,445 @@
 //! 5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4)) becomes 669060.
 //! ((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2 becomes 23340.
 //! What do you get if you add up the results of evaluating the homework problems using these new rules?
-use std::collections::VecDeque;
+use std::fmt;
 
 use aoc_runner_derive::{aoc, aoc_generator};
+use thiserror::Error;
 
+/// Byte offsets `start..end` of a token or expression in its line.
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub enum Token {
-    Num(u64),
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
     Add,
+    Sub,
     Mul,
+    Div,
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = match self {
+            Op::Add => '+',
+            Op::Sub => '-',
+            Op::Mul => '*',
+            Op::Div => '/',
+        };
+        write!(f, "{}", c)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+/// Precedence table, each operator with its binding power (higher binds tighter) and
+/// associativity. Operators missing from the table are rejected by the parser.
+pub type Precedence = [(Op, u8, Assoc)];
+
+/// Part 1: every operator binds equally and evaluates left to right.
+pub const SAME_PRECEDENCE: &Precedence = &[
+    (Op::Add, 1, Assoc::Left),
+    (Op::Sub, 1, Assoc::Left),
+    (Op::Mul, 1, Assoc::Left),
+    (Op::Div, 1, Assoc::Left),
+];
+
+/// Part 2: addition and subtraction bind tighter than multiplication and division.
+pub const ADDITION_FIRST: &Precedence = &[
+    (Op::Add, 2, Assoc::Left),
+    (Op::Sub, 2, Assoc::Left),
+    (Op::Mul, 1, Assoc::Left),
+    (Op::Div, 1, Assoc::Left),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenKind {
+    Num(i64),
+    Op(Op),
     Open,
     Close,
-    Space,
-    Stop,
 }
 
-#[aoc_generator(day18)]
-fn generator(input: &str) -> Vec<Vec<Token>> {
-    input.split('\n').map(lex).collect()
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
 }
 
-fn lex(input: &str) -> Vec<Token> {
-    input
-        .bytes()
-        .map(|b| match b {
-            b'(' => Token::Open,
-            b')' => Token::Close,
-            b'+' => Token::Add,
-            b'*' => Token::Mul,
-            b' ' => Token::Space,
-            c @ b'0'..=b'9' => Token::Num((c - b'0') as u64),
-            c => panic!("unexpected char '{:?}'", c),
-        })
-        // Ignore spaces
-        .filter(|t| t != &Token::Space)
-        .chain(std::iter::once(Token::Stop))
-        .collect()
+#[derive(Debug, Error, PartialEq)]
+pub enum ParseError {
+    #[error("{0}: unexpected character '{1}'")]
+    UnexpectedChar(Span, char),
+    #[error("{0}: number too large")]
+    NumberTooLarge(Span),
+    #[error("{0}: expected a number or '('")]
+    ExpectedOperand(Span),
+    #[error("{0}: expected an operator")]
+    ExpectedOperator(Span),
+    #[error("{0}: unclosed '('")]
+    UnclosedParen(Span),
+    #[error("{0}: operator '{1}' isn't in the precedence table")]
+    UnsupportedOperator(Span, Op),
 }
 
-mod part1 {
-    use std::collections::VecDeque;
+#[derive(Debug, Error, PartialEq)]
+pub enum EvalError {
+    #[error("{0}: overflow evaluating '{1}'")]
+    Overflow(Span, Op),
+    #[error("{0}: division by zero")]
+    DivisionByZero(Span),
+}
 
-    use super::Token;
-    fn parse_item(tokens: &mut VecDeque<Token>) -> u64 {
-        let t = tokens.pop_front().unwrap();
-        match t {
-            Token::Num(n) => return n,
-            Token::Open => {
-                let expr = parse_expression(tokens);
-                if let Token::Close = tokens[0] {
-                    // TODO(wathiede): how to do not?
-                } else {
-                    panic!("expected close paren");
+pub fn lex(line: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let mut span = Span {
+            start,
+            end: start + c.len_utf8(),
+        };
+        let kind = match c {
+            c if c.is_whitespace() => continue,
+            '(' => TokenKind::Open,
+            ')' => TokenKind::Close,
+            '+' => TokenKind::Op(Op::Add),
+            '-' => TokenKind::Op(Op::Sub),
+            '*' => TokenKind::Op(Op::Mul),
+            '/' => TokenKind::Op(Op::Div),
+            '0'..='9' => {
+                while let Some((i, _)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                    span.end = i + 1;
                 }
-                tokens.pop_front();
-                return expr;
+                let n = line[span.start..span.end]
+                    .parse()
+                    .map_err(|_| ParseError::NumberTooLarge(span))?;
+                TokenKind::Num(n)
             }
-            t => panic!("unexpected token {:?}", t),
+            c => return Err(ParseError::UnexpectedChar(span, c)),
         };
+        tokens.push(Token { kind, span });
     }
+    Ok(tokens)
+}
 
-    pub fn parse_expression(tokens: &mut VecDeque<Token>) -> u64 {
-        let mut result = parse_item(tokens);
-        let mut t = tokens[0];
-        loop {
-            match t {
-                Token::Mul => {
-                    tokens.pop_front();
-                    let rhs = parse_item(tokens);
-                    if let Token::Mul = t {
-                        result = result * rhs;
-                    }
-                    t = tokens[0];
-                }
-                Token::Add => {
-                    tokens.pop_front();
-                    let rhs = parse_item(tokens);
-                    if let Token::Add = t {
-                        result = result + rhs;
-                    }
-                    t = tokens[0];
-                }
-                _ => break,
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Num(i64, Span),
+    Binary {
+        op: Op,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+        span: Span,
+    },
+}
+
+impl Expr {
+    /// Parses `tokens` with operator precedence climbing, using the binding powers in `table`.
+    pub fn parse(tokens: &[Token], table: &Precedence) -> Result<Expr, ParseError> {
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            table,
+        };
+        let expr = parser.expr(0)?;
+        match parser.peek() {
+            Some(t) => Err(ParseError::ExpectedOperator(t.span)),
+            None => Ok(expr),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Num(_, span) | Expr::Binary { span, .. } => *span,
+        }
+    }
+
+    /// Evaluates the expression, reporting overflow and division by zero instead of panicking.
+    /// Division truncates towards zero.
+    pub fn eval(&self) -> Result<i64, EvalError> {
+        match self {
+            Expr::Num(n, _) => Ok(*n),
+            Expr::Binary { op, lhs, rhs, span } => {
+                let (l, r) = (lhs.eval()?, rhs.eval()?);
+                let v = match op {
+                    Op::Add => l.checked_add(r),
+                    Op::Sub => l.checked_sub(r),
+                    Op::Mul => l.checked_mul(r),
+                    Op::Div if r == 0 => return Err(EvalError::DivisionByZero(*span)),
+                    Op::Div => l.checked_div(r),
+                };
+                v.ok_or(EvalError::Overflow(*span, *op))
             }
         }
-        result
     }
 }
 
-fn parse_part1(tokens: &[Token]) -> u64 {
-    let mut vd: VecDeque<Token> = tokens.into_iter().cloned().collect();
-    part1::parse_expression(&mut vd)
+/// Fully parenthesized, so the shape of the tree is visible.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Num(n, _) => write!(f, "{}", n),
+            Expr::Binary { op, lhs, rhs, .. } => write!(f, "({} {} {})", lhs, op, rhs),
+        }
+    }
 }
 
-#[aoc(day18, part1)]
-fn solution1(tokens_list: &[Vec<Token>]) -> u64 {
-    tokens_list.iter().map(|tokens| parse_part1(tokens)).sum()
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    table: &'a Precedence,
 }
 
-mod part2 {
-    use std::collections::VecDeque;
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
 
-    use super::Token;
-    fn parse_item(tokens: &mut VecDeque<Token>) -> u64 {
-        let t = tokens.pop_front().unwrap();
-        match t {
-            Token::Num(n) => return n,
-            Token::Open => {
-                let expr = parse_expression(tokens);
-                if let Token::Close = tokens[0] {
-                    // TODO(wathiede): how to do not?
-                } else {
-                    panic!("expected close paren");
-                }
-                tokens.pop_front();
-                return expr;
-            }
-            t => panic!("unexpected token {:?}", t),
-        };
+    fn next(&mut self) -> Option<Token> {
+        let t = self.peek();
+        self.pos += 1;
+        t
     }
 
-    fn parse_term(tokens: &mut VecDeque<Token>) -> u64 {
-        let mut result = parse_item(tokens);
-        let mut t = tokens[0];
-        while let Token::Add = t {
-            tokens.pop_front();
-            let rhs = parse_item(tokens);
-            if let Token::Add = t {
-                result = result + rhs;
+    // Span just past the last token, for errors at the end of the line.
+    fn end(&self) -> Span {
+        let end = self.tokens.last().map_or(0, |t| t.span.end);
+        Span { start: end, end }
+    }
+
+    fn operand(&mut self) -> Result<Expr, ParseError> {
+        let t = match self.next() {
+            Some(t) => t,
+            None => return Err(ParseError::ExpectedOperand(self.end())),
+        };
+        match t.kind {
+            TokenKind::Num(n) => Ok(Expr::Num(n, t.span)),
+            TokenKind::Open => {
+                let mut expr = self.expr(0)?;
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::Close,
+                        span: close,
+                    }) => {
+                        // Parentheses only group, but errors should point at them too.
+                        match &mut expr {
+                            Expr::Num(_, span) | Expr::Binary { span, .. } => {
+                                *span = t.span.to(close)
+                            }
+                        }
+                        Ok(expr)
+                    }
+                    Some(t) => Err(ParseError::ExpectedOperator(t.span)),
+                    None => Err(ParseError::UnclosedParen(t.span)),
+                }
             }
-            t = tokens[0];
+            _ => Err(ParseError::ExpectedOperand(t.span)),
         }
-        result
     }
 
-    pub fn parse_expression(tokens: &mut VecDeque<Token>) -> u64 {
-        let mut result = parse_term(tokens);
-        let mut t = tokens[0];
-        while let Token::Mul = t {
-            tokens.pop_front();
-            let rhs = parse_term(tokens);
-            if let Token::Mul = t {
-                result = result * rhs;
+    // Parses an expression whose operators all bind at least as tightly as `min_power`.
+    fn expr(&mut self, min_power: u8) -> Result<Expr, ParseError> {
+        let mut lhs = self.operand()?;
+        while let Some(t) = self.peek() {
+            let op = match t.kind {
+                TokenKind::Op(op) => op,
+                _ => break,
+            };
+            let (power, assoc) = self
+                .table
+                .iter()
+                .find(|(o, ..)| *o == op)
+                .map(|(_, p, a)| (*p, *a))
+                .ok_or(ParseError::UnsupportedOperator(t.span, op))?;
+            if power < min_power {
+                break;
             }
-            t = tokens[0];
+            self.pos += 1;
+            let rhs = match assoc {
+                Assoc::Left => self.expr(power + 1)?,
+                Assoc::Right => self.expr(power)?,
+            };
+            let span = lhs.span().to(rhs.span());
+            lhs = Expr::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                span,
+            };
         }
-        result
+        Ok(lhs)
     }
 }
 
-fn parse_part2(tokens: &[Token]) -> u64 {
-    let mut vd: VecDeque<Token> = tokens.into_iter().cloned().collect();
-    part2::parse_expression(&mut vd)
+#[aoc_generator(day18)]
+fn generator(input: &str) -> Vec<Vec<Token>> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, l)| lex(l).unwrap_or_else(|e| panic!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+fn solve(lines: &[Vec<Token>], table: &Precedence) -> i64 {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, tokens)| {
+            let expr =
+                Expr::parse(tokens, table).unwrap_or_else(|e| panic!("line {}: {}", i + 1, e));
+            expr.eval()
+                .unwrap_or_else(|e| panic!("line {}: {}", i + 1, e))
+        })
+        .sum()
+}
+
+#[aoc(day18, part1)]
+fn solution1(lines: &[Vec<Token>]) -> i64 {
+    solve(lines, SAME_PRECEDENCE)
 }
 
 #[aoc(day18, part2)]
-fn solution2(tokens_list: &[Vec<Token>]) -> u64 {
-    tokens_list.iter().map(|tokens| parse_part2(tokens)).sum()
+fn solution2(lines: &[Vec<Token>]) -> i64 {
+    solve(lines, ADDITION_FIRST)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn eval(input: &str, table: &Precedence) -> i64 {
+        Expr::parse(&lex(input).unwrap(), table)
+            .unwrap()
+            .eval()
+            .unwrap()
+    }
+
     #[test]
     fn test_lex() {
-        let tokens = vec![
-            Token::Num(2),
-            Token::Mul,
-            Token::Num(3),
-            Token::Add,
-            Token::Open,
-            Token::Num(4),
-            Token::Mul,
-            Token::Num(5),
-            Token::Close,
-            Token::Stop,
+        let kinds: Vec<_> = lex("12 * 3 - (4 / 56)")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Num(12),
+                TokenKind::Op(Op::Mul),
+                TokenKind::Num(3),
+                TokenKind::Op(Op::Sub),
+                TokenKind::Open,
+                TokenKind::Num(4),
+                TokenKind::Op(Op::Div),
+                TokenKind::Num(56),
+                TokenKind::Close,
+            ]
+        );
+        assert_eq!(lex("12").unwrap()[0].span, Span { start: 0, end: 2 });
+        assert_eq!(
+            lex("1 + x"),
+            Err(ParseError::UnexpectedChar(Span { start: 4, end: 5 }, 'x'))
+        );
+        assert!(matches!(
+            lex("99999999999999999999"),
+            Err(ParseError::NumberTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let parse = |s| Expr::parse(&lex(s).unwrap(), SAME_PRECEDENCE);
+        assert_eq!(
+            parse("1 +"),
+            Err(ParseError::ExpectedOperand(Span { start: 3, end: 3 }))
+        );
+        assert_eq!(
+            parse("(1 + 2"),
+            Err(ParseError::UnclosedParen(Span { start: 0, end: 1 }))
+        );
+        assert_eq!(
+            parse("1 + 2) * 3"),
+            Err(ParseError::ExpectedOperator(Span { start: 5, end: 6 }))
+        );
+        assert_eq!(
+            parse("1 2"),
+            Err(ParseError::ExpectedOperator(Span { start: 2, end: 3 }))
+        );
+        assert_eq!(
+            parse("* 2"),
+            Err(ParseError::ExpectedOperand(Span { start: 0, end: 1 }))
+        );
+        let add_only = &[(Op::Add, 1, Assoc::Left)];
+        assert_eq!(
+            Expr::parse(&lex("1 + 2 * 3").unwrap(), add_only),
+            Err(ParseError::UnsupportedOperator(
+                Span { start: 6, end: 7 },
+                Op::Mul
+            ))
+        );
+    }
+
+    #[test]
+    fn precedence_table() {
+        let ast = |s, table| Expr::parse(&lex(s).unwrap(), table).unwrap().to_string();
+        assert_eq!(ast("1 + 2 * 3 + 4", SAME_PRECEDENCE), "(((1 + 2) * 3) + 4)");
+        assert_eq!(ast("1 + 2 * 3 + 4", ADDITION_FIRST), "((1 + 2) * (3 + 4))");
+        let standard = &[
+            (Op::Add, 1, Assoc::Left),
+            (Op::Sub, 1, Assoc::Left),
+            (Op::Mul, 2, Assoc::Left),
+            (Op::Div, 2, Assoc::Left),
         ];
-        assert_eq!(lex("2 * 3 + (4 * 5)"), tokens);
+        assert_eq!(eval("2 + 3 * 4 - 10 / 5", standard), 12);
+        let right = &[(Op::Sub, 1, Assoc::Right)];
+        assert_eq!(ast("8 - 4 - 2", right), "(8 - (4 - 2))");
+        assert_eq!(eval("8 - 4 - 2", right), 6);
+        assert_eq!(eval("8 - 4 - 2", SAME_PRECEDENCE), 2);
+        assert_eq!(eval("100 / 7 / 2", SAME_PRECEDENCE), 7);
+    }
+
+    #[test]
+    fn eval_errors() {
+        let eval = |s| {
+            Expr::parse(&lex(s).unwrap(), SAME_PRECEDENCE)
+                .unwrap()
+                .eval()
+        };
+        assert_eq!(
+            eval("1 + (4 / (2 - 2))"),
+            Err(EvalError::DivisionByZero(Span { start: 4, end: 17 }))
+        );
+        assert_eq!(
+            eval("9223372036854775807 + 1"),
+            Err(EvalError::Overflow(Span { start: 0, end: 23 }, Op::Add))
+        );
     }
 
     #[test]
     fn part1() {
-        for (input, want) in vec![
+        for (input, want) in [
             ("1 + 2 * 3", 9),
             ("(1 + 2) * 3", 9),
             ("((1 + 2) * 3)", 9),
@@ -247,7 +503,7 @@ mod tests {
             ("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))", 12240),
             ("((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2", 13632),
         ] {
-            let got = parse_part1(&lex(input));
+            let got = eval(input, SAME_PRECEDENCE);
             assert_eq!(got, want, "got {}, want {} for {}", got, want, input);
         }
         let input = r#"1 + 2 * 3 + 4 * 5 + 6
@@ -259,14 +515,14 @@ mod tests {
     }
     #[test]
     fn part2() {
-        for (input, want) in vec![
+        for (input, want) in [
             ("1 + (2 * 3) + (4 * (5 + 6))", 51),
             ("2 * 3 + (4 * 5)", 46),
             ("5 + (8 * 3 + 9 + 3 * 4 * 3)", 1445),
             ("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))", 669060),
-            ("((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2)", 23340),
+            ("((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2", 23340),
         ] {
-            let got = parse_part2(&lex(input));
+            let got = eval(input, ADDITION_FIRST);
             assert_eq!(got, want, "got {}, want {} for {}", got, want, input);
         }
     }
@@ -647,9 +903,9 @@ mod tests {
             ("8 * 7 * (9 * 2 * 4 + 3 + 4 * 4) + (8 * 8 + 9 + 6) + ((7 * 7 + 2 * 9) * (2 * 8 + 2) * 6 * 6) + 8", 315215),
             ];
         // Find shortest failures first, that should make it easier to understand.
-        ts.sort_by(|l, r| l.0.len().cmp(&r.0.len()));
+        ts.sort_by_key(|t| t.0.len());
         for (input, want) in ts {
-            let got = parse_part1(&lex(input));
+            let got = eval(input, SAME_PRECEDENCE);
             assert_eq!(got, want, "got {}, want {} for {}", got, want, input);
         }
     }
@@ -1031,9 +1287,9 @@ mod tests {
             ("8 * 7 * (9 * 2 * 4 + 3 + 4 * 4) + (8 * 8 + 9 + 6) + ((7 * 7 + 2 * 9) * (2 * 8 + 2) * 6 * 6) + 8", 22916544),
             ];
         // Find shortest failures first, that should make it easier to understand.
-        ts.sort_by(|l, r| l.0.len().cmp(&r.0.len()));
+        ts.sort_by_key(|t| t.0.len());
         for (input, want) in ts {
-            let got = parse_part2(&lex(input));
+            let got = eval(input, ADDITION_FIRST);
             assert_eq!(got, want, "got {}, want {} for {}", got, want, input);
         }
     }
@@ -127,23 +127,20 @@
 //! aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba
 //! After updating rules 8 and 11, how many messages completely match rule 0?
 
-use std::{collections::HashMap, fmt};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
 
 use aoc_runner_derive::{aoc, aoc_generator};
-use regex::Regex;
+use thiserror::Error;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 struct Input {
-    rules: Regex,
+    rules: Grammar,
     messages: Vec<String>,
 }
 
-impl PartialEq for Input {
-    fn eq(&self, other: &Self) -> bool {
-        self.rules.as_str() == other.rules.as_str() && self.messages == other.messages
-    }
-}
-
 #[derive(Clone, Eq, PartialEq, Hash)]
 enum Entry {
     Rule(usize),
@@ -159,78 +156,249 @@ impl fmt::Debug for Entry {
     }
 }
 
-struct Resolver {
-    rule_map: HashMap<usize, Vec<Vec<Entry>>>,
-    resolved: HashMap<Entry, String>,
+#[derive(Debug, Error, PartialEq)]
+enum GrammarError {
+    #[error("rule {0} references undefined rule {1}")]
+    UndefinedRule(usize, usize),
+}
+
+/// How a message was derived from a rule.
+#[derive(Clone, Debug, PartialEq)]
+enum ParseTree {
+    Rule {
+        rule: usize,
+        /// Index of the alternative that matched.
+        alt: usize,
+        children: Vec<ParseTree>,
+    },
+    Char(String),
 }
 
-impl Resolver {
-    fn resolve(&mut self, e: &Entry) -> String {
-        assert_ne!(e, &Entry::Rule(0));
-        if let Some(v) = self.resolved.get(e) {
-            return v.to_string();
+impl fmt::Display for ParseTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseTree::Char(s) => write!(f, "{:?}", s),
+            ParseTree::Rule { rule, children, .. } => {
+                write!(f, "({}", rule)?;
+                for c in children {
+                    write!(f, " {}", c)?;
+                }
+                write!(f, ")")
+            }
         }
+    }
+}
 
-        match e {
-            Entry::Char(s) => {
-                self.resolved.insert(e.clone(), s.to_string());
-                s.to_string()
+/// Context free grammar over the puzzle's rules, matched with an Earley parser so any recursion,
+/// including left recursion, is supported.
+#[derive(Debug, PartialEq)]
+struct Grammar {
+    rules: HashMap<usize, Vec<Vec<Entry>>>,
+    // Rules that can match the empty string.
+    nullable: HashSet<usize>,
+}
+
+// A partially matched alternative: `rules[rule][alt][..dot]` matched the input starting at
+// `origin`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Item {
+    rule: usize,
+    alt: usize,
+    dot: usize,
+    origin: usize,
+}
+
+// Completed matches of the Earley parse, as (rule, start, end).
+type Spans = HashSet<(usize, usize, usize)>;
+
+impl Grammar {
+    fn new(rules: HashMap<usize, Vec<Vec<Entry>>>) -> Result<Grammar, GrammarError> {
+        for (k, alts) in &rules {
+            for e in alts.iter().flatten() {
+                if let Entry::Rule(n) = e {
+                    if !rules.contains_key(n) {
+                        return Err(GrammarError::UndefinedRule(*k, *n));
+                    }
+                }
             }
-            Entry::Rule(n) => {
-                let subrules: Vec<String> = self.rule_map[n]
-                    // TODO(wathiede): clone here seems inefficient, but it made the borrow checker
-                    // happy given the &mut recursive call to self.resolve in the map closure.
-                    .clone()
-                    .iter()
-                    .map(|rule| {
-                        rule.iter()
-                            .map(|e| self.resolve(e))
-                            .collect::<Vec<_>>()
-                            .join("")
+        }
+        let mut nullable = HashSet::new();
+        loop {
+            let before = nullable.len();
+            for (k, alts) in &rules {
+                let empty = alts.iter().any(|alt| {
+                    alt.iter().all(|e| match e {
+                        Entry::Rule(n) => nullable.contains(n),
+                        Entry::Char(s) => s.is_empty(),
                     })
-                    .collect();
-                let s = subrules.join("|");
+                });
+                if empty {
+                    nullable.insert(*k);
+                }
+            }
+            if nullable.len() == before {
+                break;
+            }
+        }
+        Ok(Grammar { rules, nullable })
+    }
 
-                if subrules.len() > 1 {
-                    // More than one rule,
-                    return format!("({})", s);
+    /// Returns true if all of `msg` matches rule `start`.
+    fn matches(&self, start: usize, msg: &str) -> bool {
+        self.earley(start, msg).contains(&(start, 0, msg.len()))
+    }
+
+    /// Returns one derivation of `msg` from rule `start`, if it matches.
+    fn parse(&self, start: usize, msg: &str) -> Option<ParseTree> {
+        let spans = self.earley(start, msg);
+        self.tree(&spans, msg, start, 0, msg.len(), &mut HashSet::new())
+    }
+
+    fn earley(&self, start: usize, msg: &str) -> Spans {
+        let n = msg.len();
+        let mut chart: Vec<Vec<Item>> = vec![Vec::new(); n + 1];
+        let mut seen: Vec<HashSet<Item>> = vec![HashSet::new(); n + 1];
+        let mut spans = HashSet::new();
+        let mut add = |chart: &mut Vec<Vec<Item>>, pos: usize, item: Item| {
+            if seen[pos].insert(item) {
+                chart[pos].push(item);
+            }
+        };
+        for alt in 0..self.rules[&start].len() {
+            let item = Item {
+                rule: start,
+                alt,
+                dot: 0,
+                origin: 0,
+            };
+            add(&mut chart, 0, item);
+        }
+        for pos in 0..=n {
+            let mut i = 0;
+            while i < chart[pos].len() {
+                let item = chart[pos][i];
+                i += 1;
+                let advanced = Item {
+                    dot: item.dot + 1,
+                    ..item
+                };
+                match self.rules[&item.rule][item.alt].get(item.dot) {
+                    // Complete: advance everything that was waiting on this rule.
+                    None => {
+                        spans.insert((item.rule, item.origin, pos));
+                        let waiting: Vec<Item> = chart[item.origin]
+                            .iter()
+                            .filter(|w| {
+                                self.rules[&w.rule][w.alt].get(w.dot)
+                                    == Some(&Entry::Rule(item.rule))
+                            })
+                            .copied()
+                            .collect();
+                        for w in waiting {
+                            add(
+                                &mut chart,
+                                pos,
+                                Item {
+                                    dot: w.dot + 1,
+                                    ..w
+                                },
+                            );
+                        }
+                    }
+                    // Predict: start matching every alternative of the rule here.
+                    Some(Entry::Rule(r)) => {
+                        for alt in 0..self.rules[r].len() {
+                            let predicted = Item {
+                                rule: *r,
+                                alt,
+                                dot: 0,
+                                origin: pos,
+                            };
+                            add(&mut chart, pos, predicted);
+                        }
+                        // Completing an empty match wouldn't revisit items already processed.
+                        if self.nullable.contains(r) {
+                            spans.insert((*r, pos, pos));
+                            add(&mut chart, pos, advanced);
+                        }
+                    }
+                    // Scan
+                    Some(Entry::Char(s)) => {
+                        if msg[pos..].starts_with(s.as_str()) {
+                            add(&mut chart, pos + s.len(), advanced);
+                        }
+                    }
                 }
-                s
             }
         }
+        spans
     }
-}
 
-fn expand_rulemap(rule_map: HashMap<usize, Vec<Vec<Entry>>>) -> Regex {
-    // Hack
-    let part2 = rule_map.len() > 8 && rule_map[&8].len() > 1;
-    let mut r = Resolver {
-        rule_map,
-        resolved: HashMap::new(),
-    };
-    let re = if part2 {
-        let hack = (1..10_usize)
-            .map(|i| {
-                format!(
-                    "{}{}",
-                    r.resolve(&Entry::Rule(42)).repeat(i),
-                    r.resolve(&Entry::Rule(31)).repeat(i)
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("|");
-        format!("({})+({})", r.resolve(&Entry::Rule(42)), hack)
-    } else {
-        let rule_zero = r.rule_map[&0][0].clone();
-        rule_zero
+    // Rebuilds a derivation of `msg[start..end]` from `rule` using the completed spans. `active`
+    // guards against cycles like `1: 1 | "a"`.
+    fn tree(
+        &self,
+        spans: &Spans,
+        msg: &str,
+        rule: usize,
+        start: usize,
+        end: usize,
+        active: &mut HashSet<(usize, usize, usize)>,
+    ) -> Option<ParseTree> {
+        if !spans.contains(&(rule, start, end)) || !active.insert((rule, start, end)) {
+            return None;
+        }
+        let res = self.rules[&rule]
             .iter()
-            .fold("".to_string(), |acc, e| format!("{}{}", acc, r.resolve(e)))
-    };
+            .enumerate()
+            .find_map(|(alt, entries)| {
+                let children = self.children(spans, msg, entries, start, end, active)?;
+                Some(ParseTree::Rule {
+                    rule,
+                    alt,
+                    children,
+                })
+            });
+        active.remove(&(rule, start, end));
+        res
+    }
 
-    Regex::new(&format!(r"^{}$", re)).unwrap()
+    fn children(
+        &self,
+        spans: &Spans,
+        msg: &str,
+        entries: &[Entry],
+        start: usize,
+        end: usize,
+        active: &mut HashSet<(usize, usize, usize)>,
+    ) -> Option<Vec<ParseTree>> {
+        let (first, rest) = match entries.split_first() {
+            Some(split) => split,
+            None => return (start == end).then(Vec::new),
+        };
+        let (child, mid) = match first {
+            Entry::Char(s) => {
+                if !msg[start..end].starts_with(s.as_str()) {
+                    return None;
+                }
+                (ParseTree::Char(s.clone()), start + s.len())
+            }
+            Entry::Rule(r) => {
+                return (start..=end).find_map(|mid| {
+                    let child = self.tree(spans, msg, *r, start, mid, active)?;
+                    let mut res = vec![child];
+                    res.extend(self.children(spans, msg, rest, mid, end, active)?);
+                    Some(res)
+                })
+            }
+        };
+        let mut res = vec![child];
+        res.extend(self.children(spans, msg, rest, mid, end, active)?);
+        Some(res)
+    }
 }
 
-fn make_rules(lines: Vec<String>) -> Regex {
+fn make_rules(lines: Vec<String>) -> HashMap<usize, Vec<Vec<Entry>>> {
     let mut rules = HashMap::new();
 
     lines.iter().for_each(|l| {
@@ -250,19 +418,20 @@ fn make_rules(lines: Vec<String>) -> Regex {
             .collect();
         rules.insert(k, sub);
     });
-    expand_rulemap(rules)
+    rules
 }
 
-#[aoc_generator(day19, part1)]
-fn generator_part1(input: &str) -> Input {
+fn parse_input(input: &str, patch: impl Fn(&mut HashMap<usize, Vec<Vec<Entry>>>)) -> Input {
     let mut it = input.split("\n\n");
-    let rules = make_rules(
+    let mut rules = make_rules(
         it.next()
             .unwrap()
             .split('\n')
             .map(|s| s.trim().to_string())
             .collect(),
     );
+    patch(&mut rules);
+    let rules = Grammar::new(rules).expect("invalid rules");
 
     let messages = it
         .next()
@@ -273,43 +442,32 @@ fn generator_part1(input: &str) -> Input {
     Input { rules, messages }
 }
 
+#[aoc_generator(day19, part1)]
+fn generator_part1(input: &str) -> Input {
+    parse_input(input, |_| {})
+}
+
 #[aoc(day19, part1)]
 fn solution1(input: &Input) -> usize {
     input
         .messages
         .iter()
-        .filter(|msg| input.rules.is_match(msg))
+        .filter(|msg| input.rules.matches(0, msg))
         .count()
 }
 
 #[aoc_generator(day19, part2)]
 fn generator_part2(input: &str) -> Input {
-    let mut it = input.split("\n\n");
-    let rules = make_rules(
-        it.next()
-            .unwrap()
-            .split('\n')
-            .map(|s| s.trim())
-            .map(|s| {
-                if s.starts_with("8:") {
-                    return "8: 42 | 42 8";
-                }
-                if s.starts_with("11:") {
-                    return "11: 42 31 | 42 11 31";
-                }
-                s
-            })
-            .map(|s| s.to_string())
-            .collect(),
-    );
-
-    let messages = it
-        .next()
-        .unwrap()
-        .split('\n')
-        .map(|s| s.trim().to_string())
-        .collect();
-    Input { rules, messages }
+    use Entry::Rule;
+    parse_input(input, |rules| {
+        // 8: 42 | 42 8
+        rules.insert(8, vec![vec![Rule(42)], vec![Rule(42), Rule(8)]]);
+        // 11: 42 31 | 42 11 31
+        rules.insert(
+            11,
+            vec![vec![Rule(42), Rule(31)], vec![Rule(42), Rule(11), Rule(31)]],
+        );
+    })
 }
 
 #[aoc(day19, part2)]
@@ -317,7 +475,7 @@ fn solution2(input: &Input) -> usize {
     input
         .messages
         .iter()
-        .filter(|msg| input.rules.is_match(msg))
+        .filter_map(|msg| input.rules.parse(0, msg))
         .count()
 }
 
@@ -326,7 +484,7 @@ mod tests {
     use pretty_assertions::assert_eq;
 
     use super::*;
-    const INPUT1: &'static str = r#"0: 4 1 5
+    const INPUT1: &str = r#"0: 4 1 5
         1: 2 3 | 3 2
         2: 4 4 | 5 5
         3: 4 5 | 5 4
@@ -339,6 +497,13 @@ mod tests {
         aaabbb
         aaaabbb"#;
 
+    fn grammar(lines: &str) -> Grammar {
+        Grammar::new(make_rules(
+            lines.lines().map(|l| l.trim().to_string()).collect(),
+        ))
+        .unwrap()
+    }
+
     #[test]
     fn part1() {
         assert_eq!(solution1(&generator_part1(INPUT1)), 2);
@@ -346,19 +511,23 @@ mod tests {
 
     #[test]
     fn parse1() {
+        let input = generator_part1(INPUT1);
+        assert_eq!(input.rules.rules.len(), 6);
         assert_eq!(
-            generator_part1(INPUT1),
-            Input {
-                rules: Regex::new("^a((aa|bb)(ab|ba)|(ab|ba)(aa|bb))b$").unwrap(),
-                messages: vec!["ababbb", "bababa", "abbbab", "aaabbb", "aaaabbb",]
-                    .into_iter()
-                    .map(|s| s.to_string())
-                    .collect(),
-            }
+            input.rules.rules[&1],
+            vec![
+                vec![Entry::Rule(2), Entry::Rule(3)],
+                vec![Entry::Rule(3), Entry::Rule(2)]
+            ]
+        );
+        assert_eq!(
+            input.messages,
+            vec!["ababbb", "bababa", "abbbab", "aaabbb", "aaaabbb"]
         );
     }
+
     #[test]
-    fn expand() {
+    fn matches() {
         use Entry::*;
         let r: HashMap<usize, Vec<Vec<Entry>>> = vec![
             vec![vec![Rule(1)]],
@@ -369,12 +538,63 @@ mod tests {
         .into_iter()
         .enumerate()
         .collect();
+        let g = Grammar::new(r).unwrap();
+        assert!(g.matches(0, "aa"));
+        assert!(g.matches(0, "bb"));
+        assert!(!g.matches(0, "ab"));
+        assert!(!g.matches(0, "aab"));
+        assert!(!g.matches(0, ""));
+    }
+
+    #[test]
+    fn undefined_rule() {
+        let r = make_rules(vec!["0: 1 2".to_string(), r#"1: "a""#.to_string()]);
+        assert_eq!(Grammar::new(r), Err(GrammarError::UndefinedRule(0, 2)));
+    }
+
+    #[test]
+    fn recursion() {
+        // Left recursive, and nested deeper than a fixed unrolling would handle.
+        let g = grammar(
+            r#"0: 0 1 | 1
+            1: "a"
+            2: 1 3 | 1 2 3
+            3: "b""#,
+        );
+        assert!(g.matches(0, "a"));
+        assert!(g.matches(0, &"a".repeat(50)));
+        assert!(!g.matches(0, "ab"));
+        assert!(g.matches(2, &format!("{}{}", "a".repeat(20), "b".repeat(20))));
+        assert!(!g.matches(2, &format!("{}{}", "a".repeat(20), "b".repeat(19))));
+        // Empty alternatives and a rule that derives itself.
+        let mut r = make_rules(vec![
+            r#"0: 1 0 | 1 | 2"#.to_string(),
+            r#"1: "a""#.to_string(),
+        ]);
+        r.insert(2, vec![vec![]]);
+        let g = Grammar::new(r).unwrap();
+        assert!(g.matches(0, ""));
+        assert!(g.matches(0, "aaa"));
+        let g = grammar(
+            r#"0: 0 | 1
+            1: "a""#,
+        );
+        assert!(g.matches(0, "a"));
+        assert!(g.parse(0, "a").is_some());
+    }
+
+    #[test]
+    fn parse_tree() {
+        let input = generator_part1(INPUT1);
+        let tree = input.rules.parse(0, "ababbb").unwrap();
         assert_eq!(
-            expand_rulemap(r).as_str(),
-            Regex::new("^(aa|bb)$").unwrap().as_str()
+            tree.to_string(),
+            r#"(0 (4 "a") (1 (3 (5 "b") (4 "a")) (2 (5 "b") (5 "b"))) (5 "b"))"#
         );
+        assert_eq!(input.rules.parse(0, "bababa"), None);
     }
-    const INPUT2: &'static str = r#"42: 9 14 | 10 1
+
+    const INPUT2: &str = r#"42: 9 14 | 10 1
 9: 14 27 | 1 26
 10: 23 14 | 28 1
 1: "a"
@@ -429,7 +649,7 @@ aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba"#;
             input
                 .messages
                 .iter()
-                .filter(|msg| input.rules.is_match(msg))
+                .filter(|msg| input.rules.matches(0, msg))
                 .collect::<Vec<_>>(),
             vec![
                 "bbabbbbaabaabba",
@@ -448,6 +668,11 @@ aabbbbbaabbbaaaaaabbbbbababaaaaabbaaabba"#;
         );
     }
 
+    #[test]
+    fn part1_with_recursive_rules() {
+        assert_eq!(solution1(&generator_part1(INPUT2)), 3);
+    }
+
     #[test]
     fn part2() {
         assert_eq!(solution2(&generator_part2(INPUT2)), 12);
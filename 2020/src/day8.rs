@@ -75,129 +75,29 @@
 //!
 //! Fix the program so that it terminates normally by changing exactly one jmp (to nop) or nop (to jmp). What is the value of the accumulator after the program terminates?
 
-use std::str::FromStr;
-
 use aoc_runner_derive::aoc;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-enum Instruction {
-    Nop(i32),
-    Acc(i32),
-    Jmp(i32),
-}
-impl FromStr for Instruction {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut it = s.split(' ');
-        Ok(match it.next() {
-            Some("nop") => Instruction::Nop(it.next().ok_or(())?.parse().map_err(|_| ())?),
-            Some("acc") => Instruction::Acc(it.next().ok_or(())?.parse().map_err(|_| ())?),
-            Some("jmp") => Instruction::Jmp(it.next().ok_or(())?.parse().map_err(|_| ())?),
-            Some(c) => panic!("unknown instruction '{}'", c),
-            None => panic!("no space in '{}'", s),
-        })
-    }
-}
-
-#[derive(Default, Debug, PartialEq)]
-struct Program {
-    ip: usize,
-    acc: i32,
-    intrs: Vec<Instruction>,
-    executed: Vec<bool>,
-}
-
-impl Program {
-    fn reset(&mut self) {
-        self.ip = 0;
-        self.acc = 0;
-        self.executed.iter_mut().for_each(|i| *i = false);
-    }
-    fn debug(&mut self, bad_ip: usize) -> Option<i32> {
-        loop {
-            if self.executed[self.ip] {
-                return None;
-            }
-            self.executed[self.ip] = true;
-            let intr = self.intrs[self.ip];
-            let intr = if self.ip == bad_ip {
-                match intr {
-                    // Swap instruction as this is a possible bug location.
-                    Instruction::Nop(op) => Instruction::Jmp(op),
-                    Instruction::Jmp(op) => Instruction::Nop(op),
-                    // Acc can't be buggy per the instructions.
-                    Instruction::Acc(_) => return None,
-                }
-            } else {
-                intr
-            };
-            match intr {
-                Instruction::Nop(_) => self.ip += 1,
-                Instruction::Acc(op) => {
-                    self.acc += op;
-                    self.ip += 1;
-                }
-                Instruction::Jmp(op) => self.ip = (self.ip as i32 + op) as usize,
-            }
-            if self.ip >= self.intrs.len() {
-                return Some(self.acc);
-            }
-        }
-    }
-
-    fn run(&mut self) -> i32 {
-        loop {
-            if self.executed[self.ip] {
-                return self.acc;
-            }
-            self.executed[self.ip] = true;
-            match self.intrs[self.ip] {
-                Instruction::Nop(_) => self.ip += 1,
-                Instruction::Acc(op) => {
-                    self.acc += op;
-                    self.ip += 1;
-                }
-                Instruction::Jmp(op) => self.ip = (self.ip as i32 + op) as usize,
-            }
-        }
-    }
-}
-
-impl FromStr for Program {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let intrs: Vec<_> = s.split('\n').filter_map(|i| i.parse().ok()).collect();
-        let executed = vec![false; intrs.len()];
-        Ok(Program {
-            ip: 0,
-            acc: 0,
-            intrs,
-            executed,
-        })
-    }
-}
+use crate::handheld::{repair, Halt, Program, Vm};
 
 #[aoc(day8, part1)]
-fn solution1(input: &str) -> i32 {
-    let mut p: Program = input.parse().expect("Failed to parse Program");
-    p.run()
+fn solution1(input: &str) -> i64 {
+    let p: Program = input.parse().expect("Failed to parse Program");
+    match Vm::new(&p).run() {
+        Halt::Loop(report) => report.registers.acc,
+        h => panic!("expected an infinite loop, got {:?}", h),
+    }
 }
 
 #[aoc(day8, part2)]
-fn solution2(input: &str) -> i32 {
-    let mut p: Program = input.parse().expect("Failed to parse Program");
-    for bad_ip in 0..p.intrs.len() {
-        if let Some(acc) = p.debug(bad_ip) {
-            return acc;
-        }
-        p.reset();
-    }
-    panic!("no bugfix found")
+fn solution2(input: &str) -> i64 {
+    let p: Program = input.parse().expect("Failed to parse Program");
+    repair(&p).expect("no bugfix found").registers.acc
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::handheld::Instruction;
 
     const INPUT1: &'static str = r#"nop +0
 acc +1
@@ -214,9 +114,7 @@ acc +6"#;
         assert_eq!(
             INPUT1.parse::<Program>().expect("Failed to parse input"),
             Program {
-                ip: 0,
-                acc: 0,
-                intrs: vec![
+                instructions: vec![
                     Instruction::Nop(0),
                     Instruction::Acc(1),
                     Instruction::Jmp(4),
@@ -227,7 +125,6 @@ acc +6"#;
                     Instruction::Jmp(-4),
                     Instruction::Acc(6),
                 ],
-                executed: vec![false; 9],
             }
         );
     }
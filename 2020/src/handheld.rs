@@ -0,0 +1,395 @@
+//! Virtual machine for the handheld game console's boot code, see day 8.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Instruction {
+    Nop(i64),
+    Acc(i64),
+    Jmp(i64),
+}
+
+impl Instruction {
+    /// Swaps `jmp` and `nop`, the only kind of corruption boot code can have. Returns `None` for
+    /// `acc`.
+    pub fn patched(self) -> Option<Instruction> {
+        match self {
+            Instruction::Nop(arg) => Some(Instruction::Jmp(arg)),
+            Instruction::Jmp(arg) => Some(Instruction::Nop(arg)),
+            Instruction::Acc(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Nop(arg) => write!(f, "nop {arg:+}"),
+            Instruction::Acc(arg) => write!(f, "acc {arg:+}"),
+            Instruction::Jmp(arg) => write!(f, "jmp {arg:+}"),
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ParseError {
+    #[error("line {line}: unknown opcode '{op}'")]
+    UnknownOpcode { line: usize, op: String },
+    #[error("line {line}: missing argument")]
+    MissingArgument { line: usize },
+    #[error("line {line}: couldn't parse argument '{arg}': {err}")]
+    BadArgument {
+        line: usize,
+        arg: String,
+        err: ParseIntError,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Program {
+    pub instructions: Vec<Instruction>,
+}
+
+impl Program {
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+    /// Returns a copy with the instruction at `ip` patched, see [`Instruction::patched`].
+    pub fn patched(&self, ip: usize) -> Option<Program> {
+        let mut p = self.clone();
+        p.instructions[ip] = p.instructions[ip].patched()?;
+        Some(p)
+    }
+}
+
+impl FromStr for Program {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let instructions = s
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.is_empty())
+            .map(|(i, l)| {
+                let line = i + 1;
+                let mut it = l.split(' ');
+                let op = it.next().unwrap_or_default();
+                let arg = it.next().ok_or(ParseError::MissingArgument { line })?;
+                let arg = arg.parse().map_err(|err| ParseError::BadArgument {
+                    line,
+                    arg: arg.to_string(),
+                    err,
+                })?;
+                Ok(match op {
+                    "nop" => Instruction::Nop(arg),
+                    "acc" => Instruction::Acc(arg),
+                    "jmp" => Instruction::Jmp(arg),
+                    op => {
+                        return Err(ParseError::UnknownOpcode {
+                            line,
+                            op: op.to_string(),
+                        })
+                    }
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Program { instructions })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Registers {
+    /// Signed so jumps before the start of the program can be reported.
+    pub ip: i64,
+    pub acc: i64,
+}
+
+/// One executed instruction and the registers after it ran.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Step {
+    pub ip: usize,
+    pub instruction: Instruction,
+    pub registers: Registers,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:4}: {} | ip {} acc {}",
+            self.ip, self.instruction, self.registers.ip, self.registers.acc
+        )
+    }
+}
+
+/// Condition checked before each instruction executes.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Breakpoint {
+    Ip(usize),
+    AccEq(i64),
+    AccGt(i64),
+    AccLt(i64),
+}
+
+impl Breakpoint {
+    fn hit(&self, r: &Registers) -> bool {
+        match self {
+            Breakpoint::Ip(ip) => r.ip == *ip as i64,
+            Breakpoint::AccEq(v) => r.acc == *v,
+            Breakpoint::AccGt(v) => r.acc > *v,
+            Breakpoint::AccLt(v) => r.acc < *v,
+        }
+    }
+}
+
+/// Instructions that would repeat forever, starting with the one about to run a second time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoopReport {
+    /// Registers immediately before the first repeated instruction.
+    pub registers: Registers,
+    pub cycle: Vec<(usize, Instruction)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Halt {
+    /// Tried to run the instruction immediately after the last one.
+    Terminated(Registers),
+    /// Jumped somewhere other than the end of the program.
+    OutOfBounds(Registers),
+    Loop(LoopReport),
+    /// Stopped before running the instruction at `registers.ip`, calling `run` again resumes.
+    Breakpoint(Breakpoint, Registers),
+}
+
+#[derive(Clone, Debug)]
+pub struct Vm<'a> {
+    program: &'a Program,
+    registers: Registers,
+    // Position in `history` of the first time each instruction ran.
+    first_run: Vec<Option<usize>>,
+    history: Vec<usize>,
+    breakpoints: Vec<Breakpoint>,
+    // Set when stopped at a breakpoint, so resuming doesn't stop at it again immediately.
+    resuming: bool,
+    trace: Option<Vec<Step>>,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(program: &'a Program) -> Vm<'a> {
+        Vm {
+            program,
+            registers: Registers::default(),
+            first_run: vec![None; program.len()],
+            history: Vec::new(),
+            breakpoints: Vec::new(),
+            resuming: false,
+            trace: None,
+        }
+    }
+    /// Records every executed step, see [`Vm::trace`].
+    pub fn with_tracing(mut self) -> Self {
+        self.trace = Some(Vec::new());
+        self
+    }
+    pub fn add_breakpoint(&mut self, bp: Breakpoint) {
+        self.breakpoints.push(bp);
+    }
+    pub fn registers(&self) -> Registers {
+        self.registers
+    }
+    /// Steps executed so far, empty unless tracing was enabled.
+    pub fn trace(&self) -> &[Step] {
+        self.trace.as_deref().unwrap_or_default()
+    }
+    pub fn reset(&mut self) {
+        self.registers = Registers::default();
+        self.first_run.iter_mut().for_each(|r| *r = None);
+        self.history.clear();
+        self.resuming = false;
+        if let Some(t) = &mut self.trace {
+            t.clear();
+        }
+    }
+    fn current_ip(&self) -> Option<usize> {
+        usize::try_from(self.registers.ip)
+            .ok()
+            .filter(|ip| *ip < self.program.len())
+    }
+    /// Executes a single instruction, or returns `None` if the instruction pointer is outside
+    /// the program.
+    pub fn step(&mut self) -> Option<Step> {
+        let ip = self.current_ip()?;
+        let instruction = self.program.instructions[ip];
+        self.first_run[ip].get_or_insert(self.history.len());
+        self.history.push(ip);
+        match instruction {
+            Instruction::Nop(_) => self.registers.ip += 1,
+            Instruction::Acc(arg) => {
+                self.registers.acc += arg;
+                self.registers.ip += 1;
+            }
+            Instruction::Jmp(arg) => self.registers.ip += arg,
+        }
+        let step = Step {
+            ip,
+            instruction,
+            registers: self.registers,
+        };
+        if let Some(t) = &mut self.trace {
+            t.push(step);
+        }
+        Some(step)
+    }
+    /// Runs until the program ends, an instruction is about to run a second time, or a
+    /// breakpoint is hit.
+    pub fn run(&mut self) -> Halt {
+        loop {
+            let Some(ip) = self.current_ip() else {
+                return if self.registers.ip == self.program.len() as i64 {
+                    Halt::Terminated(self.registers)
+                } else {
+                    Halt::OutOfBounds(self.registers)
+                };
+            };
+            if !std::mem::take(&mut self.resuming) {
+                if let Some(bp) = self.breakpoints.iter().find(|bp| bp.hit(&self.registers)) {
+                    self.resuming = true;
+                    return Halt::Breakpoint(*bp, self.registers);
+                }
+            }
+            if let Some(start) = self.first_run[ip] {
+                let cycle = self.history[start..]
+                    .iter()
+                    .map(|ip| (*ip, self.program.instructions[*ip]))
+                    .collect();
+                return Halt::Loop(LoopReport {
+                    registers: self.registers,
+                    cycle,
+                });
+            }
+            self.step();
+        }
+    }
+}
+
+/// Result of repairing a program with [`repair`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Repair {
+    /// Instruction that was swapped between `jmp` and `nop`.
+    pub ip: usize,
+    pub patched: Instruction,
+    /// Registers when the repaired program terminated.
+    pub registers: Registers,
+}
+
+/// Finds the single `jmp`/`nop` swap that makes `program` terminate.
+pub fn repair(program: &Program) -> Option<Repair> {
+    (0..program.len()).find_map(|ip| {
+        let patched = program.patched(ip)?;
+        match Vm::new(&patched).run() {
+            Halt::Terminated(registers) => Some(Repair {
+                ip,
+                patched: patched.instructions[ip],
+                registers,
+            }),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = r#"nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6"#;
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            "nop +0\nmul +3".parse::<Program>(),
+            Err(ParseError::UnknownOpcode {
+                line: 2,
+                op: "mul".to_string()
+            })
+        );
+        assert_eq!(
+            "acc".parse::<Program>(),
+            Err(ParseError::MissingArgument { line: 1 })
+        );
+        assert!(matches!(
+            "jmp x".parse::<Program>(),
+            Err(ParseError::BadArgument { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn loop_report() {
+        let p: Program = INPUT.parse().unwrap();
+        let mut vm = Vm::new(&p).with_tracing();
+        let report = match vm.run() {
+            Halt::Loop(report) => report,
+            h => panic!("expected loop, got {:?}", h),
+        };
+        assert_eq!(report.registers, Registers { ip: 1, acc: 5 });
+        let ips: Vec<_> = report.cycle.iter().map(|(ip, _)| *ip).collect();
+        assert_eq!(ips, vec![1, 2, 6, 7, 3, 4]);
+        assert_eq!(report.cycle[0].1.to_string(), "acc +1");
+        assert_eq!(vm.trace().len(), 7);
+        assert_eq!(vm.trace()[2].to_string(), "   2: jmp +4 | ip 6 acc 1");
+    }
+
+    #[test]
+    fn breakpoints() {
+        let p: Program = INPUT.parse().unwrap();
+        let mut vm = Vm::new(&p);
+        vm.add_breakpoint(Breakpoint::Ip(6));
+        vm.add_breakpoint(Breakpoint::AccGt(4));
+        assert_eq!(
+            vm.run(),
+            Halt::Breakpoint(Breakpoint::Ip(6), Registers { ip: 6, acc: 1 })
+        );
+        assert_eq!(
+            vm.run(),
+            Halt::Breakpoint(Breakpoint::AccGt(4), Registers { ip: 4, acc: 5 })
+        );
+        assert_eq!(
+            vm.run(),
+            Halt::Breakpoint(Breakpoint::AccGt(4), Registers { ip: 1, acc: 5 })
+        );
+        assert!(matches!(vm.run(), Halt::Loop(_)));
+    }
+
+    #[test]
+    fn repair_program() {
+        let p: Program = INPUT.parse().unwrap();
+        assert_eq!(
+            repair(&p),
+            Some(Repair {
+                ip: 7,
+                patched: Instruction::Nop(-4),
+                registers: Registers { ip: 9, acc: 8 },
+            })
+        );
+        let p: Program = "jmp -1".parse().unwrap();
+        assert_eq!(
+            Vm::new(&p).run(),
+            Halt::OutOfBounds(Registers { ip: -1, acc: 0 })
+        );
+        assert_eq!(repair(&"jmp +0\nacc +1\njmp -2".parse().unwrap()), None);
+    }
+}
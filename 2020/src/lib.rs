@@ -23,6 +23,7 @@ pub mod day6;
 pub mod day7;
 pub mod day8;
 pub mod day9;
+pub mod handheld;
 
 use aoc_runner_derive::aoc_lib;
 
@@ -23,20 +23,16 @@ impl Image {
             height,
             pixels,
         };
-        dbg!(&width, &height);
         pts.iter().for_each(|xy| im[*xy] = 1);
         im
     }
     fn fold_y(&self, y_axis: usize) -> Image {
-        println!("fold_y @ {}", y_axis);
         let mut im = Image::new(self.width, y_axis);
-        let odd = self.height % 2;
         for y in 0..self.height {
             for x in 0..self.width {
-                //dbg!( self.width, self.height, x, y, y_axis, (y % y_axis), self.pixels.len(), im.pixels.len());
                 if self[(x, y)] > 0 {
                     if y > y_axis {
-                        im[(x, self.height - y - odd)] = self[(x, y)];
+                        im[(x, 2 * y_axis - y)] = self[(x, y)];
                     } else {
                         im[(x, y)] = self[(x, y)];
                     }
@@ -46,8 +42,6 @@ impl Image {
         im
     }
     fn fold_x(&self, x_axis: usize) -> Image {
-        let odd = self.width % 2;
-        println!("fold_x @ {}", x_axis);
         for y in 0..self.height {
             assert_eq!(
                 self[(x_axis, y)],
@@ -63,7 +57,7 @@ impl Image {
             for x in 0..self.width {
                 if self[(x, y)] > 0 {
                     if x > x_axis {
-                        im[(self.width - x - odd, y)] = self[(x, y)];
+                        im[(2 * x_axis - x, y)] = self[(x, y)];
                     } else {
                         im[(x, y)] = self[(x, y)];
                     }
@@ -76,6 +70,16 @@ impl Image {
     fn count(&self) -> usize {
         self.pixels.iter().filter(|&n| *n != 0).count()
     }
+
+    fn to_image(&self) -> advent::prelude::Image<u8> {
+        let mut im = advent::prelude::Image::new(self.width, self.height, 0);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                im[(x, y)] = self[(x, y)];
+            }
+        }
+        im
+    }
 }
 
 impl Debug for Image {
@@ -135,13 +139,11 @@ fn part1(input: &str) -> Result<usize> {
             im.fold_x(*idx)
         };
     }
-    //assert!(im.count() < 896);
-    dbg!(&im);
     Ok(im.count())
 }
 
 #[aoc(day13, part2)]
-fn part2(input: &str) -> Result<usize> {
+fn part2(input: &str) -> Result<String> {
     let (pts, folds) = input.split_once("\n\n").unwrap();
     let pts: Vec<(usize, usize)> = pts
         .lines()
@@ -166,8 +168,7 @@ fn part2(input: &str) -> Result<usize> {
             im.fold_x(*idx)
         };
     }
-    dbg!(&im);
-    Ok(im.count())
+    Ok(advent::ocr::recognize(&im.to_image())?)
 }
 
 #[cfg(test)]
@@ -245,14 +246,21 @@ fold along y=2
         //assert_eq!(im.count(), 17);
         Ok(())
     }
-    /*
+
+    #[test]
+    fn test_fold_off_center() {
+        // Folding above the middle mirrors each point around the fold line, not the image edge.
+        let im = Image::new_with_pts(3, 9, &[(0, 6), (1, 8)]).fold_y(5);
+        assert_eq!((im.width, im.height), (3, 5));
+        assert_eq!((im[(0, 4)], im[(1, 2)], im.count()), (1, 1, 2));
+        let im = Image::new_with_pts(9, 3, &[(6, 0), (8, 1)]).fold_x(5);
+        assert_eq!((im.width, im.height), (5, 3));
+        assert_eq!((im[(4, 0)], im[(2, 1)], im.count()), (1, 1, 2));
+    }
+
     #[test]
-    fn test_part2()->Result<()> {
-    let input = r#"
-    "#
-    .trim();
-    assert_eq!(part2(input)?, usize::MAX);
-    Ok(())
+    fn test_part2() -> Result<()> {
+        assert_eq!(part2(&input_for(2021, 13))?, "EPUELPBR");
+        Ok(())
     }
-    */
 }
@@ -1,230 +1,126 @@
-use std::collections::HashMap;
+use std::{cmp::Reverse, collections::HashMap};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use aoc_runner_derive::aoc;
-
-struct TupleWindow<I, T>
-where
-    I: Iterator<Item = T>,
-{
-    iter: I,
-    prev: Option<T>,
-    next: Option<T>,
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+enum PolymerError {
+    #[error("line {0}: expected 'XY -> Z'")]
+    BadRule(usize),
+    #[error("template needs at least one element")]
+    EmptyTemplate,
+    #[error("element counts overflowed during step {0}")]
+    Overflow(usize),
 }
 
-impl<I, T> TupleWindow<I, T>
-where
-    I: Iterator<Item = T>,
-{
-    fn new(iter: I, rules: &HashMap<&[u8], u8>) -> Self {
-        TupleWindow {
-            iter,
-            prev: None,
-            next: None,
-        }
-    }
+/// Polymer tracked as the number of each adjacent pair of elements rather than as a string, so a
+/// step costs O(rules) no matter how long the polymer gets. The length still roughly doubles each
+/// step though, so the u128 counts overflow a little past 120 steps with typical rules. Past that
+/// `step` returns `Overflow` and leaves the polymer as it was; running thousands of steps would need
+/// a big integer count, which this doesn't attempt.
+#[derive(Clone, Debug)]
+struct Polymer {
+    pairs: HashMap<[u8; 2], u128>,
+    rules: HashMap<[u8; 2], u8>,
+    // Every element but the last is the first of some pair, insertions never change the ends.
+    last: u8,
+    steps: usize,
 }
 
-impl<I, T> Iterator for TupleWindow<I, T>
-where
-    I: Iterator<Item = T>,
-{
-    type Item = T;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.prev.is_none() {
-            self.prev = self.iter.next();
+impl Polymer {
+    fn parse(input: &str) -> Result<Polymer, PolymerError> {
+        let (template, rules) = input.split_once("\n\n").unwrap_or((input, ""));
+        let template = template.trim().as_bytes();
+        let last = *template.last().ok_or(PolymerError::EmptyTemplate)?;
+        let rules = rules
+            .lines()
+            .enumerate()
+            .map(|(i, l)| match l.trim().as_bytes() {
+                [a, b, b' ', b'-', b'>', b' ', c] => Ok(([*a, *b], *c)),
+                _ => Err(PolymerError::BadRule(i + 1)),
+            })
+            .collect::<Result<_, _>>()?;
+        let mut pairs = HashMap::new();
+        for w in template.windows(2) {
+            *pairs.entry([w[0], w[1]]).or_insert(0) += 1;
         }
-        /*
-           template.next() {
-           template.flat_map(|y|
-           let z = rules[xy];
-           res[i * 2] = xy[0];
-           res[i * 2 + 1] = z;
-           res[i * 2 + 2] = xy[1];
-           });
-        //dbg!(String::from_utf8_lossy(&res));
-        res
-        */
+        Ok(Polymer {
+            pairs,
+            rules,
+            last,
+            steps: 0,
+        })
+    }
 
-        if let Some(next) = self.iter.next() {
-            let prev = self.prev.take();
-            self.prev = Some(next);
-            return prev;
+    /// Applies every insertion rule once.
+    fn step(&mut self) -> Result<(), PolymerError> {
+        let mut next = HashMap::with_capacity(self.pairs.len() * 2);
+        for (&[a, b], &n) in &self.pairs {
+            let (first, second) = match self.rules.get(&[a, b]) {
+                Some(&c) => ([a, c], Some([c, b])),
+                None => ([a, b], None),
+            };
+            for p in [Some(first), second].into_iter().flatten() {
+                let count = next.entry(p).or_insert(0u128);
+                *count = count
+                    .checked_add(n)
+                    .ok_or(PolymerError::Overflow(self.steps + 1))?;
+            }
         }
-        None
+        self.pairs = next;
+        self.steps += 1;
+        Ok(())
     }
-}
 
-fn expand_it<'a, I: 'a + Iterator<Item = &'a u8>>(
-    template: I,
-    rules: &HashMap<&[u8], u8>,
-) -> impl Iterator<Item = &'a u8> {
-    TupleWindow::new(template, rules)
-}
+    fn run(&mut self, steps: usize) -> Result<(), PolymerError> {
+        (0..steps).try_for_each(|_| self.step())
+    }
 
-fn forty_steps<'a, I: 'a + Iterator<Item = &'a u8>>(it: I, rules: &HashMap<&[u8], u8>) -> usize {
-    return 0;
-    //let it = (1..40).fold(it, |acc, _| expand_it(acc, &rules));
-    expand_it(
-        expand_it(
-            expand_it(
-                expand_it(
-                    expand_it(
-                        expand_it(
-                            expand_it(
-                                expand_it(
-                                    expand_it(
-                                        expand_it(
-                                            expand_it(
-                                                expand_it(
-                                                    expand_it(
-                                                        expand_it(
-                                                            expand_it(
-                                                                expand_it(
-                                                                    expand_it(
-                                                                        expand_it(
-                                                                            expand_it(
-                                                                                expand_it(
-                                                                                    expand_it(
-                                                                                        expand_it(
-                                                                                            expand_it(
-                                                                                                expand_it(
-                                                                                                    expand_it(
-                                                                                                        expand_it(
-                                                                                                            expand_it(
-                                                                                                                expand_it(
-                                                                                                                    expand_it(
-                                                                                                                        expand_it(
-                                                                                                                            expand_it(
-                                                                                                                                expand_it(
-                                                                                                                                    expand_it(
-                                                                                                                                        expand_it(
-                                                                                                                                            expand_it(
-                                                                                                                                                expand_it(
-                                                                                                                                                    expand_it(
-                                                                                                                                                        expand_it(
-    expand_it(
-        expand_it(it, &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-                                                                                                                                                                    &rules),
-        &rules,
-    )
-    .count()
-}
+    fn element_counts(&self) -> HashMap<u8, u128> {
+        let mut counts = HashMap::from([(self.last, 1)]);
+        for (&[a, _], &n) in &self.pairs {
+            *counts.entry(a).or_insert(0) += n;
+        }
+        counts
+    }
 
-fn expand(template: &[u8], rules: &HashMap<&[u8], u8>) -> Vec<u8> {
-    let mut res = vec![0u8; template.len() * 2 - 1];
-    template.windows(2).enumerate().for_each(|(i, xy)| {
-        let z = rules[xy];
-        res[i * 2] = xy[0];
-        res[i * 2 + 1] = z;
-        res[i * 2 + 2] = xy[1];
-    });
-    //dbg!(String::from_utf8_lossy(&res));
-    res
+    /// Returns the most and least common elements with their counts, ties broken by the
+    /// alphabetically first element.
+    fn most_and_least_common(&self) -> ((u8, u128), (u8, u128)) {
+        let counts = self.element_counts();
+        let most = counts.iter().min_by_key(|(e, n)| (Reverse(**n), **e));
+        let least = counts.iter().min_by_key(|(e, n)| (**n, **e));
+        let (Some((&me, &mn)), Some((&le, &ln))) = (most, least) else {
+            unreachable!("a polymer always has an element");
+        };
+        ((me, mn), (le, ln))
+    }
 }
 
-fn count(template: &[u8]) -> (usize, usize) {
-    let m = template
-        .iter()
-        .fold(HashMap::<u8, usize>::new(), |mut m, v| {
-            *m.entry(*v).or_insert(0) += 1;
-            m
-        });
-    let mut keys: Vec<_> = m.keys().collect();
-    keys.sort_unstable();
-    let mut s = "".to_string();
-    for k in keys {
-        s.push_str(&format!("{}: {} ", String::from_utf8_lossy(&[*k]), m[k]));
-    }
-    m.values()
-        .fold((usize::MAX, 0), |(min, max), v| (min.min(*v), max.max(*v)))
+fn solve(input: &str, steps: usize) -> Result<u128> {
+    let mut polymer = Polymer::parse(input)?;
+    polymer.run(steps)?;
+    let ((_, most), (_, least)) = polymer.most_and_least_common();
+    most.checked_sub(least)
+        .ok_or_else(|| anyhow!("most common count below least common"))
 }
 
 #[aoc(day14, part1)]
-fn part1(input: &str) -> Result<usize> {
-    let (template, rules) = input.split_once("\n\n").unwrap();
-    let rules: HashMap<&[u8], u8> = rules
-        .lines()
-        .map(|l| {
-            let (pair, insert) = l.split_once(" -> ").unwrap();
-            (pair.as_bytes(), insert.as_bytes()[0])
-        })
-        .collect();
-    let mut template = template.as_bytes().to_vec();
-    for i in 1..11 {
-        template = expand(&template, &rules);
-        let s = String::from_utf8_lossy(&template);
-        count(&template);
-    }
-    let (min, max) = count(&template);
-    Ok(max - min)
+fn part1(input: &str) -> Result<u128> {
+    solve(input, 10)
 }
 
-// TODO
-//#[aoc(day14, part2)]
-fn part2(input: &str) -> Result<usize> {
-    let (template, rules) = input.split_once("\n\n").unwrap();
-    let rules: HashMap<&[u8], u8> = rules
-        .lines()
-        .map(|l| {
-            let (pair, insert) = l.split_once(" -> ").unwrap();
-            (pair.as_bytes(), insert.as_bytes()[0])
-        })
-        .collect();
-    let cnt = forty_steps(template.as_bytes().iter(), &rules);
-    dbg!(cnt);
-    //println!("After step {}: {}", i, String::from_utf8_lossy(&template));
-    //let (min, max) = count(template);
-    //Ok(max - min)
-    Ok(0)
+#[aoc(day14, part2)]
+fn part2(input: &str) -> Result<u128> {
+    solve(input, 40)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_part1() -> Result<()> {
-        let input = r#"
-NNCB
+    const INPUT: &str = r#"NNCB
 
 CH -> B
 HH -> N
@@ -241,58 +137,66 @@ BN -> B
 BB -> N
 BC -> B
 CC -> N
-CN -> C
-            "#
-        .trim();
-        assert_eq!(part1(input)?, 1588);
+CN -> C"#;
+
+    #[test]
+    fn test_part1() -> Result<()> {
+        assert_eq!(part1(INPUT)?, 1588);
         Ok(())
     }
 
-    // TODO
-    //#[test]
+    #[test]
     fn test_part2() -> Result<()> {
-        let input = r#"
-NNCB
+        assert_eq!(part2(INPUT)?, 2188189693529);
+        Ok(())
+    }
 
-CH -> B
-HH -> N
-CB -> H
-NH -> C
-HB -> C
-HC -> B
-HN -> C
-NN -> C
-BH -> H
-NC -> B
-NB -> B
-BN -> B
-BB -> N
-BC -> B
-CC -> N
-CN -> C
-            "#
-        .trim();
-        assert_eq!(part2(input)?, 2188189693529);
+    fn len(p: &Polymer) -> u128 {
+        p.element_counts().values().sum()
+    }
+
+    #[test]
+    fn test_steps() -> Result<()> {
+        let mut p = Polymer::parse(INPUT)?;
+        p.run(4)?;
+        // NBBNBNBBCCNBCNCCNBBNBBNBBBNBBNBBCBHCBHHNHCBBCBHCB
+        assert_eq!(len(&p), 49);
+        let counts = p.element_counts();
+        assert_eq!(counts[&b'N'], 11);
+        assert_eq!(counts[&b'H'], 5);
+        p.run(6)?;
+        assert_eq!(len(&p), 3073);
+        assert_eq!(p.most_and_least_common(), ((b'B', 1749), (b'H', 161)));
         Ok(())
     }
-}
 
-// BB -> N   BN NB    BB NB NB BB
-// BC -> B   BB BC    BN NB BB BC
-// BH -> H   BH HH    BH HH HN NH
-// BN -> B   BB NB    BN NB NB BB
-// CB -> H   CH HB    \
-// CC -> N   CN NC
-// CH -> B   CB BH
-// CN -> C   CC CN
-// HB -> C   HC CB
-// HC -> B   HB BC
-// HH -> N   HN NH
-// HN -> C   HC CN
-// NB -> B   NB BB
-// NC -> B   NC BC
-// NH -> C   NC CH
-// NN -> C   NC CN
-//
-//
-//
+    #[test]
+    fn test_overflow() -> Result<()> {
+        let mut p = Polymer::parse(INPUT)?;
+        // The polymer roughly doubles each step, so u128 counts last a bit over 120 steps.
+        p.run(100)?;
+        assert!(len(&p) > 1 << 100);
+        let Err(PolymerError::Overflow(n)) = p.run(10_000) else {
+            panic!("expected overflow");
+        };
+        assert!(n < 130);
+        // The failed step isn't counted or applied.
+        assert_eq!(p.steps, n - 1);
+        let before = p.pairs.clone();
+        assert_eq!(p.step(), Err(PolymerError::Overflow(n)));
+        assert_eq!(p.pairs, before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Polymer::parse("NN\n\nNN -> C\nNC C").unwrap_err(),
+            PolymerError::BadRule(2)
+        );
+        assert_eq!(
+            Polymer::parse("\n\nNN -> C").unwrap_err(),
+            PolymerError::EmptyTemplate
+        );
+    }
+}
@@ -1,37 +1,143 @@
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
-fn hex(b: &u8) -> u8 {
-    if *b >= b'A' {
-        10 + b - b'A'
-    } else {
-        b - b'0'
+#[derive(Debug, Error, PartialEq)]
+pub enum BitsError {
+    #[error("invalid hex digit {1:?} at {0}")]
+    InvalidHexDigit(usize, char),
+    #[error("invalid binary digit {1:?} at {0}")]
+    InvalidBinaryDigit(usize, char),
+    #[error("can read at most 64 bits at a time, asked for {0}")]
+    ReadTooLarge(usize),
+    #[error("bit {pos}: wanted {wanted} bits, only {remaining} left")]
+    UnexpectedEof {
+        pos: usize,
+        wanted: usize,
+        remaining: usize,
+    },
+    #[error("bit {0}: literal doesn't fit in 64 bits")]
+    LiteralOverflow(usize),
+    #[error("bit {pos}: sub-packets took {got} bits, header said {want}")]
+    LengthMismatch { pos: usize, want: u64, got: u64 },
+    #[error("version {0} doesn't fit in 3 bits")]
+    VersionTooLarge(u8),
+    #[error("bit {pos}: operator type {type_id} can't have {count} sub-packets")]
+    SubPacketCount {
+        pos: usize,
+        type_id: u64,
+        count: usize,
+    },
+    #[error("{0} sub-packets is too many to encode")]
+    TooManySubPackets(usize),
+}
+
+/// Reads big-endian bit fields from a transmission.
+pub struct BitReader {
+    bits: Vec<bool>,
+    pos: usize,
+}
+
+impl BitReader {
+    pub fn from_hex(input: &str) -> Result<BitReader, BitsError> {
+        let mut bits = Vec::with_capacity(input.len() * 4);
+        for (i, c) in input.trim().chars().enumerate() {
+            let n = c.to_digit(16).ok_or(BitsError::InvalidHexDigit(i, c))?;
+            bits.extend((0..4).rev().map(|b| n & (1 << b) != 0));
+        }
+        Ok(BitReader { bits, pos: 0 })
+    }
+    pub fn from_binary(input: &str) -> Result<BitReader, BitsError> {
+        let bits = input
+            .trim()
+            .chars()
+            .enumerate()
+            .map(|(i, c)| match c {
+                '0' => Ok(false),
+                '1' => Ok(true),
+                c => Err(BitsError::InvalidBinaryDigit(i, c)),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(BitReader { bits, pos: 0 })
+    }
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+    pub fn remaining(&self) -> usize {
+        self.bits.len() - self.pos
+    }
+    /// Reads the next `n` bits, `n` <= 64, as an unsigned integer.
+    pub fn read(&mut self, n: usize) -> Result<u64, BitsError> {
+        if n > 64 {
+            return Err(BitsError::ReadTooLarge(n));
+        }
+        if n > self.remaining() {
+            return Err(BitsError::UnexpectedEof {
+                pos: self.pos,
+                wanted: n,
+                remaining: self.remaining(),
+            });
+        }
+        let v = self.bits[self.pos..self.pos + n]
+            .iter()
+            .fold(0, |acc, b| (acc << 1) | *b as u64);
+        self.pos += n;
+        Ok(v)
     }
 }
 
-fn sum_version(packet: &Packet) -> u64 {
-    fn sum_packets(packets: &[Packet]) -> u64 {
-        packets.iter().map(sum_version).sum()
+#[derive(Default)]
+pub struct BitWriter {
+    bits: Vec<bool>,
+}
+
+impl BitWriter {
+    /// Appends the low `n` bits of `v`, most significant first.
+    pub fn write(&mut self, v: u64, n: usize) {
+        self.bits.extend((0..n).rev().map(|b| v & (1 << b) != 0));
+    }
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
     }
+    /// Returns the bits as hex, zero padded to a whole number of bytes like the puzzle input.
+    pub fn to_hex(&self) -> String {
+        let mut bits = self.bits.clone();
+        bits.resize(self.bits.len().div_ceil(8) * 8, false);
+        bits.chunks(4)
+            .map(|c| {
+                let n = c.iter().fold(0, |acc, b| (acc << 1) | *b as u32);
+                char::from_digit(n, 16).unwrap().to_ascii_uppercase()
+            })
+            .collect()
+    }
+}
+
+fn sum_version(packet: &Packet) -> u64 {
     packet.version as u64
-        + match &packet.packet_type {
-            PacketType::Sum(packets) => sum_packets(packets),
-            PacketType::Product(packets) => sum_packets(packets),
-            PacketType::Minimum(packets) => sum_packets(packets),
-            PacketType::Maximum(packets) => sum_packets(packets),
-            PacketType::Literal(_) => 0,
-            PacketType::GreaterThan(packets) => sum_packets(packets),
-            PacketType::LessThan(packets) => sum_packets(packets),
-            PacketType::Equal(packets) => sum_packets(packets),
-        }
+        + packet
+            .packet_type
+            .sub_packets()
+            .iter()
+            .map(sum_version)
+            .sum::<u64>()
 }
 
 fn interpret(packet: &Packet) -> u64 {
     match &packet.packet_type {
         PacketType::Sum(packets) => packets.iter().map(interpret).sum(),
         PacketType::Product(packets) => packets.iter().map(interpret).product(),
-        PacketType::Minimum(packets) => packets.iter().map(interpret).min().unwrap(),
-        PacketType::Maximum(packets) => packets.iter().map(interpret).max().unwrap(),
+        PacketType::Minimum(packets) => packets
+            .iter()
+            .map(interpret)
+            .min()
+            .expect("decode checks for sub-packets"),
+        PacketType::Maximum(packets) => packets
+            .iter()
+            .map(interpret)
+            .max()
+            .expect("decode checks for sub-packets"),
         PacketType::Literal(v) => *v,
         PacketType::GreaterThan(packets) => {
             if interpret(&packets[0]) > interpret(&packets[1]) {
@@ -57,8 +163,8 @@ fn interpret(packet: &Packet) -> u64 {
     }
 }
 
-#[derive(Debug)]
-enum PacketType {
+#[derive(Clone, Debug, PartialEq)]
+pub enum PacketType {
     // 0
     Sum(Vec<Packet>),
     // 1
@@ -77,123 +183,227 @@ enum PacketType {
     Equal(Vec<Packet>),
 }
 
-#[derive(Debug)]
-struct Packet {
-    version: u64,
-    bit_size: u64,
-    packet_type: PacketType,
-}
-
-struct Parser<'a> {
-    bytes: &'a [u8],
-    tmp: u64,
-    tmp_len: usize,
-}
+pub const LITERAL_TYPE_ID: u64 = 4;
 
-impl<'a> Parser<'a> {
-    fn new(input: &str) -> Parser<'_> {
-        Parser {
-            bytes: input.as_bytes(),
-            tmp: 0,
-            tmp_len: 0,
+impl PacketType {
+    pub fn type_id(&self) -> u64 {
+        match self {
+            PacketType::Sum(_) => 0,
+            PacketType::Product(_) => 1,
+            PacketType::Minimum(_) => 2,
+            PacketType::Maximum(_) => 3,
+            PacketType::Literal(_) => LITERAL_TYPE_ID,
+            PacketType::GreaterThan(_) => 5,
+            PacketType::LessThan(_) => 6,
+            PacketType::Equal(_) => 7,
+        }
+    }
+    /// Builds the operator for `type_id`, panics if it's the literal type.
+    pub fn operator(type_id: u64, packets: Vec<Packet>) -> PacketType {
+        match type_id {
+            0 => PacketType::Sum(packets),
+            1 => PacketType::Product(packets),
+            2 => PacketType::Minimum(packets),
+            3 => PacketType::Maximum(packets),
+            5 => PacketType::GreaterThan(packets),
+            6 => PacketType::LessThan(packets),
+            7 => PacketType::Equal(packets),
+            _ => panic!("type ID {type_id} isn't an operator"),
         }
     }
-    fn read(&mut self, n: usize) -> u64 {
-        assert!(n < 32, "can't read more than 32 bits at time");
-        //print!( "   BEGIN n {0} tmp 0b{1:b} len {2}    - ", n, self.tmp, self.tmp_len);
-        while self.tmp_len < n {
-            let mut buf = [0; 1];
-            self.bytes.read_exact(&mut buf).expect("EOF");
-            // Convert the byte from hexdecimal to binary and merge with any leftover bits.
-            self.tmp = (self.tmp << 4) | hex(&buf[0]) as u64;
-            self.tmp_len += 4;
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            PacketType::Sum(_) => "+",
+            PacketType::Product(_) => "*",
+            PacketType::Minimum(_) => "min",
+            PacketType::Maximum(_) => "max",
+            PacketType::Literal(_) => "literal",
+            PacketType::GreaterThan(_) => ">",
+            PacketType::LessThan(_) => "<",
+            PacketType::Equal(_) => "=",
+        }
+    }
+    pub fn sub_packets(&self) -> &[Packet] {
+        match self {
+            PacketType::Literal(_) => &[],
+            PacketType::Sum(packets)
+            | PacketType::Product(packets)
+            | PacketType::Minimum(packets)
+            | PacketType::Maximum(packets)
+            | PacketType::GreaterThan(packets)
+            | PacketType::LessThan(packets)
+            | PacketType::Equal(packets) => packets,
         }
-
-        let mask = (1 << n) - 1;
-        self.tmp_len -= n;
-        let v = (self.tmp >> self.tmp_len) & mask;
-
-        let mask = (1 << self.tmp_len) - 1;
-        self.tmp &= mask;
-
-        //println!( "   END n {0} tmp 0b{2:b} len {3} v 0b{1:00$b} ", n, v, self.tmp, self.tmp_len);
-        v as u64
+    }
+    fn fmt_indented(&self, f: &mut Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let packets = match self {
+            PacketType::Literal(v) => return write!(f, "{v}"),
+            _ => self.sub_packets(),
+        };
+        write!(f, "({}", self.symbol())?;
+        let flat = packets
+            .iter()
+            .all(|p| matches!(p.packet_type, PacketType::Literal(_)));
+        for p in packets {
+            if flat {
+                write!(f, " ")?;
+            } else {
+                write!(f, "\n{:1$}", "", (depth + 1) * 2)?;
+            }
+            p.packet_type.fmt_indented(f, depth + 1)?;
+        }
+        write!(f, ")")
     }
 }
 
-fn parse_packet(p: &mut Parser) -> Packet {
-    let mut bit_size: u64 = 0;
-    let version = p.read(3);
-    bit_size += 3;
-    let packet_type_id = p.read(3);
-    bit_size += 3;
-    let packet_type = if packet_type_id == 4 {
-        // Literal, read 5 bits at a time until MSB is 0
-        let mut v = 0;
-        loop {
-            let l = p.read(5);
-            v = (v << 4) | (l & 0b1111);
-            bit_size += 5;
-            if 0b10000 & l == 0 {
-                break;
+/// S-expression for the packet's computation, e.g. `(+ 1 (* 2 3))`. The alternate form, `{:#}`,
+/// puts each operand of an operator on its own indented line unless they're all literals.
+impl Display for PacketType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            return self.fmt_indented(f, 0);
+        }
+        match self {
+            PacketType::Literal(v) => write!(f, "{v}"),
+            _ => {
+                write!(f, "({}", self.symbol())?;
+                for p in self.sub_packets() {
+                    write!(f, " {}", p.packet_type)?;
+                }
+                write!(f, ")")
             }
         }
-        PacketType::Literal(v)
-    } else {
-        // length type ID
-        let ltid = p.read(1);
-        bit_size += 1;
-        let mut packets = Vec::new();
-        if ltid == 0 {
-            // If the length type ID is 0, then the next 15 bits are a number that represents the total length in bits of the sub-packets contained by this packet.
-            let len = p.read(15);
-            bit_size += 15;
-            let mut sub_bits = 0;
-            while sub_bits < len {
-                let sub_p = parse_packet(p);
-                bit_size += sub_p.bit_size;
-                sub_bits += sub_p.bit_size;
-                packets.push(sub_p);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packet {
+    pub version: u8,
+    pub packet_type: PacketType,
+}
+
+impl Packet {
+    pub fn from_hex(input: &str) -> Result<Packet, BitsError> {
+        Packet::decode(&mut BitReader::from_hex(input)?)
+    }
+
+    /// Decodes one packet, leaving the reader after its last bit. Comparison operators must have
+    /// exactly two sub-packets and the others at least one.
+    pub fn decode(r: &mut BitReader) -> Result<Packet, BitsError> {
+        let packet_start = r.position();
+        let version = r.read(3)? as u8;
+        let packet_type_id = r.read(3)?;
+        let packet_type = if packet_type_id == LITERAL_TYPE_ID {
+            // Literal, read 5 bits at a time until MSB is 0
+            let start = r.position();
+            let mut v: u64 = 0;
+            loop {
+                let l = r.read(5)?;
+                if v >> 60 != 0 {
+                    return Err(BitsError::LiteralOverflow(start));
+                }
+                v = (v << 4) | (l & 0b1111);
+                if 0b10000 & l == 0 {
+                    break;
+                }
             }
+            PacketType::Literal(v)
         } else {
-            // If the length type ID is 1, then the next 11 bits are a number that represents the number of sub-packets immediately contained by this packet.
-            let num = p.read(11);
-            bit_size += 11;
-            for _ in 0..num {
-                let sub_p = parse_packet(p);
-                bit_size += sub_p.bit_size;
-                packets.push(sub_p);
+            // length type ID
+            let ltid = r.read(1)?;
+            let mut packets = Vec::new();
+            if ltid == 0 {
+                // If the length type ID is 0, then the next 15 bits are a number that represents the total length in bits of the sub-packets contained by this packet.
+                let len = r.read(15)?;
+                let start = r.position();
+                while ((r.position() - start) as u64) < len {
+                    packets.push(Packet::decode(r)?);
+                }
+                let got = (r.position() - start) as u64;
+                if got != len {
+                    return Err(BitsError::LengthMismatch {
+                        pos: start,
+                        want: len,
+                        got,
+                    });
+                }
+            } else {
+                // If the length type ID is 1, then the next 11 bits are a number that represents the number of sub-packets immediately contained by this packet.
+                let num = r.read(11)?;
+                for _ in 0..num {
+                    packets.push(Packet::decode(r)?);
+                }
+            }
+            let count_ok = match packet_type_id {
+                5..=7 => packets.len() == 2,
+                _ => !packets.is_empty(),
+            };
+            if !count_ok {
+                return Err(BitsError::SubPacketCount {
+                    pos: packet_start,
+                    type_id: packet_type_id,
+                    count: packets.len(),
+                });
             }
+            PacketType::operator(packet_type_id, packets)
+        };
+        Ok(Packet {
+            version,
+            packet_type,
+        })
+    }
+
+    /// Encodes the packet as hex. Operators use length type ID 0 when their sub-packets fit in 15
+    /// bits, and fall back to counting sub-packets otherwise.
+    pub fn to_hex(&self) -> Result<String, BitsError> {
+        let mut w = BitWriter::default();
+        self.encode(&mut w)?;
+        Ok(w.to_hex())
+    }
+
+    pub fn encode(&self, w: &mut BitWriter) -> Result<(), BitsError> {
+        if self.version >= 8 {
+            return Err(BitsError::VersionTooLarge(self.version));
         }
-        match packet_type_id {
-            0 => PacketType::Sum(packets),
-            1 => PacketType::Product(packets),
-            2 => PacketType::Minimum(packets),
-            3 => PacketType::Maximum(packets),
-            5 => PacketType::GreaterThan(packets),
-            6 => PacketType::LessThan(packets),
-            7 => PacketType::Equal(packets),
-            _ => panic!("unknown packet type ID {}", packet_type_id),
+        w.write(self.version as u64, 3);
+        w.write(self.packet_type.type_id(), 3);
+        if let PacketType::Literal(v) = self.packet_type {
+            let groups = (64 - v.leading_zeros() as usize).div_ceil(4).max(1);
+            for g in (0..groups).rev() {
+                let more = if g > 0 { 0b10000 } else { 0 };
+                w.write(more | ((v >> (g * 4)) & 0b1111), 5);
+            }
+            return Ok(());
+        }
+
+        let packets = self.packet_type.sub_packets();
+        let mut sub = BitWriter::default();
+        for p in packets {
+            p.encode(&mut sub)?;
         }
-    };
-    Packet {
-        version,
-        bit_size,
-        packet_type,
+        if sub.len() < 1 << 15 {
+            w.write(0, 1);
+            w.write(sub.len() as u64, 15);
+        } else if packets.len() < 1 << 11 {
+            w.write(1, 1);
+            w.write(packets.len() as u64, 11);
+        } else {
+            return Err(BitsError::TooManySubPackets(packets.len()));
+        }
+        w.bits.extend(sub.bits);
+        Ok(())
     }
 }
 
 #[aoc(day16, part1)]
 fn part1(input: &str) -> Result<u64> {
-    let mut p = Parser::new(input);
-    let packet = parse_packet(&mut p);
+    let packet = Packet::from_hex(input)?;
     Ok(sum_version(&packet))
 }
 
 #[aoc(day16, part2)]
 fn part2(input: &str) -> Result<u64> {
-    let mut p = Parser::new(input);
-    let packet = parse_packet(&mut p);
+    let packet = Packet::from_hex(input)?;
     Ok(interpret(&packet))
 }
 
@@ -205,18 +415,14 @@ mod tests {
     fn test_part1() -> Result<()> {
         let input = vec![
             ("D2FE28", 6),
-            //("38006F45291200", 1 + 0 + 0),
+            ("38006F45291200", 1 + 6 + 2),
             ("8A004A801A8002F478", 16),
             ("620080001611562C8802118E34", 12),
             ("C0015000016115A2E0802F182340", 23),
             ("A0016C880162017C3686B18A3D4780", 31),
         ];
         for (inp, want) in input {
-            print!("\nTesting '{}'\n - ", inp);
-            inp.as_bytes().iter().for_each(|c| print!("{:04b}", hex(c)));
-            println!();
-            assert_eq!(part1(inp)?, want);
-            println!("Passed '{}'", inp);
+            assert_eq!(part1(inp)?, want, "{inp}");
         }
         Ok(())
     }
@@ -234,12 +440,176 @@ mod tests {
             ("9C0141080250320F1802104A08", 1),
         ];
         for (inp, want) in input {
-            print!("\nTesting '{}'\n - ", inp);
-            inp.as_bytes().iter().for_each(|c| print!("{:04b}", hex(c)));
-            println!();
-            assert_eq!(part2(inp)?, want);
-            println!("Passed '{}'", inp);
+            assert_eq!(part2(inp)?, want, "{inp}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reader() -> Result<()> {
+        let mut r = BitReader::from_binary("110100101111111000101000")?;
+        assert_eq!(r.read(3)?, 6);
+        assert_eq!(r.read(0)?, 0);
+        assert_eq!(r.read(21)?, 0b100101111111000101000);
+        assert_eq!(
+            r.read(1),
+            Err(BitsError::UnexpectedEof {
+                pos: 24,
+                wanted: 1,
+                remaining: 0
+            })
+        );
+        let mut r = BitReader::from_hex("FFFFFFFFFFFFFFFF1")?;
+        assert_eq!(r.read(64)?, u64::MAX);
+        assert_eq!(r.read(4)?, 1);
+        assert_eq!(r.read(65), Err(BitsError::ReadTooLarge(65)));
+        assert!(matches!(
+            BitReader::from_hex("D2FG28"),
+            Err(BitsError::InvalidHexDigit(3, 'G'))
+        ));
+        assert!(matches!(
+            BitReader::from_binary("0120"),
+            Err(BitsError::InvalidBinaryDigit(2, '2'))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert!(matches!(
+            Packet::from_hex("D2FE"),
+            Err(BitsError::UnexpectedEof { .. })
+        ));
+        // A literal with 17 groups of 4 bits.
+        let mut w = BitWriter::default();
+        w.write(0b100, 6);
+        for _ in 0..16 {
+            w.write(0b11111, 5);
+        }
+        w.write(0b01111, 5);
+        assert_eq!(
+            Packet::from_hex(&w.to_hex()),
+            Err(BitsError::LiteralOverflow(6))
+        );
+        // A minimum of nothing, and a comparison with only one operand.
+        let literal = Packet {
+            version: 0,
+            packet_type: PacketType::Literal(1),
+        };
+        for (packet_type, type_id, count) in [
+            (PacketType::Minimum(vec![]), 2, 0),
+            (PacketType::LessThan(vec![literal.clone()]), 6, 1),
+        ] {
+            let p = Packet {
+                version: 0,
+                packet_type,
+            };
+            assert_eq!(
+                Packet::from_hex(&p.to_hex().unwrap()),
+                Err(BitsError::SubPacketCount {
+                    pos: 0,
+                    type_id,
+                    count
+                })
+            );
         }
+        // Nested, the position is where the bad packet starts.
+        let p = Packet {
+            version: 0,
+            packet_type: PacketType::Sum(vec![
+                literal,
+                Packet {
+                    version: 0,
+                    packet_type: PacketType::Maximum(vec![]),
+                },
+            ]),
+        };
+        assert_eq!(
+            Packet::from_hex(&p.to_hex().unwrap()),
+            Err(BitsError::SubPacketCount {
+                pos: 22 + 11,
+                type_id: 3,
+                count: 0
+            })
+        );
+    }
+
+    #[test]
+    fn encode() -> Result<()> {
+        // These use length type ID 0, which the encoder prefers, so they round trip exactly.
+        for inp in ["D2FE28", "38006F45291200", "04005AC33890"] {
+            assert_eq!(Packet::from_hex(inp)?.to_hex()?, inp);
+        }
+        // Counts its sub-packets instead.
+        let p = Packet::from_hex("C200B40A82")?;
+        assert_eq!(p.to_hex()?, "C0005B40A820");
+        assert_eq!(Packet::from_hex(&p.to_hex()?)?, p);
+        let p = Packet {
+            version: 8,
+            packet_type: PacketType::Literal(1),
+        };
+        assert_eq!(p.to_hex(), Err(BitsError::VersionTooLarge(8)));
+        Ok(())
+    }
+
+    #[test]
+    fn pretty_print() -> Result<()> {
+        let p = Packet::from_hex("9C0141080250320F1802104A08")?;
+        assert_eq!(p.packet_type.to_string(), "(= (+ 1 3) (* 2 2))");
+        assert_eq!(format!("{:#}", p.packet_type), "(=\n  (+ 1 3)\n  (* 2 2))");
+        assert_eq!(Packet::from_hex("D2FE28")?.packet_type.to_string(), "2021");
+        Ok(())
+    }
+
+    fn random_packet(rng: &mut XorShift, depth: usize) -> Packet {
+        let version = rng.below(8) as u8;
+        let type_id = if depth == 0 { 4 } else { rng.below(8) };
+        let packet_type = if type_id == LITERAL_TYPE_ID {
+            // Anywhere from 1 to 64 significant bits.
+            let bits = 1 + rng.below(64);
+            PacketType::Literal(rng.next_u64() >> (64 - bits))
+        } else {
+            let n = match type_id {
+                5..=7 => 2,
+                _ => 1 + rng.below(4) as usize,
+            };
+            let packets = (0..n).map(|_| random_packet(rng, depth - 1)).collect();
+            PacketType::operator(type_id, packets)
+        };
+        Packet {
+            version,
+            packet_type,
+        }
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let mut rng = XorShift::new(0x2021_1216);
+        for _ in 0..500 {
+            let depth = rng.below(5) as usize;
+            let p = random_packet(&mut rng, depth);
+            let hex = p.to_hex()?;
+            assert_eq!(Packet::from_hex(&hex)?, p, "{hex}");
+            assert_eq!(Packet::from_hex(&hex)?.to_hex()?, hex);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip_large() -> Result<()> {
+        // Too many bits of sub-packets for length type ID 0.
+        let literal = Packet {
+            version: 7,
+            packet_type: PacketType::Literal(u64::MAX),
+        };
+        let p = Packet {
+            version: 1,
+            packet_type: PacketType::Sum(vec![literal; 500]),
+        };
+        let hex = p.to_hex()?;
+        // Version 1, type 0, length type ID 1, 500 sub-packets.
+        assert!(hex.starts_with("227D"), "{hex}");
+        assert_eq!(Packet::from_hex(&hex)?, p);
         Ok(())
     }
 }
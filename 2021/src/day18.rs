@@ -1,10 +1,13 @@
 use advent::prelude::*;
+use advent::vprint;
 use aoc_runner_derive::aoc;
 use std::{
     io::{BufReader, Cursor, Read},
     ops::Add,
 };
 
+const VERBOSE: bool = false;
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 enum ChildType {
     None,
@@ -27,83 +30,100 @@ impl Display for Idx {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 struct Node {
-    deleted: bool,
     idx: Idx,
     parent: Option<Idx>,
     left: ChildType,
     right: ChildType,
 }
-// Tree needs to support merging two into one for adding snailfish numbers.
-// Tree needs to support rightward and leftward depth first searches to find neighbors for applying
-// exploded spill over.
-// Need to support remove and/or replace for explode.
-// Need to support insert and/or replace for split.
-#[derive(Debug, Default)]
+/// Snailfish number as an arena of pairs indexed by `Idx`, each pointing at its parent so
+/// explosions can climb to their neighbors. Exploded pairs stay in `nodes` but become unreachable
+/// from `root`.
+#[derive(Clone, Debug, Default)]
 struct Tree {
     root: Idx,
     nodes: Vec<Node>,
 }
 
-struct TreeIter {
-    it: std::vec::IntoIter<Idx>,
+impl PartialEq for Tree {
+    fn eq(&self, other: &Self) -> bool {
+        // Lazy but should work.
+        self.to_string() == other.to_string()
+    }
 }
 
-impl TreeIter {
-    fn new(indices: &[Idx]) -> TreeIter {
-        let indices = indices.to_vec();
-        TreeIter {
-            it: indices.into_iter(),
-        }
-    }
+fn read_byte<R: Read>(reader: &mut R) -> std::io::Result<Option<u8>> {
+    let mut buf = [0; 1];
+    Ok((reader.read(&mut buf)? == 1).then_some(buf[0]))
+}
+
+/// One action taken while reducing a snailfish number.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    /// A pair of regular numbers nested inside four pairs exploded.
+    Explode { left: usize, right: usize },
+    /// A regular number of 10 or more split into a pair.
+    Split(usize),
 }
 
-impl Iterator for TreeIter {
-    type Item = Idx;
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Side {
+    Left,
+    Right,
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.it.next()
+impl Side {
+    fn opposite(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
     }
 }
 
-impl PartialEq for Tree {
-    fn eq(&self, other: &Self) -> bool {
-        // Lazy but should work.
-        self.to_string() == other.to_string()
+/// Iterator over the steps reducing a tree, each call to `next` applies one step.
+struct Reduction<'a> {
+    tree: &'a mut Tree,
+}
+
+impl Reduction<'_> {
+    /// The tree as of the last step.
+    fn tree(&self) -> &Tree {
+        self.tree
     }
 }
 
-fn read_byte<R: Read>(reader: &mut R) -> std::io::Result<Option<u8>> {
-    reader.bytes().next().transpose()
+impl Iterator for Reduction<'_> {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        self.tree.explode().or_else(|| self.tree.split())
+    }
 }
 
 impl Tree {
     fn reduce(&mut self) {
-        let mut changed = true;
-        while changed {
-            changed = self.explode();
-            println!("after explode {}", self);
-            if changed {
-                continue;
-            }
-            changed = self.split();
-            println!("after split {}", self);
-            //println!("splice changed {}", changed);
+        let mut reduction = self.reduction();
+        while let Some(step) = reduction.next() {
+            vprint!("after {step:?}: {}", reduction.tree());
         }
     }
+    fn reduction(&mut self) -> Reduction<'_> {
+        Reduction { tree: self }
+    }
     fn magnitude(&self) -> usize {
         fn inner(tree: &Tree, node: &Node) -> usize {
             match (node.left, node.right) {
                 (ChildType::Value(l), ChildType::Value(r)) => 3 * l + 2 * r,
                 (ChildType::Subtree(idx), ChildType::Value(r)) => {
-                    3 * inner(&tree, &tree[idx]) + 2 * r
+                    3 * inner(tree, &tree[idx]) + 2 * r
                 }
                 (ChildType::Value(l), ChildType::Subtree(idx)) => {
-                    3 * l + 2 * inner(&tree, &tree[idx])
+                    3 * l + 2 * inner(tree, &tree[idx])
                 }
                 (ChildType::Subtree(l_idx), ChildType::Subtree(r_idx)) => {
-                    3 * inner(&tree, &tree[l_idx]) + 2 * inner(&tree, &tree[r_idx])
+                    3 * inner(tree, &tree[l_idx]) + 2 * inner(tree, &tree[r_idx])
                 }
                 _ => panic!("unhandled combo for magnitude"),
             }
@@ -111,126 +131,107 @@ impl Tree {
         inner(self, &self[self.root])
     }
 
-    fn split(&mut self) -> bool {
-        if let Some(split_idx) = self
-            .left_to_right()
-            .skip_while(|idx| {
-                let n = &self[*idx];
-                if let ChildType::Value(v) = n.left {
-                    if v > 9 {
-                        return false;
-                    }
-                }
-                if let ChildType::Value(v) = n.right {
-                    if v > 9 {
-                        return false;
-                    }
-                }
-                true
-            })
-            .next()
-        {
-            if let ChildType::Value(v) = self[split_idx].left {
-                if v > 9 {
-                    let l = v / 2;
-                    let r = if v % 2 == 1 { 1 + v / 2 } else { v / 2 };
-                    let mut new_idx = self.add_node(ChildType::Value(l), ChildType::Value(r));
-                    self[new_idx].parent = Some(split_idx);
-                    self[split_idx].left = ChildType::Subtree(new_idx);
-                }
-            }
-            if let ChildType::Value(v) = self[split_idx].right {
-                if v > 9 {
-                    let l = v / 2;
-                    let r = if v % 2 == 1 { 1 + v / 2 } else { v / 2 };
-                    let mut new_idx = self.add_node(ChildType::Value(l), ChildType::Value(r));
-                    self[new_idx].parent = Some(split_idx);
-                    self[split_idx].right = ChildType::Subtree(new_idx);
-                }
-            }
-            return true;
+    fn child(&self, idx: Idx, side: Side) -> ChildType {
+        match side {
+            Side::Left => self[idx].left,
+            Side::Right => self[idx].right,
         }
-        false
-    }
-    fn explode(&mut self) -> bool {
-        let mut changed = false;
-        if let Some(node) = self
-            .nodes
-            .iter()
-            .filter(|n| !n.deleted)
-            .find(|n| self.depth(n) >= 4)
-        {
-            changed = true;
-            let ex_idx = node.idx;
-            // Find spillover to the right
-            if let Some(spillover) = self
-                .left_to_right()
-                .skip_while(|idx| *idx != ex_idx)
-                .skip(1)
-                .find(|idx| {
-                    let n = &self[*idx];
-                    match (n.left, n.right) {
-                        (ChildType::Subtree(_), ChildType::Subtree(_)) => false,
-                        _ => true,
-                    }
+    }
+    fn set_child(&mut self, idx: Idx, side: Side, child: ChildType) {
+        match side {
+            Side::Left => self[idx].left = child,
+            Side::Right => self[idx].right = child,
+        }
+    }
+
+    /// Splits the leftmost regular number of 10 or more.
+    fn split(&mut self) -> Option<Step> {
+        fn find(tree: &Tree, idx: Idx) -> Option<(Idx, Side, usize)> {
+            [Side::Left, Side::Right]
+                .into_iter()
+                .find_map(|side| match tree.child(idx, side) {
+                    ChildType::Value(v) if v > 9 => Some((idx, side, v)),
+                    ChildType::Subtree(sub) => find(tree, sub),
+                    _ => None,
                 })
-            {
-                let src = self[ex_idx].right;
-                let tgt = &mut self[spillover];
-                if let (ChildType::Value(l), ChildType::Value(r)) = (src, tgt.left) {
-                    tgt.left = ChildType::Value(l + r);
-                } else if let (ChildType::Value(l), ChildType::Value(r)) = (src, tgt.right) {
-                    tgt.right = ChildType::Value(l + r);
-                } else {
-                    unreachable!()
-                };
+        }
+        let (idx, side, v) = find(self, self.root)?;
+        let new_idx = self.add_node(ChildType::Value(v / 2), ChildType::Value(v.div_ceil(2)));
+        self[new_idx].parent = Some(idx);
+        self.set_child(idx, side, ChildType::Subtree(new_idx));
+        Some(Step::Split(v))
+    }
+
+    /// Explodes the leftmost pair nested inside four pairs.
+    fn explode(&mut self) -> Option<Step> {
+        fn find(tree: &Tree, idx: Idx, depth: usize) -> Option<(Idx, usize, usize)> {
+            match (tree[idx].left, tree[idx].right) {
+                (ChildType::Value(l), ChildType::Value(r)) if depth >= 4 => Some((idx, l, r)),
+                (l, r) => [l, r].into_iter().find_map(|c| match c {
+                    ChildType::Subtree(sub) => find(tree, sub, depth + 1),
+                    _ => None,
+                }),
             }
-            // Find spillover to the left
-            if let Some(spillover) = self
-                .right_to_left()
-                .skip_while(|idx| *idx != ex_idx)
-                .skip(1)
-                .find(|idx| {
-                    let n = &self[*idx];
-                    match (n.left, n.right) {
-                        (ChildType::Subtree(_), ChildType::Subtree(_)) => false,
-                        _ => true,
-                    }
-                })
-            {
-                let src = self[ex_idx].left;
-                let tgt = &mut self[spillover];
-                if let (ChildType::Value(l), ChildType::Value(r)) = (src, tgt.right) {
-                    tgt.right = ChildType::Value(l + r);
-                } else if let (ChildType::Value(l), ChildType::Value(r)) = (src, tgt.left) {
-                    tgt.left = ChildType::Value(l + r);
-                } else {
-                    unreachable!()
-                };
+        }
+        let (ex_idx, l, r) = find(self, self.root, 0)?;
+        self.add_to_neighbor(ex_idx, Side::Left, l);
+        self.add_to_neighbor(ex_idx, Side::Right, r);
+        // Replace exploded node
+        let p_idx = self[ex_idx].parent.expect("exploded root");
+        let side = if self[p_idx].left == ChildType::Subtree(ex_idx) {
+            Side::Left
+        } else {
+            Side::Right
+        };
+        self.set_child(p_idx, side, ChildType::Value(0));
+        Some(Step::Explode { left: l, right: r })
+    }
+
+    /// Adds `v` to the first regular number on `side` of the pair at `idx`, if there is one.
+    fn add_to_neighbor(&mut self, idx: Idx, side: Side, v: usize) {
+        // Climb until the path turns away from `side`, the neighbor is in the sibling subtree
+        // there.
+        let mut child = idx;
+        let (mut node, mut target) = loop {
+            let Some(parent) = self[child].parent else {
+                return;
+            };
+            match self.child(parent, side) {
+                ChildType::Subtree(sib) if sib == child => child = parent,
+                ChildType::Subtree(sib) => break (sib, side.opposite()),
+                ChildType::Value(_) => break (parent, side),
+                ChildType::None => unreachable!("incomplete pair"),
             }
-            // Replace exploded node
-            self[ex_idx].deleted = true;
-            let p_idx = self[ex_idx].parent.expect("exploded root");
-            let p = &mut self[p_idx];
-            if let ChildType::Subtree(idx) = p.left {
-                if idx == ex_idx {
-                    p.left = ChildType::Value(0);
+        };
+        // Then descend along the side closest to `idx`.
+        loop {
+            match self.child(node, target) {
+                ChildType::Value(n) => {
+                    return self.set_child(node, target, ChildType::Value(n + v));
                 }
-            }
-            if let ChildType::Subtree(idx) = p.right {
-                if idx == ex_idx {
-                    p.right = ChildType::Value(0);
+                ChildType::Subtree(sub) => {
+                    node = sub;
+                    target = side.opposite();
                 }
+                ChildType::None => unreachable!("incomplete pair"),
             }
         }
-        changed
     }
-    fn depth(&self, node: &Node) -> usize {
-        if let Some(parent_idx) = node.parent {
-            1 + self.depth(&self[parent_idx])
-        } else {
-            0
+
+    /// Copies the subtree at `idx` of `src` into this tree, leaving out unreachable nodes.
+    fn copy_subtree(&mut self, src: &Tree, idx: Idx, parent: Option<Idx>) -> Idx {
+        let new_idx = self.add_node(ChildType::None, ChildType::None);
+        self[new_idx].parent = parent;
+        for side in [Side::Left, Side::Right] {
+            let child = match src.child(idx, side) {
+                ChildType::Subtree(sub) => {
+                    ChildType::Subtree(self.copy_subtree(src, sub, Some(new_idx)))
+                }
+                c => c,
+            };
+            self.set_child(new_idx, side, child);
         }
+        new_idx
     }
     fn find_root(&self, node: &Node) -> Idx {
         match node.parent {
@@ -241,7 +242,6 @@ impl Tree {
     fn add_node(&mut self, left: ChildType, right: ChildType) -> Idx {
         let idx = Idx(self.nodes.len());
         self.nodes.push(Node {
-            deleted: false,
             idx,
             parent: None,
             left,
@@ -250,7 +250,7 @@ impl Tree {
         idx
     }
 
-    fn from_str_node(&mut self, r: &mut BufReader<Cursor<&[u8]>>) -> ChildType {
+    fn parse_node(&mut self, r: &mut BufReader<Cursor<&[u8]>>) -> ChildType {
         let mut parsing_left = true;
         // Can this be rewritten to eliminate the need for `None`?
         let mut left = ChildType::None;
@@ -258,7 +258,7 @@ impl Tree {
         while let Ok(Some(b)) = read_byte(r) {
             match b {
                 b'[' => {
-                    let node = self.from_str_node(r);
+                    let node = self.parse_node(r);
                     if parsing_left {
                         left = node;
                     } else {
@@ -323,46 +323,20 @@ impl Tree {
         write!(f, "]")?;
         Ok(())
     }
-
-    fn left_to_right(&mut self) -> TreeIter {
-        fn dfs(tree: &Tree, n: &Node, mut indices: &mut Vec<Idx>) {
-            if let ChildType::Subtree(idx) = n.left {
-                dfs(tree, &tree[idx], indices);
-            }
-            indices.push(n.idx);
-            if let ChildType::Subtree(idx) = n.right {
-                dfs(tree, &tree[idx], indices);
-            }
-        }
-        let mut indices = Vec::with_capacity(self.nodes.len());
-        dfs(self, &self[self.root], &mut indices);
-        TreeIter::new(&indices)
-    }
-    fn right_to_left(&mut self) -> TreeIter {
-        fn dfs(tree: &Tree, n: &Node, mut indices: &mut Vec<Idx>) {
-            if let ChildType::Subtree(idx) = n.right {
-                dfs(tree, &tree[idx], indices);
-            }
-            indices.push(n.idx);
-            if let ChildType::Subtree(idx) = n.left {
-                dfs(tree, &tree[idx], indices);
-            }
-        }
-        let mut indices = Vec::with_capacity(self.nodes.len());
-        dfs(self, &self[self.root], &mut indices);
-        TreeIter::new(&indices)
-    }
 }
 
 impl Add for Tree {
     type Output = Tree;
 
+    /// Pairs the two numbers without reducing the result.
     fn add(self, other: Self) -> Self {
-        // This is lazy but works for simple any obvious reasons (if FromStr and Display work
-        // correctly).
-        format!("[{},{}]", self, other)
-            .parse()
-            .expect("failed to parse merge tree")
+        let mut tree = Tree::default();
+        let l = tree.copy_subtree(&self, self.root, None);
+        let r = tree.copy_subtree(&other, other.root, None);
+        tree.root = tree.add_node(ChildType::Subtree(l), ChildType::Subtree(r));
+        tree[l].parent = Some(tree.root);
+        tree[r].parent = Some(tree.root);
+        tree
     }
 }
 
@@ -377,7 +351,7 @@ impl FromStr for Tree {
             Some(b'[')
         );
         let mut b = BufReader::new(Cursor::new(bytes));
-        tree.from_str_node(&mut b);
+        tree.parse_node(&mut b);
         tree.root = tree.find_root(&tree[Idx(0)]);
         Ok(tree)
     }
@@ -403,48 +377,68 @@ impl Display for Tree {
         }
 
         let node = &self[self.root];
-        self.fmt_node(f, &node)?;
+        self.fmt_node(f, node)?;
         Ok(())
     }
 }
 
+/// Adds up the numbers on each line, reducing after every addition.
 fn sum(input: &str) -> Tree {
     input
         .lines()
-        .map(|l| l.parse().expect("failed to parse"))
-        .reduce(|acc, t| acc + t)
+        .map(|l| l.parse::<Tree>().expect("failed to parse"))
+        .reduce(|acc, t| {
+            let mut sum = acc + t;
+            sum.reduce();
+            sum
+        })
         .expect("failed to reduce")
 }
 
 #[aoc(day18, part1)]
 fn part1(input: &str) -> Result<usize> {
-    let nums: Vec<Tree> = input
-        .lines()
-        .map(|l| {
-            dbg!(l);
-            l.parse().expect("failed to parse")
-        })
-        .collect();
-    let mut it = nums.into_iter();
-    let mut last = it.next().unwrap();
-    while let Some(next) = it.next() {
-        println!("  {}", last);
-        println!("+ {}", next);
-        last = last + next;
-        println!("= {}", last);
-        last.reduce();
-        println!("= {}\n", last);
-    }
-    Ok(last.magnitude())
+    Ok(sum(input).magnitude())
+}
+
+/// Largest magnitude of the sum of any two different numbers, with the pairs split across
+/// threads.
+fn largest_pair(nums: &[Tree]) -> usize {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    std::thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                s.spawn(move || {
+                    let mut best = 0;
+                    for i in (t..nums.len()).step_by(threads) {
+                        for j in 0..nums.len() {
+                            if i == j {
+                                continue;
+                            }
+                            let mut sum = nums[i].clone() + nums[j].clone();
+                            sum.reduce();
+                            best = best.max(sum.magnitude());
+                        }
+                    }
+                    best
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("worker panicked"))
+            .max()
+            .unwrap_or(0)
+    })
 }
 
-/*
 #[aoc(day18, part2)]
 fn part2(input: &str) -> Result<usize> {
-todo!("part2");
-Ok(0)
+    let nums: Vec<Tree> = input
+        .lines()
+        .map(|l| l.parse().expect("failed to parse"))
+        .collect();
+    Ok(largest_pair(&nums))
 }
-*/
 
 #[cfg(test)]
 mod tests {
@@ -538,7 +532,6 @@ mod tests {
         ] {
             println!("== test_split: {}", input);
             let mut tree: Tree = input.parse()?;
-            dbg!(&tree);
             tree.split();
             let want = want.parse()?;
             assert_eq!(tree, want, "\nInput {} Got {} Want {}", input, tree, want);
@@ -719,14 +712,58 @@ mod tests {
         Ok(())
     }
 
-    /*
     #[test]
-    fn test_part2()->Result<()> {
-    let input = r#"
+    fn test_steps() -> Result<()> {
+        let mut tree: Tree = "[[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]".parse()?;
+        let mut reduction = tree.reduction();
+        let mut steps = Vec::new();
+        while let Some(step) = reduction.next() {
+            steps.push((step, reduction.tree().to_string()));
+        }
+        assert_eq!(
+            steps,
+            vec![
+                (
+                    Step::Explode { left: 4, right: 3 },
+                    "[[[[0,7],4],[7,[[8,4],9]]],[1,1]]".to_string()
+                ),
+                (
+                    Step::Explode { left: 8, right: 4 },
+                    "[[[[0,7],4],[15,[0,13]]],[1,1]]".to_string()
+                ),
+                (
+                    Step::Split(15),
+                    "[[[[0,7],4],[[7,8],[0,13]]],[1,1]]".to_string()
+                ),
+                (
+                    Step::Split(13),
+                    "[[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]".to_string()
+                ),
+                (
+                    Step::Explode { left: 6, right: 7 },
+                    "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]".to_string()
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_part2() -> Result<()> {
+        let input = r#"
+[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]
+[[[5,[2,8]],4],[5,[[9,9],0]]]
+[6,[[[6,2],[5,6]],[[7,6],[4,7]]]]
+[[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]
+[[[7,[6,4]],[3,[1,3]]],[[[5,5],1],9]]
+[[6,[[7,3],[3,2]]],[[[3,8],[5,7]],4]]
+[[[[5,4],[7,7]],8],[[8,3],8]]
+[[9,3],[[9,9],[6,[4,9]]]]
+[[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]
+[[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]
     "#
-    .trim();
-    assert_eq!(part2(input)?, usize::MAX);
-    Ok(())
+        .trim();
+        assert_eq!(part2(input)?, 3993);
+        Ok(())
     }
-    */
 }
@@ -38,15 +38,10 @@ fn take_turn(p: &mut Player, die: &mut Die) -> bool {
 
 #[aoc(day21, part1)]
 fn part1(input: &str) -> Result<usize> {
-    let mut p: Vec<_> = input
-        .lines()
-        .map(|l| l.split_once(": ").unwrap())
-        .map(|(_, space)| space.parse().expect("couldn't parse starting spaceition"))
-        .map(|space: usize| Player {
-            tally: space - 1,
-            score: 0,
-        })
-        .collect();
+    let mut p = starting_spaces(input)?.map(|space| Player {
+        tally: space - 1,
+        score: 0,
+    });
     let mut die = Die::default();
     loop {
         if take_turn(&mut p[0], &mut die) {
@@ -61,86 +56,91 @@ fn part1(input: &str) -> Result<usize> {
     }
 }
 
-fn play_part2(p1: Player, p2: Player) -> (usize, usize) {
-    fn play_part2_rec(
-        mut p1: Player,
-        mut p2: Player,
-        r1: usize,
-        r2: usize,
-        r3: usize,
-        r4: usize,
-        r5: usize,
-        r6: usize,
-    ) -> (usize, usize) {
-        //println!( "p1 {} {} p2 {} {} die {} {} {} {} {} {}", p1.score, p1.space(), p2.score, p2.space(), r1, r2, r3, r4, r5, r6,);
-        p1.tally += r1 + r2 + r3;
-        p1.score += p1.space();
-        if p1.score >= 21 {
-            return (1, 0);
-        }
-
-        p2.tally += r4 + r5 + r6;
-        p2.score += p2.space();
-        if p2.score >= 21 {
-            return (0, 1);
-        }
+/// Quantum version of the game, where every roll of the Dirac die splits the universe once per
+/// face.
+#[derive(Clone, Debug)]
+struct DiracGame {
+    board: usize,
+    target: usize,
+    // (sum of a turn's rolls, number of universes producing it)
+    outcomes: Vec<(usize, u128)>,
+    cache: HashMap<([usize; 2], [usize; 2], usize), [u128; 2]>,
+}
 
-        let mut p1_score = 0;
-        let mut p2_score = 0;
-
-        for i in [1, 2, 3] {
-            for j in [1, 2, 3] {
-                for k in [1, 2, 3] {
-                    for x in [1, 2, 3] {
-                        for y in [1, 2, 3] {
-                            for z in [1, 2, 3] {
-                                let (p1s, p2s) = play_part2_rec(p1, p2, i, j, k, x, y, z);
-                                p1_score += p1s;
-                                p2_score += p2s;
-                            }
-                        }
-                    }
+impl DiracGame {
+    fn new(board: usize, target: usize, faces: usize, rolls_per_turn: usize) -> DiracGame {
+        let mut counts = vec![1u128];
+        for _ in 0..rolls_per_turn {
+            let mut next = vec![0; counts.len() + faces];
+            for (sum, n) in counts.iter().enumerate() {
+                for face in 1..=faces {
+                    next[sum + face] += n;
                 }
             }
+            counts = next;
+        }
+        let outcomes = counts
+            .into_iter()
+            .enumerate()
+            .filter(|(_, n)| *n > 0)
+            .collect();
+        DiracGame {
+            board,
+            target,
+            outcomes,
+            cache: HashMap::new(),
         }
-        (p1_score, p2_score)
     }
 
-    let mut p1_score = 0;
-    let mut p2_score = 0;
-
-    for i in [1, 2, 3] {
-        for j in [1, 2, 3] {
-            for k in [1, 2, 3] {
-                for x in [1, 2, 3] {
-                    for y in [1, 2, 3] {
-                        for z in [1, 2, 3] {
-                            let (p1s, p2s) = play_part2_rec(p1, p2, i, j, k, x, y, z);
-                            p1_score += p1s;
-                            p2_score += p2s;
-                            println!("Running score {} vs {}", p1_score, p2_score);
-                        }
-                    }
-                }
+    /// Counts the universes each player wins in, starting from the given spaces (1 based) with
+    /// player 1 to move.
+    fn wins(&mut self, spaces: [usize; 2]) -> [u128; 2] {
+        self.wins_from(spaces.map(|s| s - 1), [0, 0], 0)
+    }
+
+    fn wins_from(&mut self, pos: [usize; 2], scores: [usize; 2], turn: usize) -> [u128; 2] {
+        let key = (pos, scores, turn);
+        if let Some(wins) = self.cache.get(&key) {
+            return *wins;
+        }
+        let mut wins = [0, 0];
+        for i in 0..self.outcomes.len() {
+            let (sum, n) = self.outcomes[i];
+            let (mut pos, mut scores) = (pos, scores);
+            pos[turn] = (pos[turn] + sum) % self.board;
+            scores[turn] += pos[turn] + 1;
+            if scores[turn] >= self.target {
+                wins[turn] += n;
+            } else {
+                let sub = self.wins_from(pos, scores, 1 - turn);
+                wins[0] += n * sub[0];
+                wins[1] += n * sub[1];
             }
         }
+        self.cache.insert(key, wins);
+        wins
     }
-    (p1_score, p2_score)
 }
 
-//#[aoc(day21, part2)]
-fn part2(input: &str) -> Result<usize> {
-    let p: Vec<_> = input
+fn starting_spaces(input: &str) -> Result<[usize; 2]> {
+    let spaces: Vec<usize> = input
         .lines()
-        .map(|l| l.split_once(": ").unwrap())
-        .map(|(_, space)| space.parse().expect("couldn't parse starting spaceition"))
-        .map(|space: usize| Player {
-            tally: space - 1,
-            score: 0,
+        .map(|l| {
+            let (_, space) = l
+                .split_once(": ")
+                .ok_or_else(|| anyhow::anyhow!("expected 'Player N starting position: S'"))?;
+            Ok(space.parse()?)
         })
-        .collect();
-    let (p1_wins, p2_wins) = play_part2(p[0], p[1]);
-    Ok(if p1_wins > p2_wins { p1_wins } else { p2_wins })
+        .collect::<Result<_>>()?;
+    spaces
+        .try_into()
+        .map_err(|v: Vec<usize>| anyhow::anyhow!("expected 2 players, got {}", v.len()))
+}
+
+#[aoc(day21, part2)]
+fn part2(input: &str) -> Result<u128> {
+    let wins = DiracGame::new(10, 21, 3, 3).wins(starting_spaces(input)?);
+    Ok(wins[0].max(wins[1]))
 }
 
 #[cfg(test)]
@@ -158,7 +158,7 @@ Player 2 starting position: 8
         Ok(())
     }
 
-    //#[test]
+    #[test]
     fn test_part2() -> Result<()> {
         let input = r#"
 Player 1 starting position: 4
@@ -168,4 +168,25 @@ Player 2 starting position: 8
         assert_eq!(part2(input)?, 444356092776315);
         Ok(())
     }
+
+    #[test]
+    fn test_outcomes() {
+        let game = DiracGame::new(10, 21, 3, 3);
+        assert_eq!(
+            game.outcomes,
+            vec![(3, 1), (4, 3), (5, 6), (6, 7), (7, 6), (8, 3), (9, 1)]
+        );
+    }
+
+    #[test]
+    fn test_wins() {
+        assert_eq!(
+            DiracGame::new(10, 21, 3, 3).wins([4, 8]),
+            [444356092776315, 341960390180808]
+        );
+        // A one faced die plays the deterministic game: player 1 moves 3 to space 4 and wins.
+        assert_eq!(DiracGame::new(10, 4, 1, 3).wins([1, 1]), [1, 0]);
+        // Two coin flips move player 1 to space 3, 4 or 5, winning all 4 universes at once.
+        assert_eq!(DiracGame::new(5, 3, 2, 2).wins([1, 1]), [4, 0]);
+    }
 }
@@ -1,41 +1,454 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
+const HALLWAY: usize = 11;
+const ROOMS: usize = 4;
+const MAX_DEPTH: usize = 4;
+// Hallway positions directly outside each room, amphipods never stop there.
+const ENTRANCES: [usize; ROOMS] = [2, 4, 6, 8];
+const EMPTY: u8 = b'.';
+// Lines inserted between the first and last row of each room when the diagram is unfolded.
+const FOLDED: [&str; 2] = ["  #D#C#B#A#", "  #D#B#A#C#"];
+
+#[derive(Debug, Error, PartialEq)]
+enum BurrowError {
+    #[error("line {0}: doesn't look like part of a burrow")]
+    BadLine(usize),
+    #[error("unknown amphipod {0:?}")]
+    UnknownAmphipod(char),
+    #[error("rooms must be 1 to {MAX_DEPTH} deep, got {0}")]
+    BadDepth(usize),
+    #[error("every room needs {0} of each amphipod")]
+    WrongCount(usize),
+    #[error("only 2 deep burrows can be unfolded, this one is {0} deep")]
+    CantUnfold(usize),
+}
+
+fn energy(amphipod: u8) -> usize {
+    10usize.pow((amphipod - b'A') as u32)
+}
+
+fn room_for(amphipod: u8) -> usize {
+    (amphipod - b'A') as usize
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Pos {
+    Hallway(usize),
+    /// Room and slot, slot 0 is nearest the hallway.
+    Room(usize, usize),
+}
+
+impl Pos {
+    fn x(self) -> usize {
+        match self {
+            Pos::Hallway(x) => x,
+            Pos::Room(r, _) => ENTRANCES[r],
+        }
+    }
+    // Steps between this position and the hallway.
+    fn depth(self) -> usize {
+        match self {
+            Pos::Hallway(_) => 0,
+            Pos::Room(_, slot) => slot + 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Move {
+    amphipod: char,
+    from: Pos,
+    to: Pos,
+    energy: usize,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:?} -> {:?} ({} energy)",
+            self.amphipod, self.from, self.to, self.energy
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Burrow {
+    hallway: [u8; HALLWAY],
+    rooms: [[u8; MAX_DEPTH]; ROOMS],
+    depth: usize,
+}
+
+impl FromStr for Burrow {
+    type Err = BurrowError;
+
+    fn from_str(s: &str) -> std::result::Result<Burrow, BurrowError> {
+        let mut hallway = [EMPTY; HALLWAY];
+        let mut rooms = [[EMPTY; MAX_DEPTH]; ROOMS];
+        let mut depth = 0;
+        for (i, line) in s.lines().enumerate() {
+            let cells: Vec<char> = line.chars().filter(|c| *c != '#' && *c != ' ').collect();
+            if cells.is_empty() {
+                continue;
+            }
+            let valid = |c: &char| *c == '.' || ('A'..='D').contains(c);
+            if let Some(c) = cells.iter().find(|c| !valid(c)) {
+                return Err(BurrowError::UnknownAmphipod(*c));
+            }
+            match cells.len() {
+                HALLWAY => {
+                    for (h, c) in hallway.iter_mut().zip(&cells) {
+                        *h = *c as u8;
+                    }
+                }
+                ROOMS if depth < MAX_DEPTH => {
+                    for (room, c) in rooms.iter_mut().zip(&cells) {
+                        room[depth] = *c as u8;
+                    }
+                    depth += 1;
+                }
+                ROOMS => return Err(BurrowError::BadDepth(depth + 1)),
+                _ => return Err(BurrowError::BadLine(i + 1)),
+            }
+        }
+        if depth == 0 {
+            return Err(BurrowError::BadDepth(0));
+        }
+        let burrow = Burrow {
+            hallway,
+            rooms,
+            depth,
+        };
+        for a in b'A'..=b'D' {
+            let n = burrow.cells().filter(|c| *c == a).count();
+            if n != depth {
+                return Err(BurrowError::WrongCount(depth));
+            }
+        }
+        Ok(burrow)
+    }
+}
+
+/// Draws the burrow like the puzzle's diagrams.
+impl Display for Burrow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "#############")?;
+        writeln!(f, "#{}#", String::from_utf8_lossy(&self.hallway))?;
+        for slot in 0..self.depth {
+            let row: Vec<String> = self
+                .rooms
+                .iter()
+                .map(|r| (r[slot] as char).to_string())
+                .collect();
+            if slot == 0 {
+                writeln!(f, "###{}###", row.join("#"))?;
+            } else {
+                writeln!(f, "  #{}#", row.join("#"))?;
+            }
+        }
+        write!(f, "  #########")
+    }
+}
+
+impl Burrow {
+    /// Inserts the two extra rows of amphipods from the folded part of the diagram.
+    fn unfold(&self) -> Result<Burrow, BurrowError> {
+        if self.depth != 2 {
+            return Err(BurrowError::CantUnfold(self.depth));
+        }
+        let mut b = *self;
+        b.depth = 4;
+        for (r, room) in b.rooms.iter_mut().enumerate() {
+            let folded: Vec<u8> = FOLDED.iter().map(|l| l.as_bytes()[3 + r * 2]).collect();
+            *room = [self.rooms[r][0], folded[0], folded[1], self.rooms[r][1]];
+        }
+        Ok(b)
+    }
+
+    fn cells(&self) -> impl Iterator<Item = u8> + '_ {
+        self.hallway
+            .iter()
+            .chain(self.rooms.iter().flat_map(|r| &r[..self.depth]))
+            .copied()
+    }
+
+    fn get(&self, p: Pos) -> u8 {
+        match p {
+            Pos::Hallway(x) => self.hallway[x],
+            Pos::Room(r, s) => self.rooms[r][s],
+        }
+    }
+
+    fn set(&mut self, p: Pos, v: u8) {
+        match p {
+            Pos::Hallway(x) => self.hallway[x] = v,
+            Pos::Room(r, s) => self.rooms[r][s] = v,
+        }
+    }
+
+    fn is_organized(&self) -> bool {
+        (0..ROOMS).all(|r| self.room_ready(r) && self.rooms[r][0] != EMPTY)
+    }
+
+    // True if the room only holds amphipods that belong there.
+    fn room_ready(&self, r: usize) -> bool {
+        self.rooms[r][..self.depth]
+            .iter()
+            .all(|c| *c == EMPTY || room_for(*c) == r)
+    }
+
+    fn hallway_clear(&self, from: usize, to: usize) -> bool {
+        let (lo, hi) = if from < to {
+            (from + 1, to)
+        } else {
+            (to, from - 1)
+        };
+        (lo..=hi).all(|x| self.hallway[x] == EMPTY)
+    }
+
+    // Where an amphipod would go in its room, if it can go in at all.
+    fn destination(&self, amphipod: u8) -> Option<Pos> {
+        let r = room_for(amphipod);
+        if !self.room_ready(r) {
+            return None;
+        }
+        (0..self.depth)
+            .rev()
+            .find(|s| self.rooms[r][*s] == EMPTY)
+            .map(|s| Pos::Room(r, s))
+    }
+
+    fn make_move(&self, from: Pos, to: Pos) -> Move {
+        let amphipod = self.get(from);
+        let steps = from.depth() + to.depth() + from.x().abs_diff(to.x());
+        Move {
+            amphipod: amphipod as char,
+            from,
+            to,
+            energy: steps * energy(amphipod),
+        }
+    }
+
+    /// All legal moves. Amphipods leave a room for the hallway, or go straight to their own
+    /// room, and only leave the hallway for their own room.
+    fn moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for x in 0..HALLWAY {
+            let a = self.hallway[x];
+            if a == EMPTY {
+                continue;
+            }
+            if let Some(to) = self
+                .destination(a)
+                .filter(|to| self.hallway_clear(x, to.x()))
+            {
+                moves.push(self.make_move(Pos::Hallway(x), to));
+            }
+        }
+        for (r, &entrance) in ENTRANCES.iter().enumerate() {
+            if self.room_ready(r) {
+                continue;
+            }
+            let Some(slot) = (0..self.depth).find(|s| self.rooms[r][*s] != EMPTY) else {
+                continue;
+            };
+            let from = Pos::Room(r, slot);
+            let a = self.get(from);
+            let direct = self.destination(a).filter(|to| {
+                self.hallway[entrance] == EMPTY && self.hallway_clear(entrance, to.x())
+            });
+            if let Some(to) = direct {
+                moves.push(self.make_move(from, to));
+                continue;
+            }
+            for x in (0..HALLWAY).filter(|x| !ENTRANCES.contains(x)) {
+                if self.hallway_clear(entrance, x) && self.hallway[x] == EMPTY {
+                    moves.push(self.make_move(from, Pos::Hallway(x)));
+                }
+            }
+        }
+        moves
+    }
+
+    fn apply(&self, m: &Move) -> Burrow {
+        let mut b = *self;
+        let a = b.get(m.from);
+        b.set(m.from, EMPTY);
+        b.set(m.to, a);
+        b
+    }
+
+    // Lower bound on the energy still needed: every amphipod that isn't settled has to at least
+    // walk to the top of its room.
+    fn estimate(&self) -> usize {
+        let mut total = 0;
+        for x in 0..HALLWAY {
+            let a = self.hallway[x];
+            if a != EMPTY {
+                total += (x.abs_diff(ENTRANCES[room_for(a)]) + 1) * energy(a);
+            }
+        }
+        for (r, room) in self.rooms.iter().enumerate() {
+            for s in 0..self.depth {
+                let a = room[s];
+                if a == EMPTY {
+                    continue;
+                }
+                let settled = room[s..self.depth].iter().all(|c| room_for(*c) == r);
+                if !settled {
+                    let across = ENTRANCES[r].abs_diff(ENTRANCES[room_for(a)]).max(2);
+                    total += (s + 1 + across + 1) * energy(a);
+                }
+            }
+        }
+        total
+    }
+
+    /// Finds the least energy needed to organize the amphipods, with the moves that do it.
+    fn organize(&self) -> Option<(usize, Vec<Move>)> {
+        let mut best = HashMap::from([(*self, 0)]);
+        let mut came_from: HashMap<Burrow, (Burrow, Move)> = HashMap::new();
+        let mut heap = BinaryHeap::from([Reverse((self.estimate(), 0, *self))]);
+        while let Some(Reverse((_, cost, b))) = heap.pop() {
+            if b.is_organized() {
+                let mut moves = Vec::new();
+                let mut cur = b;
+                while let Some((prev, m)) = came_from.get(&cur) {
+                    moves.push(*m);
+                    cur = *prev;
+                }
+                moves.reverse();
+                return Some((cost, moves));
+            }
+            if cost > best[&b] {
+                continue;
+            }
+            for m in b.moves() {
+                let next = b.apply(&m);
+                let next_cost = cost + m.energy;
+                if best.get(&next).is_none_or(|c| next_cost < *c) {
+                    best.insert(next, next_cost);
+                    came_from.insert(next, (b, m));
+                    heap.push(Reverse((next_cost + next.estimate(), next_cost, next)));
+                }
+            }
+        }
+        None
+    }
+}
+
 #[aoc(day23, part1)]
 fn part1(input: &str) -> Result<usize> {
-    todo!("part1");
-    Ok(0)
+    let burrow: Burrow = input.parse()?;
+    let (energy, _) = burrow.organize().ok_or(anyhow::anyhow!("no solution"))?;
+    Ok(energy)
 }
 
-/*
 #[aoc(day23, part2)]
 fn part2(input: &str) -> Result<usize> {
-    todo!("part2");
-    Ok(0)
+    let burrow: Burrow = input.parse()?;
+    let (energy, _) = burrow
+        .unfold()?
+        .organize()
+        .ok_or(anyhow::anyhow!("no solution"))?;
+    Ok(energy)
 }
-*/
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const INPUT: &str = r#"#############
+#...........#
+###B#C#B#D###
+  #A#D#C#A#
+  #########"#;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let b: Burrow = INPUT.parse()?;
+        assert_eq!(b.to_string(), INPUT);
+        assert_eq!(
+            b.unfold()?.to_string(),
+            r#"#############
+#...........#
+###B#C#B#D###
+  #D#C#B#A#
+  #D#B#A#C#
+  #A#D#C#A#
+  #########"#
+        );
+        assert_eq!(
+            "#...........#\n###B#C#B#E###".parse::<Burrow>(),
+            Err(BurrowError::UnknownAmphipod('E'))
+        );
+        assert_eq!(
+            "#...........#\n###B#C#B#B###".parse::<Burrow>(),
+            Err(BurrowError::WrongCount(1))
+        );
+        // Already unfolded diagrams parse, but can't be unfolded again.
+        let unfolded = INPUT.parse::<Burrow>()?.unfold()?;
+        assert_eq!(unfolded.unfold(), Err(BurrowError::CantUnfold(4)));
+        assert!(part2(&unfolded.to_string()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_moves() -> Result<()> {
+        let b: Burrow = INPUT.parse()?;
+        // Only the top amphipod of each room can leave, to any of the 7 hallway spots.
+        assert_eq!(b.moves().len(), 4 * 7);
+        let b: Burrow = r#"#############
+#...B.......#
+###B#C#.#D###
+  #A#D#C#A#
+  #########"#
+            .parse()?;
+        // C can go straight from room B to room C.
+        assert!(b.moves().contains(&Move {
+            amphipod: 'C',
+            from: Pos::Room(1, 0),
+            to: Pos::Room(2, 0),
+            energy: 400,
+        }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay() -> Result<()> {
+        let start: Burrow = INPUT.parse()?;
+        let (energy, moves) = start.organize().unwrap();
+        assert_eq!(energy, 12521);
+        let mut b = start;
+        for m in &moves {
+            assert!(b.moves().contains(m), "illegal move {m}\n{b}");
+            b = b.apply(m);
+        }
+        assert!(b.is_organized());
+        assert_eq!(moves.iter().map(|m| m.energy).sum::<usize>(), 12521);
+        assert_eq!(
+            b.to_string(),
+            r#"#############
+#...........#
+###A#B#C#D###
+  #A#B#C#D#
+  #########"#
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_part1() -> Result<()> {
-        let input = r#"
-"#
-        .trim();
-        assert_eq!(part1(input)?, usize::MAX);
+        assert_eq!(part1(INPUT)?, 12521);
         Ok(())
     }
 
-    /*
     #[test]
-    fn test_part2()->Result<()> {
-        let input = r#"
-    "#
-        .trim();
-        assert_eq!(part2(input)?, usize::MAX);
-    Ok(())
-    }
-    */
+    fn test_part2() -> Result<()> {
+        assert_eq!(part2(INPUT)?, 44169);
+        Ok(())
+    }
 }
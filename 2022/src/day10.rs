@@ -1,83 +1,115 @@
+use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
-#[aoc(day10, part1)]
-fn part1(input: &str) -> i32 {
-    let mut it = input.lines();
-    let mut x = 1;
-    let mut sum = 0;
-    let mut add: Option<i32> = None;
-    for cycle in 1i32.. {
-        //if cycle > 180 { println!("{sum} {cycle} {x} {add:?}"); }
-        if (cycle - 20) % 40 == 0 {
-            //dbg!((sum, cycle, x, x * cycle));
-            sum += cycle * x;
-            if cycle == 220 {
-                break;
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Instruction {
+    Noop,
+    Addx(i32),
+}
+
+#[derive(Debug, Error)]
+enum ParseError {
+    #[error("line {0}: unknown opcode '{1}'")]
+    UnknownOpcode(usize, String),
+    #[error("line {0}: addx missing argument")]
+    MissingArgument(usize),
+    #[error("line {0}: couldn't parse addx argument: {1}")]
+    BadArgument(usize, ParseIntError),
+}
+
+fn parse(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, l)| {
+            let line = i + 1;
+            match l.split_once(' ') {
+                None if l == "noop" => Ok(Instruction::Noop),
+                None if l == "addx" => Err(ParseError::MissingArgument(line)),
+                Some(("addx", v)) => v
+                    .parse()
+                    .map(Instruction::Addx)
+                    .map_err(|e| ParseError::BadArgument(line, e)),
+                _ => Err(ParseError::UnknownOpcode(line, l.to_string())),
             }
-        }
-        if let Some(add) = add.take() {
-            x += add;
-            continue;
-        }
+        })
+        .collect()
+}
 
-        let s = it.next();
-        //if cycle > 180 { println!("{s:?}"); }
-        match s {
-            Some(s) => match s {
-                "noop" => (),
-                addx => {
-                    let v = addx[addx.find(' ').expect("no space") + 1..]
-                        .parse()
-                        .expect("not a number");
-                    add = Some(v);
-                }
-            },
-            None => break,
+/// Iterates over `(cycle, x)` with the value of the X register *during* each cycle, starting at
+/// cycle 1.
+struct Cpu<'a> {
+    program: &'a [Instruction],
+    pc: usize,
+    cycle: i32,
+    x: i32,
+    // addx waiting for its second cycle
+    pending: Option<i32>,
+    // addx whose second cycle just ended, applied before the next cycle starts
+    finishing: Option<i32>,
+}
+
+impl<'a> Cpu<'a> {
+    fn new(program: &'a [Instruction]) -> Cpu<'a> {
+        Cpu {
+            program,
+            pc: 0,
+            cycle: 0,
+            x: 1,
+            pending: None,
+            finishing: None,
         }
     }
-    sum
 }
 
-#[aoc(day10, part2)]
-fn part2(input: &str) -> String {
-    let mut it = input.lines();
-    let mut x = 1;
-    let mut output = Vec::with_capacity(4096);
-    let mut add: Option<i32> = None;
-    for cycle in 1i32.. {
-        let beam = (cycle - 1) % 40;
-        if (beam - x).abs() <= 1 {
-            output.push('#');
-        } else {
-            output.push('.');
+impl Iterator for Cpu<'_> {
+    type Item = (i32, i32);
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(v) = self.finishing.take() {
+            self.x += v;
         }
-        if let Some(add) = add.take() {
-            x += add;
-            continue;
+        if let Some(v) = self.pending.take() {
+            self.finishing = Some(v);
+        } else {
+            match self.program.get(self.pc)? {
+                Instruction::Noop => (),
+                Instruction::Addx(v) => self.pending = Some(*v),
+            }
+            self.pc += 1;
         }
+        self.cycle += 1;
+        Some((self.cycle, self.x))
+    }
+}
 
-        match it.next() {
-            Some(s) => match s {
-                "noop" => (),
-                addx => {
-                    let v = addx[addx.find(' ').expect("no space") + 1..]
-                        .parse()
-                        .expect("not a number");
-                    add = Some(v);
-                }
-            },
-            None => break,
-        }
+const CRT_WIDTH: usize = 40;
+const CRT_HEIGHT: usize = 6;
+
+/// Draws a pixel wherever the 3 pixel wide sprite centered on X overlaps the beam.
+fn crt(program: &[Instruction]) -> Image<bool> {
+    let mut im = Image::new(CRT_WIDTH, CRT_HEIGHT, false);
+    for (cycle, x) in Cpu::new(program).take(CRT_WIDTH * CRT_HEIGHT) {
+        let pixel = (cycle - 1) as usize;
+        let (col, row) = (pixel % CRT_WIDTH, pixel / CRT_WIDTH);
+        im[(col, row)] = (col as i32 - x).abs() <= 1;
     }
-    output.truncate(output.len() - 1);
-    format!(
-        "\n{}",
-        output
-            .chunks(40)
-            .map(|c| c.iter().collect::<String>())
-            .collect::<Vec<String>>()
-            .join("\n")
-    )
+    im
+}
+
+#[aoc(day10, part1)]
+fn part1(input: &str) -> i32 {
+    let program = parse(input).expect("failed to parse program");
+    Cpu::new(&program)
+        .take_while(|(cycle, _)| *cycle <= 220)
+        .filter(|(cycle, _)| (cycle - 20) % 40 == 0)
+        .map(|(cycle, x)| cycle * x)
+        .sum()
+}
+
+#[aoc(day10, part2)]
+fn part2(input: &str) -> Result<String> {
+    let program = parse(input)?;
+    Ok(advent::ocr::recognize(&crt(&program))?)
 }
 
 #[cfg(test)]
@@ -90,7 +122,23 @@ mod tests {
     }
 
     #[test]
-    fn p2() {
+    fn bad_opcode() {
+        assert!(matches!(
+            parse("noop\nmulx 3"),
+            Err(ParseError::UnknownOpcode(2, _))
+        ));
+        assert!(matches!(parse("addx"), Err(ParseError::MissingArgument(1))));
+    }
+
+    #[test]
+    fn cpu() {
+        let program = parse("noop\naddx 3\naddx -5").unwrap();
+        let states: Vec<_> = Cpu::new(&program).collect();
+        assert_eq!(states, vec![(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]);
+    }
+
+    #[test]
+    fn crt_raster() {
         let want = r#"
 ##..##..##..##..##..##..##..##..##..##..
 ###...###...###...###...###...###...###.
@@ -98,7 +146,18 @@ mod tests {
 #####.....#####.....#####.....#####.....
 ######......######......######......####
 #######.......#######.......#######....."#;
-        assert_eq!(part2(INPUT), want);
+        let im = crt(&parse(INPUT).unwrap());
+        let mut got = String::new();
+        for y in 0..im.height {
+            got.push('\n');
+            got.extend((0..im.width).map(|x| if im[(x, y)] { '#' } else { '.' }));
+        }
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn p2() {
+        assert_eq!(part2(&input_for(2022, 10)).unwrap(), "PHLHJGZA");
     }
     const INPUT: &'static str = r#"addx 15
 addx -11
@@ -1,309 +1,272 @@
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
-// ### Grid 1 Start //
-#[derive(Clone, Debug)]
-struct Grid {
-    cells: HashMap<(usize, usize), char>,
-    width: Range<usize>,
-    height: Range<usize>,
-    start: (usize, usize),
+const SOURCE: (usize, usize) = (500, 0);
+
+#[derive(Debug, Error, PartialEq)]
+enum CaveError {
+    #[error("expected 'x,y', got {0:?}")]
+    BadPoint(String),
+    #[error("path from {0:?} to {1:?} isn't horizontal or vertical")]
+    Diagonal((usize, usize), (usize, usize)),
 }
 
-impl FromStr for Grid {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, ()> {
-        let start = (500, 0);
-        let mut cells: HashMap<(usize, usize), char> = HashMap::new();
-        s.lines().for_each(|l| {
-            println!("*******************");
-            dbg!(&l);
-            l.split(" -> ")
-                .collect::<Vec<_>>()
-                .windows(2)
-                .for_each(|se| {
-                    let s: (usize, usize) = se[0]
-                        .split_once(',')
-                        .map(|(x, y)| (x.parse().expect("s x"), y.parse().expect("s y")))
-                        .expect("s");
-                    let e: (usize, usize) = se[1]
-                        .split_once(',')
-                        .map(|(x, y)| (x.parse().expect("e x"), y.parse().expect("e y")))
-                        .expect("e");
-                    let start = (s.0.min(e.0), s.1.min(e.1));
-                    let end = (s.0.max(e.0) + 1, s.1.max(e.1) + 1);
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Cell {
+    Rock,
+    Sand,
+}
 
-                    println!("drawing  {:?} -> {:?}", &start, &end);
-                    for y in start.1..end.1 {
-                        for x in start.0..end.0 {
-                            println!("inserting @ {x:?} {y:?}");
-                            cells.insert((x, y), '#');
-                        }
-                    }
-                });
-        });
-        let (width, height) = cells.keys().chain([&start]).fold(
-            ((usize::MAX..0), (usize::MAX..0)),
-            |(w, h): (Range<usize>, Range<usize>), c: &(usize, usize)| {
-                (
-                    c.0.min(w.start)..c.0.max(w.end),
-                    c.1.min(h.start)..c.1.max(h.end),
-                )
-            },
-        );
-        Ok(Grid {
-            cells,
-            width,
-            height,
-            start,
-        })
-    }
+/// Sparse cave scan, sand pours in from `source` and either comes to rest or, without a floor,
+/// falls past the lowest rock into the abyss.
+#[derive(Clone, Debug)]
+struct Cave {
+    cells: HashMap<(usize, usize), Cell>,
+    source: (usize, usize),
+    // Lowest rock.
+    max_y: usize,
+    floor: Option<usize>,
+    // Where each grain came to rest, in order.
+    rests: Vec<(usize, usize)>,
+}
+
+fn parse_point(s: &str) -> Result<(usize, usize), CaveError> {
+    let bad = || CaveError::BadPoint(s.to_string());
+    let (x, y) = s.split_once(',').ok_or_else(bad)?;
+    Ok((
+        x.trim().parse().map_err(|_| bad())?,
+        y.trim().parse().map_err(|_| bad())?,
+    ))
 }
-impl fmt::Display for Grid {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "w {:?} h {:?}", self.width, self.height)?;
-        for y in self.height.start..self.height.end + 1 {
-            for x in self.width.start..self.width.end + 1 {
-                if (x, y) == self.start {
-                    write!(f, "+")?;
-                    continue;
+
+impl FromStr for Cave {
+    type Err = CaveError;
+    fn from_str(s: &str) -> Result<Self, CaveError> {
+        let mut cells = HashMap::new();
+        for l in s.lines() {
+            let points = l
+                .split(" -> ")
+                .map(parse_point)
+                .collect::<Result<Vec<_>, _>>()?;
+            if let [p] = points[..] {
+                cells.insert(p, Cell::Rock);
+            }
+            for w in points.windows(2) {
+                let (s, e) = (w[0], w[1]);
+                if s.0 != e.0 && s.1 != e.1 {
+                    return Err(CaveError::Diagonal(s, e));
                 }
-                match self.cells.get(&(x, y)) {
-                    Some(c) => write!(f, "{}", *c)?,
-                    None => write!(f, ".")?,
+                for y in s.1.min(e.1)..=s.1.max(e.1) {
+                    for x in s.0.min(e.0)..=s.0.max(e.0) {
+                        cells.insert((x, y), Cell::Rock);
+                    }
                 }
             }
-            writeln!(f)?;
         }
-        Ok(())
+        let max_y = cells.keys().map(|(_, y)| *y).max().unwrap_or(0);
+        Ok(Cave {
+            cells,
+            source: SOURCE,
+            max_y,
+            floor: None,
+            rests: Vec::new(),
+        })
     }
 }
-impl Grid {
-    fn drop(&mut self) -> bool {
-        self.drop_rec(self.start).is_some()
-    }
-
-    // Returns
-    //   None if we reach past the bottom
-    //   Some(v) if we hit something, v indicates what was hit
-    fn drop_rec(&mut self, xy: (usize, usize)) -> Option<char> {
-        //println!("xy {xy:?}");
-        if xy.1 > self.height.end {
-            // Fell off the bottom
-            return None;
-        }
 
-        let something_here = self.cells.contains_key(&xy);
-        if something_here {
-            return Some(self.cells[&xy]);
+impl Display for Cave {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (mut min_x, mut max_x) = (self.source.0, self.source.0);
+        let mut max_y = self.source.1;
+        for (x, y) in self.cells.keys() {
+            min_x = min_x.min(*x);
+            max_x = max_x.max(*x);
+            max_y = max_y.max(*y);
         }
-
-        let down = (xy.0, xy.1 + 1);
-        let down_left = (xy.0 - 1, xy.1 + 1);
-        let down_right = (xy.0 + 1, xy.1 + 1);
-        let something_below = self.cells.contains_key(&down);
-        if something_below {
-            let something_down_left = self.cells.contains_key(&down_left);
-            if !something_down_left {
-                return self.drop_rec(down_left);
+        let max_y = self.floor.unwrap_or(max_y);
+        for y in 0..=max_y {
+            for x in min_x..=max_x {
+                let c = match self.cells.get(&(x, y)) {
+                    _ if Some(y) == self.floor => '#',
+                    Some(Cell::Rock) => '#',
+                    Some(Cell::Sand) => 'o',
+                    None if (x, y) == self.source => '+',
+                    None => '.',
+                };
+                write!(f, "{c}")?;
             }
-
-            let something_down_right = self.cells.contains_key(&down_right);
-            if !something_down_right {
-                return self.drop_rec(down_right);
+            if y < max_y {
+                writeln!(f)?;
             }
-
-            self.cells.insert(xy, 'o');
-            return Some('o');
-        } else {
-            return self.drop_rec(down);
         }
+        Ok(())
+    }
+}
 
-        None
+impl Cave {
+    /// Adds an infinite horizontal floor two below the lowest rock.
+    fn with_floor(mut self) -> Cave {
+        self.floor = Some(self.max_y + 2);
+        self
     }
 
-    fn count_sand(&self) -> usize {
-        self.cells.values().filter(|&&c| c == 'o').count()
+    fn blocked(&self, p: (usize, usize)) -> bool {
+        self.floor == Some(p.1) || self.cells.contains_key(&p)
     }
-}
-// ### Grid 1 End //
 
-// ### Grid 2 Start //
-#[derive(Clone, Debug)]
-struct Grid2 {
-    cells: HashMap<(usize, usize), char>,
-    width: Range<usize>,
-    height: Range<usize>,
-    start: (usize, usize),
-}
+    fn past_rocks(&self, p: (usize, usize)) -> bool {
+        self.floor.is_none() && p.1 > self.max_y
+    }
 
-impl FromStr for Grid2 {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, ()> {
-        let start = (500, 0);
-        let mut cells: HashMap<(usize, usize), char> = HashMap::new();
-        s.lines().for_each(|l| {
-            println!("*******************");
-            dbg!(&l);
-            l.split(" -> ")
-                .collect::<Vec<_>>()
-                .windows(2)
-                .for_each(|se| {
-                    let s: (usize, usize) = se[0]
-                        .split_once(',')
-                        .map(|(x, y)| (x.parse().expect("s x"), y.parse().expect("s y")))
-                        .expect("s");
-                    let e: (usize, usize) = se[1]
-                        .split_once(',')
-                        .map(|(x, y)| (x.parse().expect("e x"), y.parse().expect("e y")))
-                        .expect("e");
-                    let start = (s.0.min(e.0), s.1.min(e.1));
-                    let end = (s.0.max(e.0) + 1, s.1.max(e.1) + 1);
+    // Where a grain at `p` moves next, or None if it comes to rest.
+    fn fall(&self, (x, y): (usize, usize)) -> Option<(usize, usize)> {
+        [Some(x), x.checked_sub(1), Some(x + 1)]
+            .into_iter()
+            .flatten()
+            .map(|x| (x, y + 1))
+            .find(|p| !self.blocked(*p))
+    }
 
-                    println!("drawing  {:?} -> {:?}", &start, &end);
-                    for y in start.1..end.1 {
-                        for x in start.0..end.0 {
-                            println!("inserting @ {x:?} {y:?}");
-                            cells.insert((x, y), '#');
-                        }
-                    }
-                });
-        });
-        let (width, height) = cells.keys().chain([&start]).fold(
-            ((usize::MAX..0), (usize::MAX..0)),
-            |(w, h): (Range<usize>, Range<usize>), c: &(usize, usize)| {
-                (
-                    c.0.min(w.start)..c.0.max(w.end),
-                    c.1.min(h.start)..c.1.max(h.end),
-                )
-            },
-        );
-        Ok(Grid2 {
-            cells,
-            width,
-            height,
-            start,
-        })
+    fn rest(&mut self, p: (usize, usize)) {
+        self.cells.insert(p, Cell::Sand);
+        self.rests.push(p);
     }
-}
-impl fmt::Display for Grid2 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let (width, height) = self.cells.keys().chain([&self.start]).fold(
-            ((usize::MAX..0), (usize::MAX..0)),
-            |(w, h): (Range<usize>, Range<usize>), c: &(usize, usize)| {
-                (
-                    c.0.min(w.start)..c.0.max(w.end),
-                    c.1.min(h.start)..c.1.max(h.end),
-                )
-            },
-        );
-        writeln!(f, "w {:?} h {:?} c {}", width, height, self.count_sand())?;
-        for y in height.start..height.end + 1 {
-            for x in width.start - 0..width.end + 1 {
-                if (x, y) == self.start {
-                    write!(f, "+")?;
-                    continue;
-                }
-                match self.cells.get(&(x, y)) {
-                    Some(c) => write!(f, "{}", *c)?,
-                    None => write!(f, ".")?,
+
+    /// Drops a single grain from the source, returning where it comes to rest, or None if it
+    /// falls into the abyss or the source is already covered.
+    fn drop_grain(&mut self) -> Option<(usize, usize)> {
+        let mut p = self.source;
+        if self.blocked(p) {
+            return None;
+        }
+        while !self.past_rocks(p) {
+            match self.fall(p) {
+                Some(next) => p = next,
+                None => {
+                    self.rest(p);
+                    return Some(p);
                 }
             }
-            writeln!(f)?;
         }
-        Ok(())
-    }
-}
-impl Grid2 {
-    fn drop(&mut self) -> bool {
-        self.drop_rec(self.start).is_some()
+        None
     }
 
-    // Returns
-    //   None if we reach past the bottom
-    //   Some(v) if we hit something, v indicates what was hit
-    fn drop_rec(&mut self, xy: (usize, usize)) -> Option<char> {
-        //println!("xy {xy:?}");
-        if xy.1 == self.height.end + 2 {
-            self.cells.insert(xy, '#');
-        }
-        let something_here = self.cells.contains_key(&xy);
-        if something_here {
-            if xy == self.start {
-                return None;
+    /// Pours sand until it starts falling into the abyss or covers the source, returning the
+    /// number of grains that came to rest. Same result as calling `drop_grain` until it returns
+    /// None, but each grain picks up where the last left its path instead of starting over.
+    fn fill(&mut self) -> usize {
+        let before = self.rests.len();
+        let mut path = vec![self.source];
+        while let Some(&p) = path.last() {
+            if self.blocked(p) {
+                path.pop();
+                continue;
             }
-            return Some(self.cells[&xy]);
-        }
-
-        let down = (xy.0, xy.1 + 1);
-        let down_left = (xy.0 - 1, xy.1 + 1);
-        let down_right = (xy.0 + 1, xy.1 + 1);
-        let something_below = self.cells.contains_key(&down);
-        if something_below {
-            let something_down_left = self.cells.contains_key(&down_left);
-            if !something_down_left {
-                return self.drop_rec(down_left);
+            if self.past_rocks(p) {
+                break;
             }
-
-            let something_down_right = self.cells.contains_key(&down_right);
-            if !something_down_right {
-                return self.drop_rec(down_right);
+            match self.fall(p) {
+                Some(next) => path.push(next),
+                None => {
+                    self.rest(p);
+                    path.pop();
+                }
             }
-
-            self.cells.insert(xy, 'o');
-            return Some('o');
-        } else {
-            return self.drop_rec(down);
         }
-
-        unreachable!();
-    }
-
-    fn count_sand(&self) -> usize {
-        self.cells.values().filter(|&&c| c == 'o').count()
+        self.rests.len() - before
     }
 }
-// ### Grid 2 End //
 
 #[aoc(day14, part1)]
-fn part1(input: &str) -> usize {
-    let mut g: Grid = input.parse().expect("grid");
-    println!("g:\n{g}");
-    for i in 0.. {
-        if !g.drop() {
-            println!("g{i}\n{g}");
-            return g.count_sand();
-        }
-    }
-    unreachable!()
+fn part1(input: &str) -> Result<usize> {
+    let mut cave: Cave = input.parse()?;
+    Ok(cave.fill())
 }
 
 #[aoc(day14, part2)]
-fn part2(input: &str) -> usize {
-    let mut g: Grid2 = input.parse().expect("grid");
-    println!("g:\n{g}");
-    for i in 0.. {
-        if !g.drop() {
-            println!("g{i}\n{g}");
-            return g.count_sand();
-        }
-    }
-    unreachable!()
+fn part2(input: &str) -> Result<usize> {
+    let mut cave = input.parse::<Cave>()?.with_floor();
+    Ok(cave.fill())
+}
+
+#[aoc(day14, part1, slow)]
+fn part1_slow(input: &str) -> Result<usize> {
+    let mut cave: Cave = input.parse()?;
+    Ok(std::iter::from_fn(|| cave.drop_grain()).count())
+}
+
+#[aoc(day14, part2, slow)]
+fn part2_slow(input: &str) -> Result<usize> {
+    let mut cave = input.parse::<Cave>()?.with_floor();
+    Ok(std::iter::from_fn(|| cave.drop_grain()).count())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const INPUT: &'static str = r#"498,4 -> 498,6 -> 496,6
+    const INPUT: &str = r#"498,4 -> 498,6 -> 496,6
 503,4 -> 502,4 -> 502,9 -> 494,9"#;
 
     #[test]
-    fn p1() {
-        assert_eq!(part1(INPUT), 24);
+    fn p1() -> Result<()> {
+        assert_eq!(part1(INPUT)?, 24);
+        assert_eq!(part1_slow(INPUT)?, 24);
+        Ok(())
+    }
+
+    #[test]
+    fn p2() -> Result<()> {
+        assert_eq!(part2(INPUT)?, 93);
+        assert_eq!(part2_slow(INPUT)?, 93);
+        Ok(())
+    }
+
+    #[test]
+    fn render() -> Result<()> {
+        let mut cave: Cave = INPUT.parse()?;
+        cave.fill();
+        assert_eq!(&cave.rests[..3], &[(500, 8), (499, 8), (501, 8)]);
+        assert_eq!(
+            cave.to_string(),
+            r#"......+...
+..........
+......o...
+.....ooo..
+....#ooo##
+...o#ooo#.
+..###ooo#.
+....oooo#.
+.o.ooooo#.
+#########."#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fast_matches_slow() -> Result<()> {
+        for floor in [false, true] {
+            let mut fast: Cave = INPUT.parse()?;
+            if floor {
+                fast = fast.with_floor();
+            }
+            let mut slow = fast.clone();
+            fast.fill();
+            while slow.drop_grain().is_some() {}
+            assert_eq!(fast.rests, slow.rests);
+            assert_eq!(fast.drop_grain(), None);
+        }
+        Ok(())
     }
 
     #[test]
-    fn p2() {
-        assert_eq!(part2(INPUT), 93);
+    fn errors() {
+        assert_eq!(
+            "498,4 -> 498".parse::<Cave>().unwrap_err(),
+            CaveError::BadPoint("498".into())
+        );
+        assert_eq!(
+            "1,1 -> 2,2".parse::<Cave>().unwrap_err(),
+            CaveError::Diagonal((1, 1), (2, 2))
+        );
     }
 }
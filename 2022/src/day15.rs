@@ -1,304 +1,276 @@
-use std::time::Instant;
-
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
-#[derive(Clone, Default, Eq, PartialEq)]
-struct Bitset {
-    sets: HashMap<isize, u64>,
+#[derive(Debug, Error, PartialEq)]
+enum SensorError {
+    #[error("line {0}: expected 'Sensor at x=N, y=N: closest beacon is at x=N, y=N'")]
+    BadLine(usize),
 }
 
-impl fmt::Debug for Bitset {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let (min, max) = self
-            .sets
-            .keys()
-            .fold((isize::MAX, isize::MIN), |(min, max), v| {
-                (min.min(*v), max.max(*v))
-            });
-        for i in min..max {
-            if i == 0 {
-                write!(f, "|")?;
-            }
-            write!(f, "{:064b}", self.sets[&i])?;
-        }
-        Ok(())
-    }
+fn manhattan_distance(p1: (isize, isize), p2: (isize, isize)) -> isize {
+    (p1.0 - p2.0).abs() + (p1.1 - p2.1).abs()
 }
 
-impl FromIterator<isize> for Bitset {
-    fn from_iter<I: IntoIterator<Item = isize>>(iter: I) -> Self {
-        let mut bs = Bitset::default();
-        for i in iter {
-            bs.set(i);
-        }
-        bs
-    }
+/// A sensor covers the diamond of positions no further from it than its closest beacon.
+#[derive(Debug, PartialEq)]
+struct Sensor {
+    pos: (isize, isize),
+    beacon: (isize, isize),
+    radius: isize,
 }
 
-impl Bitset {
-    fn is_empty(&self) -> bool {
-        self.sets.is_empty()
+impl Sensor {
+    fn covers(&self, p: (isize, isize)) -> bool {
+        manhattan_distance(self.pos, p) <= self.radius
     }
-    fn set(&mut self, v: isize) {
-        let idx = if v >= 0 { v / 64 } else { (v / 64) - 1 };
-        let bit: u64 = 1 << ((v.abs() % 64) as u64);
-        self.sets
-            .entry(idx)
-            .and_modify(|v| *v |= bit)
-            .or_insert(bit);
-    }
-    // TODO(wathiede): add set_rng that can optimize by doing the divide once, and potentially set
-    // 64 bits at a time.
-    fn contains(&self, v: isize) -> bool {
-        let idx = if v >= 0 { v / 64 } else { (v / 64) - 1 };
-        let bit: u64 = 1u64 << ((v.abs() % 64) as u64);
-        if !self.sets.contains_key(&idx) {
-            return false;
-        }
-        //if v < 0 { println!( "v {idx} {} sets {:064b} bit {:064b}", v, self.sets[&idx], bit); }
-        (self.sets[&idx] & bit) != 0
-    }
-    fn len(&self) -> usize {
-        self.sets
-            .values()
-            .map(|v| v.count_ones())
-            .sum::<u32>()
-            .try_into()
-            .unwrap()
+
+    fn row_span(&self, y: isize) -> Option<RangeInclusive<isize>> {
+        let half = self.radius - (self.pos.1 - y).abs();
+        (half >= 0).then(|| self.pos.0 - half..=self.pos.0 + half)
     }
-    fn truncate(&mut self, max: usize) {
-        let idx: isize = (max / 64).try_into().unwrap();
-        let mut sets: HashMap<_, _> = std::mem::take(&mut self.sets)
-            .into_iter()
-            .filter(|(k, v)| *k >= 0 && *k <= idx)
-            .collect();
-        let bit: u64 = 1u64 << ((max % 64) as u64);
-        let mask = if bit == 63 { !0 } else { (bit) - 1 };
-        println!("{max:2} {} {:064b} {:064b}", idx, bit, mask);
-        sets.iter().for_each(|(idx, b)| {
-            println!("sets {idx:2} {b:064b}");
-        });
-        sets.entry(idx).and_modify(|v| *v &= mask);
-        // If none of the bits are set in the last element, truncate it too.
-        if sets[&idx] == 0 {
-            sets.remove(&idx);
-        }
-        self.sets = sets;
+
+    // Lines hugging the outside of the diamond, as the constants c in x + y = c and x - y = c.
+    fn border_lines(&self) -> ([isize; 2], [isize; 2]) {
+        let (x, y) = self.pos;
+        let r = self.radius + 1;
+        ([x + y - r, x + y + r], [x - y - r, x - y + r])
     }
 }
 
 #[derive(Debug)]
-struct Grid {
-    cells: HashMap<(isize, isize), char>,
-    not_becons: HashMap<isize, Bitset>,
+struct Report {
+    sensors: Vec<Sensor>,
 }
 
-impl FromStr for Grid {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, ()> {
-        let mut cells: HashMap<(isize, isize), char> = HashMap::new();
-        let mut not_becons: HashMap<isize, Bitset> = HashMap::new();
-        s.lines().for_each(|l| {
-            let parts = l
-                .split(|c: char| !c.is_digit(10) && c != '-')
-                .filter(|s| !s.is_empty())
-                .map(|s| s.parse::<isize>().expect("number"))
-                .collect::<Vec<_>>();
-            let sx = parts[0];
-            let sy = parts[1];
-            let bx = parts[2];
-            let by = parts[3];
-            cells.insert((sx, sy), 'S');
-            cells.insert((bx, by), 'B');
-            let r = manhattan_distance((sx, sy), (bx, by));
-            //if sx == 8 && sy == 7 {
-            {
-                for y in 0..r + 1 {
-                    let xd = r - y;
-                    not_becons
-                        .entry(sy - y)
-                        .and_modify(|row| (sx - xd..sx + xd + 1).for_each(|x| row.set(x)))
-                        .or_insert((sx - xd..sx + xd + 1).collect());
-                    not_becons
-                        .entry(sy + y)
-                        .and_modify(|row| (sx - xd..sx + xd + 1).for_each(|x| row.set(x)))
-                        .or_insert((sx - xd..sx + xd + 1).collect());
+impl FromStr for Report {
+    type Err = SensorError;
+    fn from_str(s: &str) -> Result<Self, SensorError> {
+        let sensors = s
+            .lines()
+            .enumerate()
+            .map(|(i, l)| {
+                let nums = l
+                    .split(|c: char| !c.is_ascii_digit() && c != '-')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.parse::<isize>())
+                    .collect::<Result<Vec<_>, _>>();
+                match nums.as_deref() {
+                    Ok(&[sx, sy, bx, by]) => Ok(Sensor {
+                        pos: (sx, sy),
+                        beacon: (bx, by),
+                        radius: manhattan_distance((sx, sy), (bx, by)),
+                    }),
+                    _ => Err(SensorError::BadLine(i + 1)),
                 }
-            }
-        });
-        Ok(Grid { cells, not_becons })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Report { sensors })
     }
 }
 
-fn manhattan_distance(p1: (isize, isize), p2: (isize, isize)) -> isize {
-    (p1.0 - p2.0).abs() + (p1.1 - p2.1).abs()
-}
-
-impl fmt::Display for Grid {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let (min_no_beacons, max_no_beacons) = self
-            .not_becons
-            .keys()
-            .fold((isize::MAX, 0), |(min, max), k| (min.min(*k), max.max(*k)));
-        let (width, height) = self.cells.keys().fold(
-            ((isize::MAX..isize::MIN), (isize::MAX..isize::MIN)),
-            |(w, h): (Range<isize>, Range<isize>), c: &(isize, isize)| {
-                (
-                    c.0.min(w.start)..c.0.max(w.end),
-                    c.1.min(h.start).min(min_no_beacons)..c.1.max(h.end).max(max_no_beacons),
-                )
-            },
-        );
-        writeln!(f, "w {:?} h {:?}", width, height)?;
-        for y in height.start..height.end + 1 {
-            let empty = Bitset::default();
-            let row = self.not_becons.get(&y).unwrap_or(&empty);
-            for x in width.start..width.end + 1 {
-                match self.cells.get(&(x, y)) {
-                    Some(c) => write!(f, "{}", *c)?,
-                    None => {
-                        if row.contains(x) {
-                            write!(f, "#")?;
-                        } else {
-                            write!(f, ".")?;
-                        }
-                    }
-                }
+// Sorts and merges overlapping or touching ranges.
+fn merge(mut ranges: Vec<RangeInclusive<isize>>) -> Vec<RangeInclusive<isize>> {
+    ranges.sort_by_key(|r| *r.start());
+    let mut merged: Vec<RangeInclusive<isize>> = Vec::with_capacity(ranges.len());
+    for r in ranges {
+        match merged.last_mut() {
+            Some(last) if *r.start() <= *last.end() + 1 => {
+                *last = *last.start()..=*last.end().max(r.end());
             }
-            writeln!(f)?;
+            _ => merged.push(r),
         }
-        Ok(())
     }
+    merged
 }
-impl Grid {
-    fn row_occupancy(&self, row: isize) -> usize {
-        self.not_becons[&row].len() - self.cells.keys().filter(|(_, y)| *y == row).count()
+
+impl Report {
+    fn covered(&self, p: (isize, isize)) -> bool {
+        self.sensors.iter().any(|s| s.covers(p))
     }
-    fn find_hole(&self, max: isize) -> (isize, isize) {
-        println!("self.not_becons {}", self.not_becons.len());
-        for (y, bcns) in self.not_becons.iter() {
-            //dbg!(&s.len(), &max);
-            if bcns.len() == max.try_into().unwrap() {
-                for x in 0..max {
-                    if !bcns.contains(x) {
-                        return (x, *y);
-                    }
+
+    /// Positions in row `y` within range of some sensor, as disjoint ranges in order.
+    fn row_coverage(&self, y: isize) -> Vec<RangeInclusive<isize>> {
+        merge(self.sensors.iter().filter_map(|s| s.row_span(y)).collect())
+    }
+
+    /// Number of positions in row `y` where a beacon can't be.
+    fn no_beacon_count(&self, y: isize) -> usize {
+        let covered: isize = self
+            .row_coverage(y)
+            .iter()
+            .map(|r| r.end() - r.start() + 1)
+            .sum();
+        let beacons: HashSet<_> = self
+            .sensors
+            .iter()
+            .filter(|s| s.beacon.1 == y)
+            .map(|s| s.beacon)
+            .collect();
+        covered as usize - beacons.len()
+    }
+
+    /// Isolated gaps inside the box: uncovered points where the borders of the diamonds cross
+    /// each other or the box edges. Only those candidate points are checked, so a large uncovered
+    /// region shows up as a handful of its corners rather than every position in it.
+    fn uncovered(
+        &self,
+        xs: RangeInclusive<isize>,
+        ys: RangeInclusive<isize>,
+    ) -> Vec<(isize, isize)> {
+        let (mut sums, mut diffs): (Vec<isize>, Vec<isize>) = (Vec::new(), Vec::new());
+        for s in &self.sensors {
+            let (a, b) = s.border_lines();
+            sums.extend(a);
+            diffs.extend(b);
+        }
+        let mut candidates = Vec::new();
+        for &a in &sums {
+            for &b in &diffs {
+                if (a + b) % 2 == 0 {
+                    candidates.push(((a + b) / 2, (a - b) / 2));
                 }
             }
         }
-        unreachable!();
-    }
-    fn tune(&self, max: isize) -> isize {
-        let (x, y) = self.find_hole(max);
-        x * 4000000 + y
-    }
-    fn truncate(&mut self, max: isize) {
-        let rm_keys: Vec<_> = self
-            .cells
-            .keys()
-            .filter(|(x, y)| *x < 0 || *x > max || *y < 0 || *y > max)
-            .cloned()
-            .collect();
-        for k in rm_keys {
-            self.cells.remove(&k);
+        let (x0, x1, y0, y1) = (*xs.start(), *xs.end(), *ys.start(), *ys.end());
+        for x in [x0, x1] {
+            candidates.extend(sums.iter().map(|a| (x, a - x)));
+            candidates.extend(diffs.iter().map(|b| (x, x - b)));
         }
-
-        let not_becons = self
-            .not_becons
-            .iter_mut()
-            .filter(|(k, _v)| **k >= 0 && **k <= max)
-            .map(|(k, bs)| {
-                bs.truncate(max.try_into().map(|v: usize| v + 1).unwrap());
-                (*k, bs)
-            })
-            .filter(|(_k, bs)| !bs.is_empty())
-            .map(|(k, bs)| (k, bs.clone()))
+        for y in [y0, y1] {
+            candidates.extend(sums.iter().map(|a| (a - y, y)));
+            candidates.extend(diffs.iter().map(|b| (b + y, y)));
+        }
+        candidates.extend([(x0, y0), (x0, y1), (x1, y0), (x1, y1)]);
+        let mut gaps: Vec<_> = candidates
+            .into_iter()
+            .filter(|(x, y)| xs.contains(x) && ys.contains(y))
+            .filter(|p| !self.covered(*p))
             .collect();
-        self.not_becons = not_becons;
-        //dbg!(self.not_becons.keys());
+        gaps.sort();
+        gaps.dedup();
+        gaps
     }
 }
 
-fn solve1(input: &str, row: isize) -> usize {
-    let start = Instant::now();
-    let g: Grid = input.parse().expect("parse");
-    println!("Grid\n{g}");
-    g.row_occupancy(row)
+fn solve1(input: &str, row: isize) -> Result<usize> {
+    let report: Report = input.parse()?;
+    Ok(report.no_beacon_count(row))
 }
 
 #[aoc(day15, part1)]
-fn part1(input: &str) -> usize {
+fn part1(input: &str) -> Result<usize> {
     solve1(input, 2000000)
 }
 
-fn solve2(input: &str, max: isize) -> isize {
-    let start = Instant::now();
-
-    let mut g: Grid = input.parse().expect("parse");
-    println!("After parse {}", start.elapsed().as_secs());
-    println!("Grid\n{g}");
-    g.truncate(max);
-    println!("After truncate {}", start.elapsed().as_secs());
-    println!("Grid\n{g}");
-    let t = g.tune(max);
-    println!("After tune {}", start.elapsed().as_secs());
-    t
+fn solve2(input: &str, max: isize) -> Result<isize> {
+    let report: Report = input.parse()?;
+    match report.uncovered(0..=max, 0..=max)[..] {
+        [(x, y)] => Ok(x * 4000000 + y),
+        ref gaps => Err(anyhow::anyhow!(
+            "expected exactly one isolated gap for the beacon, found {} candidates",
+            gaps.len()
+        )),
+    }
 }
 
 #[aoc(day15, part2)]
-fn part2(input: &str) -> isize {
+fn part2(input: &str) -> Result<isize> {
     solve2(input, 4000000)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const INPUT: &'static str = r#"Sensor at x=2, y=18: closest beacon is at x=-2, y=15
-        Sensor at x=9, y=16: closest beacon is at x=10, y=16
-        Sensor at x=13, y=2: closest beacon is at x=15, y=3
-        Sensor at x=12, y=14: closest beacon is at x=10, y=16
-        Sensor at x=10, y=20: closest beacon is at x=10, y=16
-        Sensor at x=14, y=17: closest beacon is at x=10, y=16
-        Sensor at x=8, y=7: closest beacon is at x=2, y=10
-        Sensor at x=2, y=0: closest beacon is at x=2, y=10
-        Sensor at x=0, y=11: closest beacon is at x=2, y=10
-        Sensor at x=20, y=14: closest beacon is at x=25, y=17
-        Sensor at x=17, y=20: closest beacon is at x=21, y=22
-        Sensor at x=16, y=7: closest beacon is at x=15, y=3
-        Sensor at x=14, y=3: closest beacon is at x=15, y=3
-        Sensor at x=20, y=1: closest beacon is at x=15, y=3"#;
+    const INPUT: &str = r#"Sensor at x=2, y=18: closest beacon is at x=-2, y=15
+Sensor at x=9, y=16: closest beacon is at x=10, y=16
+Sensor at x=13, y=2: closest beacon is at x=15, y=3
+Sensor at x=12, y=14: closest beacon is at x=10, y=16
+Sensor at x=10, y=20: closest beacon is at x=10, y=16
+Sensor at x=14, y=17: closest beacon is at x=10, y=16
+Sensor at x=8, y=7: closest beacon is at x=2, y=10
+Sensor at x=2, y=0: closest beacon is at x=2, y=10
+Sensor at x=0, y=11: closest beacon is at x=2, y=10
+Sensor at x=20, y=14: closest beacon is at x=25, y=17
+Sensor at x=17, y=20: closest beacon is at x=21, y=22
+Sensor at x=16, y=7: closest beacon is at x=15, y=3
+Sensor at x=14, y=3: closest beacon is at x=15, y=3
+Sensor at x=20, y=1: closest beacon is at x=15, y=3"#;
 
     #[test]
-    fn p1() {
-        assert_eq!(solve1(INPUT, 10), 26);
+    fn p1() -> Result<()> {
+        assert_eq!(solve1(INPUT, 10)?, 26);
+        Ok(())
     }
 
     #[test]
-    fn p2() {
-        assert_eq!(solve2(INPUT, 20), 56000011);
+    fn p2() -> Result<()> {
+        assert_eq!(solve2(INPUT, 20)?, 56000011);
+        Ok(())
     }
 
     #[test]
-    fn bitset_truncate() {
-        let mut bs = Bitset::from_iter(0..64);
-        bs.truncate(0);
-        assert_eq!(Bitset::default(), bs);
-
-        let mut bs = Bitset::from_iter(0..64);
-        bs.truncate(20);
-        assert_eq!(Bitset::from_iter(0..20), bs);
+    fn coverage() -> Result<()> {
+        let report: Report = INPUT.parse()?;
+        assert_eq!(report.row_coverage(10), vec![-2..=24]);
+        // The gap in row 11 is the distress beacon.
+        assert_eq!(report.row_coverage(11), vec![-3..=13, 15..=25]);
+        assert_eq!(
+            merge(vec![5..=6, 0..=1, 2..=3, 1..=2, 8..=9]),
+            vec![0..=3, 5..=6, 8..=9]
+        );
+        Ok(())
+    }
 
-        let mut bs = Bitset::from_iter(0..128);
-        bs.truncate(20);
-        assert_eq!(Bitset::from_iter(0..20), bs);
+    #[test]
+    fn uncovered() -> Result<()> {
+        let report: Report = INPUT.parse()?;
+        // Everything reported really is uncovered, checked against a scan of the box.
+        for max in [5, 20, 30] {
+            let mut scan = Vec::new();
+            for x in -5..=max {
+                for y in -5..=max {
+                    if !report.covered((x, y)) {
+                        scan.push((x, y));
+                    }
+                }
+            }
+            let gaps = report.uncovered(-5..=max, -5..=max);
+            assert!(gaps.iter().all(|g| scan.contains(g)));
+        }
+        assert_eq!(report.uncovered(0..=20, 0..=20), vec![(14, 11)]);
+        // Single sensor, the corners of a box just big enough to hold its diamond are outside.
+        let one: Report = "Sensor at x=0, y=0: closest beacon is at x=1, y=0".parse()?;
+        assert_eq!(
+            one.uncovered(-1..=1, -1..=1),
+            vec![(-1, -1), (-1, 1), (1, -1), (1, 1)]
+        );
+        Ok(())
+    }
 
-        let mut bs = Bitset::from_iter(0..256);
-        bs.truncate(128);
-        assert_eq!(Bitset::from_iter(0..128), bs);
+    #[test]
+    fn large_gap() -> Result<()> {
+        // Most of the box is uncovered, only candidate points from it are reported.
+        let one: Report = "Sensor at x=0, y=0: closest beacon is at x=1, y=0".parse()?;
+        let scan: Vec<_> = (-10..=10)
+            .flat_map(|x| (-10..=10).map(move |y| (x, y)))
+            .filter(|p| !one.covered(*p))
+            .collect();
+        assert_eq!(scan.len(), 21 * 21 - 5);
+        let gaps = one.uncovered(-10..=10, -10..=10);
+        assert!(gaps.iter().all(|g| scan.contains(g)));
+        assert!(gaps.contains(&(-10, -10)) && gaps.contains(&(10, 10)));
+        assert!(gaps.len() < scan.len());
+        assert!(solve2("Sensor at x=0, y=0: closest beacon is at x=1, y=0", 20).is_err());
+        Ok(())
+    }
 
-        let mut bs = Bitset::from_iter(-256..256);
-        bs.truncate(128);
-        assert_eq!(Bitset::from_iter(0..128), bs);
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "Sensor at x=2, y=18: closest beacon is at x=-2"
+                .parse::<Report>()
+                .unwrap_err(),
+            SensorError::BadLine(1)
+        );
     }
 }
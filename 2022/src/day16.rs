@@ -1,157 +1,307 @@
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
-#[derive(Debug)]
-struct Node {
-    rate: usize,
-    outbound: u64,
+const START: &str = "AA";
+
+#[derive(Debug, Error, PartialEq)]
+enum ValveError {
+    #[error("line {0}: expected 'Valve XX has flow rate=N; tunnels lead to valves YY, ZZ'")]
+    BadLine(usize),
+    #[error("tunnel to unknown valve {0}")]
+    UnknownValve(String),
+    #[error("no valve {START} to start from")]
+    NoStart,
+    #[error("{0} valves with a flow rate, at most 64 are supported")]
+    TooManyValves(usize),
 }
 
+/// Valves and tunnels as described by the scan.
 #[derive(Debug)]
-struct Map {
-    valves: Vec<Node>,
-}
-
-fn name_to_letter(s: &str) -> char {
-    s.chars().next().unwrap().to_ascii_lowercase()
-}
-impl Map {
-    /*
-    fn print_graphviz(&self) {
-    println!(
-    r#"
-    digraph G {{
-    "#
-    );
-    for name in valves.enumerate() {
-    let node = &self.valves[name];
-    let mut outbound = node.outbound.clone();
-    outbound.sort();
-    for n in outbound {
-    println!(
-    "{}{} -> {}{};",
-    name.to_ascii_uppercase(),
-    name.to_ascii_uppercase(),
-    n.to_ascii_uppercase(),
-    n.to_ascii_uppercase()
-    );
-    }
+struct Network {
+    names: Vec<String>,
+    rates: Vec<usize>,
+    tunnels: Vec<Vec<usize>>,
+}
+
+impl FromStr for Network {
+    type Err = ValveError;
+    fn from_str(s: &str) -> Result<Self, ValveError> {
+        let mut lines = Vec::new();
+        for (i, l) in s.lines().enumerate() {
+            let bad = || ValveError::BadLine(i + 1);
+            let l = l.strip_prefix("Valve ").ok_or_else(bad)?;
+            let (name, l) = l.split_once(" has flow rate=").ok_or_else(bad)?;
+            let (rate, l) = l.split_once("; ").ok_or_else(bad)?;
+            let rate: usize = rate.parse().map_err(|_| bad())?;
+            let tunnels = l
+                .strip_prefix("tunnels lead to valves ")
+                .or_else(|| l.strip_prefix("tunnel leads to valve "))
+                .ok_or_else(bad)?;
+            lines.push((name, rate, tunnels));
+        }
+        let names: Vec<String> = lines.iter().map(|(n, _, _)| n.to_string()).collect();
+        let index: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.as_str(), i))
+            .collect();
+        let tunnels = lines
+            .iter()
+            .map(|(_, _, tunnels)| {
+                tunnels
+                    .split(", ")
+                    .map(|t| {
+                        index
+                            .get(t)
+                            .copied()
+                            .ok_or_else(|| ValveError::UnknownValve(t.to_string()))
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Network {
+            rates: lines.iter().map(|(_, r, _)| *r).collect(),
+            names,
+            tunnels,
+        })
     }
-    for (name, node) in self.valves.iter() {
-    println!(
-    r#"{}{} [label="{}{} [{}]"]"#,
-    name.to_ascii_uppercase(),
-    name.to_ascii_uppercase(),
-    name.to_ascii_uppercase(),
-    name.to_ascii_uppercase(),
-    node.rate
-    );
+}
+
+impl Network {
+    // Breadth first search for the number of steps from `from` to every valve.
+    fn distances(&self, from: usize) -> Vec<usize> {
+        let mut dist = vec![usize::MAX; self.names.len()];
+        dist[from] = 0;
+        let mut queue = VecDeque::from([from]);
+        while let Some(v) = queue.pop_front() {
+            for &t in &self.tunnels[v] {
+                if dist[t] == usize::MAX {
+                    dist[t] = dist[v] + 1;
+                    queue.push_back(t);
+                }
+            }
+        }
+        dist
     }
-    println!(
-    r#"
-    AA [shape=Mdiamond];
-    }}
-    "#
-    );
+}
+
+/// Network reduced to the valves worth opening, with the travel time between each of them.
+#[derive(Debug)]
+struct Valves {
+    names: Vec<String>,
+    rates: Vec<usize>,
+    // dist[i][j] is the steps from valve i to valve j, the last row is from the start.
+    dist: Vec<Vec<usize>>,
+}
+
+/// A valve being opened, `minute` is when it's opened so it releases pressure from the next one.
+#[derive(Clone, Debug, PartialEq)]
+struct Opening {
+    minute: usize,
+    agent: usize,
+    valve: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct Plan {
+    pressure: usize,
+    openings: Vec<Opening>,
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for o in &self.openings {
+            writeln!(
+                f,
+                "== Minute {} == agent {} opens {}",
+                o.minute, o.agent, o.valve
+            )?;
+        }
+        write!(f, "{} pressure released", self.pressure)
     }
-    */
-}
-impl FromStr for Map {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, ()> {
-        let mut names: Vec<_> = s
-            .lines()
-            .inspect(|l| {
-                println!("l: {l}");
+}
+
+// Best pressure one agent can release for a given set of opened valves, with the order to open
+// them in.
+#[derive(Debug)]
+struct Route {
+    mask: u64,
+    pressure: usize,
+    order: Vec<usize>,
+}
+
+impl TryFrom<&Network> for Valves {
+    type Error = ValveError;
+    fn try_from(n: &Network) -> Result<Self, ValveError> {
+        let start = n
+            .names
+            .iter()
+            .position(|name| name == START)
+            .ok_or(ValveError::NoStart)?;
+        let useful: Vec<usize> = (0..n.names.len()).filter(|v| n.rates[*v] > 0).collect();
+        if useful.len() > 64 {
+            return Err(ValveError::TooManyValves(useful.len()));
+        }
+        let dist = useful
+            .iter()
+            .chain([&start])
+            .map(|from| {
+                let d = n.distances(*from);
+                useful.iter().map(|to| d[*to]).collect()
             })
-            .map(|l| l[6..8].to_string())
             .collect();
-        names.sort();
-        dbg!(&names);
-        let name_map: HashMap<String, usize> = names
+        Ok(Valves {
+            names: useful.iter().map(|v| n.names[*v].clone()).collect(),
+            rates: useful.iter().map(|v| n.rates[*v]).collect(),
+            dist,
+        })
+    }
+}
+
+impl Valves {
+    /// Every set of valves one agent could open in `budget` minutes, with the most pressure it
+    /// can release doing so, best first.
+    fn routes(&self, budget: usize) -> Vec<Route> {
+        let mut best = HashMap::new();
+        let mut order = Vec::new();
+        self.explore(self.names.len(), budget, 0, 0, &mut order, &mut best);
+        let mut routes: Vec<Route> = best
             .into_iter()
-            .enumerate()
-            .map(|(i, name)| (name, i))
-            .collect();
-        dbg!(&name_map);
-        let mut valves: Vec<_> = s
-            .lines()
-            .map(|l| {
-                let l = l.replace(|c| c == '=' || c == ';', " ").replace(',', "");
-
-                let mut it = l.split(' ');
-                let name = it.nth(1).expect("name");
-                let rate = it.nth(3).expect("rate").parse().expect("rate number");
-                let outbound = it
-                    .skip(5)
-                    .fold(0, |outbound, name| outbound | 1 << name_map[name]);
-                let node = Node { rate, outbound };
-                (name_map[name], node)
+            .map(|(mask, (pressure, order))| Route {
+                mask,
+                pressure,
+                order,
             })
             .collect();
-        valves.sort_by(|a, b| a.0.cmp(&b.0));
-        let valves = valves.into_iter().map(|(_, v)| v).collect();
-
-        Ok(Map { valves })
+        routes.sort_by(|a, b| b.pressure.cmp(&a.pressure).then(a.mask.cmp(&b.mask)));
+        routes
     }
-}
 
-fn solve1(
-    m: &Map,
-    cur: u64,
-    ttl: usize,
-    open: &mut u64,
-    mut pressure: usize,
-    solution: &mut usize,
-) {
-    if ttl == 0 || open.count_ones() == m.valves.len().try_into().unwrap() {
-        println!("open = {:064b}\t\tpressure = {}", open, pressure,);
-        *solution = *solution.max(&mut pressure);
-        return;
+    fn explore(
+        &self,
+        at: usize,
+        remaining: usize,
+        mask: u64,
+        pressure: usize,
+        order: &mut Vec<usize>,
+        best: &mut HashMap<u64, (usize, Vec<usize>)>,
+    ) {
+        let entry = best.entry(mask).or_insert((0, Vec::new()));
+        if pressure > entry.0 {
+            *entry = (pressure, order.clone());
+        }
+        for v in 0..self.names.len() {
+            // Travel there, then a minute to open it.
+            let cost = self.dist[at][v].saturating_add(1);
+            if mask & 1 << v != 0 || cost >= remaining {
+                continue;
+            }
+            let left = remaining - cost;
+            order.push(v);
+            self.explore(
+                v,
+                left,
+                mask | 1 << v,
+                pressure + self.rates[v] * left,
+                order,
+                best,
+            );
+            order.pop();
+        }
     }
 
-    // At each step we can open, if not already open, or move. Each action cost 1.
-    let node: &Node = &m.valves[cur as usize];
+    /// Finds the most pressure a team of agents can release, each starting at the same time from
+    /// the start valve with its own time budget in minutes.
+    fn plan(&self, budgets: &[usize]) -> Plan {
+        let routes: Vec<Vec<Route>> = budgets.iter().map(|b| self.routes(*b)).collect();
+        // Most pressure the agents from index i onwards could add, ignoring overlap.
+        let mut optimistic = vec![0; routes.len() + 1];
+        for i in (0..routes.len()).rev() {
+            optimistic[i] = optimistic[i + 1] + routes[i][0].pressure;
+        }
+        let mut best = (0, vec![0; routes.len()]);
+        let mut chosen = Vec::new();
+        combine(&routes, &optimistic, 0, 0, &mut chosen, &mut best);
 
-    if open.count_ones() == m.valves.len().try_into().unwrap() {
-        return;
+        let mut openings = Vec::new();
+        for (agent, i) in best.1.iter().enumerate() {
+            let (mut at, mut minute) = (self.names.len(), 0);
+            for &v in &routes[agent][*i].order {
+                minute += self.dist[at][v] + 1;
+                at = v;
+                openings.push(Opening {
+                    minute,
+                    agent,
+                    valve: self.names[v].clone(),
+                });
+            }
+        }
+        openings.sort_by_key(|o| (o.minute, o.agent));
+        Plan {
+            pressure: best.0,
+            openings,
+        }
     }
+}
 
-    // Try opening
-    if *open & (1 << cur) == 0 {
-        *open |= 1 << cur;
-        pressure += ttl * node.rate;
-        solve1(m, cur, ttl - 1, open, pressure, solution);
-        pressure -= ttl * node.rate;
-        *open &= !(1 << cur);
+// Branch and bound over each agent's routes, keeping the opened valves disjoint.
+fn combine(
+    routes: &[Vec<Route>],
+    optimistic: &[usize],
+    used: u64,
+    pressure: usize,
+    chosen: &mut Vec<usize>,
+    best: &mut (usize, Vec<usize>),
+) {
+    let agent = chosen.len();
+    if agent == routes.len() {
+        if pressure > best.0 {
+            *best = (pressure, chosen.clone());
+        }
+        return;
     }
-
-    // Try moving to each neighbor
-    for out in node.outbound.trailing_zeros()..64 - node.outbound.leading_zeros() {
-        println!("{:064b} {}", node.outbound, out);
-        solve1(m, out.into(), ttl - 1, open, pressure, solution);
+    for (i, r) in routes[agent].iter().enumerate() {
+        if pressure + r.pressure + optimistic[agent + 1] <= best.0 {
+            // Sorted best first, so nothing later can do better.
+            break;
+        }
+        if r.mask & used != 0 {
+            continue;
+        }
+        chosen.push(i);
+        combine(
+            routes,
+            optimistic,
+            used | r.mask,
+            pressure + r.pressure,
+            chosen,
+            best,
+        );
+        chosen.pop();
     }
 }
 
+fn solve(input: &str, budgets: &[usize]) -> Result<Plan> {
+    let network: Network = input.parse()?;
+    let valves = Valves::try_from(&network)?;
+    Ok(valves.plan(budgets))
+}
+
 #[aoc(day16, part1)]
-fn part1(input: &str) -> usize {
-    let m: Map = input.parse().expect("map");
-    //m.print_graphviz();
-    //dbg!(&m);
-    let mut solution = 0;
-    let mut open = 0;
-    let pressure = 0;
-    solve1(&m, 0, 30, &mut open, pressure, &mut solution);
-    solution
+fn part1(input: &str) -> Result<usize> {
+    Ok(solve(input, &[30])?.pressure)
 }
 
-// #[aoc(day16, part2)]
-// fn part2(input: &str) -> usize { }
+#[aoc(day16, part2)]
+fn part2(input: &str) -> Result<usize> {
+    // You spend 4 minutes teaching an elephant to help.
+    Ok(solve(input, &[26, 26])?.pressure)
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const INPUT: &'static str = r#"Valve AA has flow rate=0; tunnels lead to valves DD, II, BB
+    const INPUT: &str = r#"Valve AA has flow rate=0; tunnels lead to valves DD, II, BB
 Valve BB has flow rate=13; tunnels lead to valves CC, AA
 Valve CC has flow rate=2; tunnels lead to valves DD, BB
 Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE
@@ -163,13 +313,73 @@ Valve II has flow rate=0; tunnels lead to valves AA, JJ
 Valve JJ has flow rate=21; tunnel leads to valve II
 "#;
 
+    fn opened(plan: &Plan, agent: usize) -> Vec<(usize, &str)> {
+        plan.openings
+            .iter()
+            .filter(|o| o.agent == agent)
+            .map(|o| (o.minute, o.valve.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn p1() -> Result<()> {
+        assert_eq!(part1(INPUT)?, 1651);
+        let plan = solve(INPUT, &[30])?;
+        assert_eq!(
+            opened(&plan, 0),
+            vec![
+                (2, "DD"),
+                (5, "BB"),
+                (9, "JJ"),
+                (17, "HH"),
+                (21, "EE"),
+                (24, "CC")
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn p2() -> Result<()> {
+        assert_eq!(part2(INPUT)?, 1707);
+        let plan = solve(INPUT, &[26, 26])?;
+        let mut valves: Vec<_> = plan.openings.iter().map(|o| o.valve.as_str()).collect();
+        valves.sort();
+        assert_eq!(valves, vec!["BB", "CC", "DD", "EE", "HH", "JJ"]);
+        Ok(())
+    }
+
     #[test]
-    fn p1() {
-        assert_eq!(part1(INPUT), 1651);
+    fn agents() -> Result<()> {
+        assert_eq!(solve(INPUT, &[])?.pressure, 0);
+        // With no time to move nobody opens anything.
+        assert_eq!(solve(INPUT, &[1, 1, 1])?.pressure, 0);
+        // Three agents can each head straight for a different valve.
+        let plan = solve(INPUT, &[4, 4, 4])?;
+        assert_eq!(plan.pressure, 20 * 2 + 13 * 2 + 21);
+        assert_eq!(plan.openings.len(), 3);
+        // A second agent with no time to spare doesn't change anything.
+        assert_eq!(solve(INPUT, &[30, 0])?, solve(INPUT, &[30])?);
+        Ok(())
     }
 
-    //#[test]
-    //fn p2() {
-    //    assert_eq!(part2(INPUT), 42);
-    //}
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "Valve AA has flow rate=x; tunnel leads to valve AA"
+                .parse::<Network>()
+                .unwrap_err(),
+            ValveError::BadLine(1)
+        );
+        assert_eq!(
+            "Valve AA has flow rate=0; tunnel leads to valve BB"
+                .parse::<Network>()
+                .unwrap_err(),
+            ValveError::UnknownValve("BB".into())
+        );
+        let n: Network = "Valve BB has flow rate=1; tunnel leads to valve BB"
+            .parse()
+            .unwrap();
+        assert_eq!(Valves::try_from(&n).unwrap_err(), ValveError::NoStart);
+    }
 }
@@ -1,314 +1,390 @@
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
-// Pieces
-//
-// ####
-//
-// .#.
-// ###
-// .#.
-//
-// ..#
-// ..#
-// ###
-//
-// #
-// #
-// #
-// #
-//
-// ##
-// ##
-
-#[derive(Copy, Clone, PartialEq)]
-enum Piece {
-    Dash,
-    Plus,
-    L,
-    I,
-    Square,
+const VERBOSE: bool = false;
+
+const WIDTH: usize = 7;
+// The rocks in the order they fall.
+const PIECES: &str = r#"####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##"#;
+// Rows from the top of the stack used to recognize a state seen before. Pieces rarely fall
+// further than this below the surface.
+const PROFILE_ROWS: usize = 64;
+
+#[derive(Debug, Error, PartialEq)]
+enum ChamberError {
+    #[error("unknown jet {0:?}")]
+    UnknownJet(char),
+    #[error("no jets of gas")]
+    NoJets,
+    #[error("no pieces to drop")]
+    NoPieces,
+    #[error("piece row {0:?} should only contain '#' and '.'")]
+    BadPiece(String),
+    #[error("piece {0} is wider than the {1} unit chamber")]
+    PieceTooWide(usize, usize),
+    #[error("chambers can be at most 32 units wide, got {0}")]
+    ChamberTooWide(usize),
 }
 
-impl Piece {
-    fn width(&self) -> usize {
-        use Piece::*;
-        match self {
-            Dash => 4,
-            Plus => 3,
-            L => 3,
-            I => 1,
-            Square => 2,
+/// A rock shape, each row is a bitmask with bit `x` set for a rock in column `x`.
+#[derive(Clone, Debug, PartialEq)]
+struct Piece {
+    // Bottom row first.
+    rows: Vec<u32>,
+    width: usize,
+}
+
+impl FromStr for Piece {
+    type Err = ChamberError;
+    fn from_str(s: &str) -> Result<Self, ChamberError> {
+        let mut rows = Vec::new();
+        let mut width = 0;
+        for l in s.lines().rev() {
+            if l.len() > 32 || l.chars().any(|c| c != '#' && c != '.') || !l.contains('#') {
+                return Err(ChamberError::BadPiece(l.to_string()));
+            }
+            width = width.max(l.len());
+            rows.push(
+                l.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .fold(0, |row, (x, _)| row | 1 << x),
+            );
         }
-    }
-    // encodes each row as a nibble
-    fn bits(&self) -> u32 {
-        use Piece::*;
-        match self {
-            Dash => 0b1111,
-            Plus => 0b0010_0111_0010,
-            L => 0b0100_0100_0111,
-            I => 0b0001_0001_0001_0001,
-            Square => 0b0011_0011,
+        if rows.is_empty() {
+            return Err(ChamberError::NoPieces);
         }
+        Ok(Piece { rows, width })
     }
 }
 
-struct Chamber {
-    stack: Vec<u8>,
-    pieces: Box<dyn Iterator<Item = Piece>>,
-    cur_piece: Option<Piece>,
-    // Lower left corner of the bounding box of the piece.
-    pos: (usize, usize),
+fn parse_pieces(s: &str) -> Result<Vec<Piece>, ChamberError> {
+    s.split("\n\n").map(str::parse).collect()
 }
 
-impl Default for Chamber {
-    fn default() -> Self {
-        Chamber {
-            stack: Default::default(),
-            pieces: Box::new(
-                [Piece::Dash, Piece::Plus, Piece::L, Piece::I, Piece::Square]
-                    .into_iter()
-                    .cycle(),
-            ),
-            cur_piece: None,
-            pos: (0, 0),
-        }
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Jet {
+    Left,
+    Right,
+}
+
+fn parse_jets(s: &str) -> Result<Vec<Jet>, ChamberError> {
+    let jets = s
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '<' => Ok(Jet::Left),
+            '>' => Ok(Jet::Right),
+            c => Err(ChamberError::UnknownJet(c)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if jets.is_empty() {
+        return Err(ChamberError::NoJets);
     }
+    Ok(jets)
 }
 
+/// Everything that decides how the chamber fills from here on, as far as can be seen from the
+/// top of the stack.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Profile {
+    rows: Vec<u32>,
+    piece: usize,
+    jet: usize,
+}
+
+#[derive(Debug)]
 enum State {
-    Rest(Piece),
+    Rest,
     Move,
 }
 
+#[derive(Debug)]
+struct Chamber {
+    width: usize,
+    pieces: Vec<Piece>,
+    jets: Vec<Jet>,
+    // Only rows with rock in them, so the height of the tower is the length.
+    stack: Vec<u32>,
+    // Index of the next piece and jet.
+    piece: usize,
+    jet: usize,
+    rocks: usize,
+    // Falling piece and the lower left corner of its bounding box.
+    falling: Option<(usize, (usize, usize))>,
+}
+
 impl Chamber {
-    fn step(&mut self, jet: char) -> State {
-        let p = match self.cur_piece {
-            Some(p) => {
-                vprint!("Continuing @ {:?}\n{}", self.pos, self);
-                p
-            }
-            None => {
-                let p = self.pieces.next().unwrap();
-                self.cur_piece = Some(p);
-                let zeros = self.stack.iter().rev().take_while(|&v| *v == 0).count();
-                vprint!("{zeros} empty layers");
-                let delta = if zeros <= 3 { 3 - zeros + 1 } else { 0 };
-                vprint!("Adding {delta} empty layers");
-                self.stack.extend((0..delta).map(|_| 0));
-                self.pos = (2, self.stack.len() - 1);
-                vprint!("A new rock begins falling @ {:?}\n{}", self.pos, self);
-                p
-            }
+    fn new(width: usize, pieces: Vec<Piece>, jets: Vec<Jet>) -> Result<Chamber, ChamberError> {
+        if width > 32 {
+            return Err(ChamberError::ChamberTooWide(width));
+        }
+        if pieces.is_empty() {
+            return Err(ChamberError::NoPieces);
+        }
+        if jets.is_empty() {
+            return Err(ChamberError::NoJets);
+        }
+        if let Some(i) = pieces.iter().position(|p| p.width > width) {
+            return Err(ChamberError::PieceTooWide(i, width));
+        }
+        Ok(Chamber {
+            width,
+            pieces,
+            jets,
+            stack: Vec::new(),
+            piece: 0,
+            jet: 0,
+            rocks: 0,
+            falling: None,
+        })
+    }
+
+    fn height(&self) -> usize {
+        self.stack.len()
+    }
+
+    // True if piece `p` with its lower left corner at (x, y) overlaps nothing.
+    fn fits(&self, p: usize, (x, y): (usize, usize)) -> bool {
+        let p = &self.pieces[p];
+        x + p.width <= self.width
+            && p.rows.iter().enumerate().all(|(dy, row)| {
+                self.stack
+                    .get(y + dy)
+                    .is_none_or(|layer| layer & row << x == 0)
+            })
+    }
+
+    /// Pushes the falling rock with the next jet then lets it fall a unit, starting a new rock
+    /// first if needed.
+    fn step(&mut self) -> State {
+        let (p, (mut x, y)) = self.falling.unwrap_or_else(|| {
+            let p = self.piece;
+            self.piece = (self.piece + 1) % self.pieces.len();
+            // Two units from the left wall, or as far as it goes in a narrow chamber.
+            let x = 2.min(self.width - self.pieces[p].width);
+            (p, (x, self.height() + 3))
+        });
+        let jet = self.jets[self.jet];
+        self.jet = (self.jet + 1) % self.jets.len();
+        let pushed = match jet {
+            Jet::Left => x.checked_sub(1),
+            Jet::Right => Some(x + 1),
         };
-        // Blow
-        match jet {
-            '<' => {
-                if self.pos.0 > 0 && !self.hit(self.pos.0 - 1, self.pos.1) {
-                    vprint!("Jet of gas pushes rock left:");
-                    self.pos = (self.pos.0 - 1, self.pos.1);
-                } else {
-                    vprint!("Jet of gas pushes rock left, but nothing happens:");
-                }
+        match pushed {
+            Some(px) if self.fits(p, (px, y)) => {
+                vprint!("Jet of gas pushes rock {jet:?}");
+                x = px;
             }
-            '>' => {
-                if (self.pos.0 + p.width()) < 7 && !self.hit(self.pos.0 + 1, self.pos.1) {
-                    vprint!("Jet of gas pushes rock right:");
-                    self.pos = (self.pos.0 + 1, self.pos.1);
-                } else {
-                    vprint!("Jet of gas pushes rock right, but nothing happens:");
-                }
-            }
-            c => panic!("Unknown {c}"),
+            _ => vprint!("Jet of gas pushes rock {jet:?}, but nothing happens"),
+        }
+        if y > 0 && self.fits(p, (x, y - 1)) {
+            vprint!("Rock falls 1 unit");
+            self.falling = Some((p, (x, y - 1)));
+            return State::Move;
+        }
+        vprint!("Rock falls 1 unit, causing it to come to rest");
+        let rows = &self.pieces[p].rows;
+        if self.stack.len() < y + rows.len() {
+            self.stack.resize(y + rows.len(), 0);
         }
-        vprint!("{}", self);
-        // If at bottom, or hit an object, rest.
-        if self.pos.1 == 0 || self.hit(self.pos.0, self.pos.1 - 1) {
-            vprint!("Rock falls 1 unit, causing it to come to rest:");
-            // fill in stack with bits.
-            let max_y = self.stack.len();
-            let pat = p.bits();
-            let y0: u8 = ((pat >> 0) & 0b1111).try_into().unwrap();
-            let y1: u8 = ((pat >> 4) & 0b1111).try_into().unwrap();
-            let y2: u8 = ((pat >> 8) & 0b1111).try_into().unwrap();
-            let y3: u8 = ((pat >> 12) & 0b1111).try_into().unwrap();
-
-            self.stack[self.pos.1 + 0] |= y0 << self.pos.0;
-            self.stack[self.pos.1 + 1] |= y1 << self.pos.0;
-            self.stack[self.pos.1 + 2] |= y2 << self.pos.0;
-            self.stack[self.pos.1 + 3] |= y3 << self.pos.0;
-            // Reset the piece
-            self.cur_piece = None;
-            State::Rest(p)
-        } else {
-            // Else fall
-            vprint!("Rock falls 1 unit:");
-            self.pos = (self.pos.0, self.pos.1 - 1);
-            State::Move
+        for (dy, row) in rows.iter().enumerate() {
+            self.stack[y + dy] |= row << x;
+        }
+        self.falling = None;
+        self.rocks += 1;
+        State::Rest
+    }
+
+    fn drop_rock(&mut self) {
+        while let State::Move = self.step() {}
+        vprint!("{self}");
+    }
+
+    /// Top `depth` rows of the stack with the piece and jet up next, the floor counts as solid
+    /// rows.
+    fn profile(&self, depth: usize) -> Profile {
+        let full = u32::MAX >> (32 - self.width);
+        let mut rows: Vec<u32> = self.stack.iter().rev().take(depth).copied().collect();
+        rows.resize(depth, full);
+        Profile {
+            rows,
+            piece: self.piece,
+            jet: self.jet,
         }
     }
-    fn hit(&self, x: usize, y: usize) -> bool {
-        let x: u8 = x.try_into().expect("x");
-        if let Some(p) = self.cur_piece {
-            let pat = p.bits();
-            let y0: u8 = ((pat >> 0) & 0b1111).try_into().unwrap();
-            let y1: u8 = ((pat >> 4) & 0b1111).try_into().unwrap();
-            let y2: u8 = ((pat >> 8) & 0b1111).try_into().unwrap();
-            let y3: u8 = ((pat >> 12) & 0b1111).try_into().unwrap();
-            let s = &self.stack[y..];
-            match s.len() {
-                0 => panic!("empty stack"),
-                1 => s[0] & (y0 << x) != 0,
-                2 => (s[0] & (y0 << x) | s[1] & (y1 << x)) != 0,
-                3 => (s[0] & (y0 << x) | s[1] & (y1 << x) | s[2] & (y2 << x)) != 0,
-                _ => {
-                    (s[0] & (y0 << x) | s[1] & (y1 << x) | s[2] & (y2 << x) | s[3] & (y3 << x)) != 0
+
+    /// Height of the tower once `rocks` rocks in total have come to rest. After the fall starts
+    /// repeating the repeats are skipped over, so it's fine to ask for a trillion.
+    fn height_after(&mut self, rocks: usize) -> usize {
+        let mut seen = HashMap::new();
+        let mut skipped = None;
+        while self.rocks < rocks {
+            if skipped.is_none() {
+                let profile = self.profile(PROFILE_ROWS);
+                if let Some((r, h)) = seen.insert(profile, (self.rocks, self.height())) {
+                    let period = self.rocks - r;
+                    let cycles = (rocks - self.rocks) / period;
+                    vprint!(
+                        "rocks {r} and {} match, skipping {cycles} cycles",
+                        self.rocks
+                    );
+                    skipped = Some(cycles * (self.height() - h));
+                    // Pretend the skipped rocks fell, the stack stays the same shape.
+                    self.rocks += cycles * period;
+                    continue;
                 }
             }
-        } else {
-            panic!("hit called with no current piece");
+            self.drop_rock();
         }
-    }
-    fn tallest(&self) -> usize {
-        self.stack.iter().rev().skip_while(|&&l| l == 0).count()
+        self.height() + skipped.unwrap_or(0)
     }
 }
 
-impl fmt::Display for Chamber {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Uncomment to run fast enough for an answer
-        //return Ok(());
-        let (y0, y1, y2, y3) = if let Some(p) = self.cur_piece {
-            let pat = p.bits();
-            let y0: u8 = ((pat >> 0) & 0b1111).try_into().unwrap();
-            let y1: u8 = ((pat >> 4) & 0b1111).try_into().unwrap();
-            let y2: u8 = ((pat >> 8) & 0b1111).try_into().unwrap();
-            let y3: u8 = ((pat >> 12) & 0b1111).try_into().unwrap();
-            (y0, y1, y2, y3)
-        } else {
-            (0, 0, 0, 0)
+impl Display for Chamber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let falling = self.falling.map(|(p, pos)| (&self.pieces[p].rows, pos));
+        let top = match falling {
+            Some((rows, (_, y))) => self.height().max(y + rows.len()),
+            None => self.height(),
         };
-
-        println!("pos.1 {}", self.pos.1);
-        println!("y0 {y0:08b}");
-        println!("y1 {y1:08b}");
-        println!("y2 {y2:08b}");
-        println!("y3 {y3:08b}");
-        let y0 = y0 << self.pos.0;
-        let y1 = y1 << self.pos.0;
-        let y2 = y2 << self.pos.0;
-        let y3 = y3 << self.pos.0;
-
-        for (y, layer) in self.stack.iter().enumerate().rev() {
+        for y in (0..top).rev() {
             write!(f, "|")?;
-            for b in 0..7 {
-                if y == self.pos.1 && (y0 & (1 << b)) != 0 {
-                    write!(f, "@")?;
-                } else if y == self.pos.1 + 1 && (y1 & (1 << b)) != 0 {
-                    write!(f, "@")?;
-                } else if y == self.pos.1 + 2 && (y2 & (1 << b)) != 0 {
+            let layer = self.stack.get(y).copied().unwrap_or(0);
+            let rock = match falling {
+                Some((rows, (x, y0))) if y >= y0 && y - y0 < rows.len() => rows[y - y0] << x,
+                _ => 0,
+            };
+            for b in 0..self.width {
+                if rock & 1 << b != 0 {
                     write!(f, "@")?;
-                } else if y == self.pos.1 + 3 && (y3 & (1 << b)) != 0 {
-                    write!(f, "@")?;
-                } else if layer & (1 << b) == 0 {
-                    write!(f, ".")?;
-                } else {
+                } else if layer & 1 << b != 0 {
                     write!(f, "#")?;
+                } else {
+                    write!(f, ".")?;
                 }
             }
             writeln!(f, "|")?;
         }
-        writeln!(f, "+-------+")
+        write!(f, "+{}+", "-".repeat(self.width))
     }
 }
 
+fn solve(input: &str, rocks: usize) -> Result<usize> {
+    let mut ch = Chamber::new(WIDTH, parse_pieces(PIECES)?, parse_jets(input)?)?;
+    Ok(ch.height_after(rocks))
+}
+
 #[aoc(day17, part1)]
-fn part1(input: &str) -> usize {
-    let mut ch = Chamber::default();
-    let mut rocks = 0;
-    let mut steps = 0;
-    let mut last_rest = 0;
-    let mut jets = input.chars().cycle();
-    // TODO remove upper bound
-    for steps in 0.. {
-        vprint!("Step {steps} Rocks {rocks} Last Rest {last_rest}");
-        match ch.step(jets.next().unwrap()) {
-            State::Rest(_last_piece) => {
-                rocks += 1;
-                last_rest = 0;
-                if rocks == 2022 {
-                    break;
-                }
-            }
-            State::Move => {
-                last_rest += 1;
-                if last_rest > ch.stack.len() {
-                    panic!(
-                        "Too many steps, {last_rest} since last rest in stack of {}",
-                        ch.stack.len()
-                    );
-                }
-            }
-        }
-    }
-    // 3073 too low
-    ch.tallest()
+fn part1(input: &str) -> Result<usize> {
+    solve(input, 2022)
 }
 
 #[aoc(day17, part2)]
-fn part2(input: &str) -> usize {
-    let mut ch = Chamber::default();
-    let mut rocks = 0usize;
-    let mut steps = 0;
-    let mut last_rest = 0;
-    let input_len = input.len();
-    dbg!(&input_len);
-    let mut jets = input.chars().cycle();
-    // TODO remove upper bound
-    for steps in 0.. {
-        vprint!("Step {steps} Rocks {rocks} Last Rest {last_rest}");
-        match ch.step(jets.next().unwrap()) {
-            State::Rest(last_piece) => {
-                assert!(
-                    !(((steps % input_len) == 0) && last_piece == Piece::Square),
-                    "last piece was a square when input restarted at {steps}"
-                );
-                rocks += 1;
-                last_rest = 0;
-                if rocks % 100000000 == 0 {
-                    println!("Rocks {rocks}");
-                }
-                if rocks == 1000000000000 {
-                    break;
-                }
-            }
-            State::Move => {
-                last_rest += 1;
-                if last_rest > ch.stack.len() {
-                    panic!(
-                        "Too many steps, {last_rest} since last rest in stack of {}",
-                        ch.stack.len()
-                    );
-                }
-            }
-        }
-    }
-    ch.tallest()
+fn part2(input: &str) -> Result<usize> {
+    solve(input, 1_000_000_000_000)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const INPUT: &'static str = r#">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>"#;
+    const INPUT: &str = r#">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>"#;
+
+    fn chamber(width: usize, pieces: &str) -> Result<Chamber> {
+        Ok(Chamber::new(
+            width,
+            parse_pieces(pieces)?,
+            parse_jets(INPUT)?,
+        )?)
+    }
 
     #[test]
-    fn p1() {
-        assert_eq!(part1(INPUT), 3068);
+    fn p1() -> Result<()> {
+        assert_eq!(part1(INPUT)?, 3068);
+        Ok(())
+    }
+
+    #[test]
+    fn p2() -> Result<()> {
+        assert_eq!(part2(INPUT)?, 1514285714288);
+        Ok(())
+    }
+
+    #[test]
+    fn first_rocks() -> Result<()> {
+        let mut ch = chamber(WIDTH, PIECES)?;
+        for _ in 0..3 {
+            ch.drop_rock();
+        }
+        assert_eq!(
+            ch.to_string(),
+            r#"|..#....|
+|..#....|
+|####...|
+|..###..|
+|...#...|
+|..####.|
++-------+"#
+        );
+        ch.step();
+        assert_eq!(
+            ch.to_string().lines().take(4).collect::<Vec<_>>(),
+            vec!["|...@...|", "|...@...|", "|...@...|", "|...@...|"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn extrapolation() -> Result<()> {
+        // Skipping ahead matches simulating every rock.
+        for rocks in [0, 1, 100, 2022, 5000] {
+            let mut ch = chamber(WIDTH, PIECES)?;
+            for _ in 0..rocks {
+                ch.drop_rock();
+            }
+            assert_eq!(chamber(WIDTH, PIECES)?.height_after(rocks), ch.height());
+        }
+        Ok(())
     }
 
     #[test]
-    fn p2() {
-        assert_eq!(part2(INPUT), 1514285714288);
+    fn configurable() -> Result<()> {
+        // Squares filling a chamber exactly as wide stack 2 units per rock.
+        assert_eq!(chamber(2, "##\n##")?.height_after(1_000_000), 2_000_000);
+        assert_eq!(
+            chamber(1, "#")?.height_after(1_000_000_000_000),
+            1_000_000_000_000
+        );
+        // Two halves of a 3 high block that can't be pushed sideways interlock perfectly.
+        assert_eq!(chamber(2, "#.\n##\n\n##\n.#")?.height_after(1000), 3 * 500);
+        assert_eq!(
+            chamber(3, PIECES).unwrap_err().to_string(),
+            "piece 0 is wider than the 3 unit chamber"
+        );
+        assert_eq!(
+            parse_jets("<>x").unwrap_err(),
+            ChamberError::UnknownJet('x')
+        );
+        assert_eq!(
+            parse_pieces("#\n\n.#.\n#o#").unwrap_err(),
+            ChamberError::BadPiece("#o#".into())
+        );
+        Ok(())
     }
 }
@@ -1,27 +1,244 @@
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
+#[derive(Debug, Error, PartialEq)]
+enum DropletError {
+    #[error("line {0}: expected 'x,y,z'")]
+    BadLine(usize),
+}
+
+fn neighbors(v: Vec3) -> impl Iterator<Item = Vec3> {
+    [
+        Vec3::new(1, 0, 0),
+        Vec3::new(-1, 0, 0),
+        Vec3::new(0, 1, 0),
+        Vec3::new(0, -1, 0),
+        Vec3::new(0, 0, 1),
+        Vec3::new(0, 0, -1),
+    ]
+    .into_iter()
+    .map(move |d| v + d)
+}
+
+/// Lava droplet as the set of 1x1x1 cubes it's made of.
+#[derive(Debug)]
+struct Droplet {
+    cubes: HashSet<Vec3>,
+    // Corners of a box one unit bigger than the droplet on every side, so air can flow all the
+    // way around it.
+    min: Vec3,
+    max: Vec3,
+}
+
+impl FromStr for Droplet {
+    type Err = DropletError;
+    fn from_str(s: &str) -> Result<Self, DropletError> {
+        let cubes: HashSet<Vec3> = s
+            .lines()
+            .enumerate()
+            .map(|(i, l)| {
+                let v = l
+                    .split(',')
+                    .map(|n| n.trim().parse())
+                    .collect::<Result<Vec<i64>, _>>();
+                match v.as_deref() {
+                    Ok(&[x, y, z]) => Ok(Vec3::new(x, y, z)),
+                    _ => Err(DropletError::BadLine(i + 1)),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        let (mut min, mut max) = ([i64::MAX; 3], [i64::MIN; 3]);
+        for c in &cubes {
+            for (i, v) in [c.x(), c.y(), c.z()].into_iter().enumerate() {
+                min[i] = min[i].min(v - 1);
+                max[i] = max[i].max(v + 1);
+            }
+        }
+        Ok(Droplet {
+            cubes,
+            min: Vec3::new(min[0], min[1], min[2]),
+            max: Vec3::new(max[0], max[1], max[2]),
+        })
+    }
+}
+
+impl Droplet {
+    fn in_bounds(&self, v: Vec3) -> bool {
+        (self.min.x()..=self.max.x()).contains(&v.x())
+            && (self.min.y()..=self.max.y()).contains(&v.y())
+            && (self.min.z()..=self.max.z()).contains(&v.z())
+    }
+
+    // Faces of `cubes` where the neighboring position is `touching`.
+    fn faces(cubes: &HashSet<Vec3>, touching: impl Fn(Vec3) -> bool) -> usize {
+        cubes
+            .iter()
+            .flat_map(|c| neighbors(*c))
+            .filter(|n| touching(*n))
+            .count()
+    }
+
+    /// Faces not touching another cube, including those facing pockets of trapped air.
+    fn surface_area(&self) -> usize {
+        Droplet::faces(&self.cubes, |n| !self.cubes.contains(&n))
+    }
+
+    /// Air reachable from outside the droplet, flood filled from a corner of the padded bounding
+    /// box.
+    fn exterior(&self) -> HashSet<Vec3> {
+        if self.cubes.is_empty() {
+            return HashSet::new();
+        }
+        let mut seen = HashSet::from([self.min]);
+        let mut queue = VecDeque::from([self.min]);
+        while let Some(v) = queue.pop_front() {
+            for n in neighbors(v) {
+                if self.in_bounds(n) && !self.cubes.contains(&n) && seen.insert(n) {
+                    queue.push_back(n);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Faces in contact with the air outside the droplet.
+    fn exterior_surface_area(&self) -> usize {
+        let exterior = self.exterior();
+        Droplet::faces(&self.cubes, |n| exterior.contains(&n))
+    }
+
+    /// Groups of connected air cubes trapped inside the droplet, each sorted, smallest first.
+    fn air_pockets(&self) -> Vec<Vec<Vec3>> {
+        let exterior = self.exterior();
+        let mut air = Vec::new();
+        for x in self.min.x()..=self.max.x() {
+            for y in self.min.y()..=self.max.y() {
+                for z in self.min.z()..=self.max.z() {
+                    let v = Vec3::new(x, y, z);
+                    if !self.cubes.contains(&v) && !exterior.contains(&v) {
+                        air.push(v);
+                    }
+                }
+            }
+        }
+        let index: HashMap<Vec3, usize> = air.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+        let mut uf = UnionFind::new(air.len());
+        for (i, v) in air.iter().enumerate() {
+            for n in neighbors(*v) {
+                if let Some(j) = index.get(&n) {
+                    uf.union(i, *j);
+                }
+            }
+        }
+        let mut pockets: Vec<Vec<Vec3>> = uf
+            .components()
+            .map(|c| c.into_iter().map(|i| air[i]).collect())
+            .collect();
+        pockets.sort_by_key(|p: &Vec<Vec3>| (p.len(), p[0]));
+        pockets
+    }
+}
+
 #[aoc(day18, part1)]
-fn part1(input: &str) -> usize {
-    0
+fn part1(input: &str) -> Result<usize> {
+    let droplet: Droplet = input.parse()?;
+    Ok(droplet.surface_area())
 }
 
-// #[aoc(day18, part2)]
-// fn part2(input: &str) -> usize { }
+#[aoc(day18, part2)]
+fn part2(input: &str) -> Result<usize> {
+    let droplet: Droplet = input.parse()?;
+    Ok(droplet.exterior_surface_area())
+}
+
+#[aoc(day18, part2, pockets)]
+fn part2_pockets(input: &str) -> Result<usize> {
+    let droplet: Droplet = input.parse()?;
+    let inside: usize = droplet
+        .air_pockets()
+        .into_iter()
+        .map(|p| Droplet::faces(&p.into_iter().collect(), |n| droplet.cubes.contains(&n)))
+        .sum();
+    Ok(droplet.surface_area() - inside)
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const INPUT: &'static str = r#"SOMETHING
-"#;
+    const INPUT: &str = r#"2,2,2
+1,2,2
+3,2,2
+2,1,2
+2,3,2
+2,2,1
+2,2,3
+2,2,4
+2,2,6
+1,2,5
+3,2,5
+2,1,5
+2,3,5"#;
 
     #[test]
-    fn p1() {
-        assert_eq!(part1(INPUT), 42);
+    fn p1() -> Result<()> {
+        assert_eq!(part1("1,1,1\n2,1,1")?, 10);
+        assert_eq!(part1(INPUT)?, 64);
+        Ok(())
     }
 
-    //#[test]
-    //fn p2() {
-    //    assert_eq!(part2(INPUT), 42);
-    //}
+    #[test]
+    fn p2() -> Result<()> {
+        assert_eq!(part2(INPUT)?, 58);
+        assert_eq!(part2_pockets(INPUT)?, 58);
+        Ok(())
+    }
+
+    #[test]
+    fn pockets() -> Result<()> {
+        let droplet: Droplet = INPUT.parse()?;
+        assert_eq!(droplet.air_pockets(), vec![vec![Vec3::new(2, 2, 5)]]);
+
+        // A hollow 4x4x4 box traps a 2x2x2 pocket, a hollow 3x3x3 box off to the side traps a
+        // single cube.
+        let mut shell = Vec::new();
+        for x in 0..4 {
+            for y in 0..4 {
+                for z in 0..4 {
+                    if [x, y, z].iter().any(|v| *v == 0 || *v == 3) {
+                        shell.push(format!("{x},{y},{z}"));
+                    }
+                }
+            }
+        }
+        for x in 10..13 {
+            for y in 10..13 {
+                for z in 10..13 {
+                    if (x, y, z) != (11, 11, 11) {
+                        shell.push(format!("{x},{y},{z}"));
+                    }
+                }
+            }
+        }
+        let droplet: Droplet = shell.join("\n").parse()?;
+        let pockets = droplet.air_pockets();
+        assert_eq!(
+            pockets.iter().map(|p| p.len()).collect::<Vec<_>>(),
+            vec![1, 8]
+        );
+        assert_eq!(pockets[0], vec![Vec3::new(11, 11, 11)]);
+        // Every face that isn't on the outside faces one of the pockets.
+        assert_eq!(
+            droplet.surface_area() - droplet.exterior_surface_area(),
+            6 + 8 * 3
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "1,1,1\n1,1".parse::<Droplet>().unwrap_err(),
+            DropletError::BadLine(2)
+        );
+    }
 }
@@ -1,27 +1,268 @@
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
+const VERBOSE: bool = false;
+
+#[derive(Debug, Error, PartialEq)]
+enum BlueprintError {
+    #[error("line {0}: expected a blueprint id and 6 robot costs")]
+    BadLine(usize),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Resource {
+    Ore,
+    Clay,
+    Obsidian,
+    Geode,
+}
+use Resource::*;
+
+const RESOURCES: [Resource; 4] = [Ore, Clay, Obsidian, Geode];
+
+impl Display for Resource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Ore => "ore",
+            Clay => "clay",
+            Obsidian => "obsidian",
+            Geode => "geode",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Blueprint {
+    id: usize,
+    // costs[robot][resource]
+    costs: [[u32; 4]; 4],
+    // Only one robot can be built a minute, so more robots than the most of a resource any robot
+    // needs is wasted, except for geode robots.
+    max_robots: [u32; 4],
+}
+
+impl Blueprint {
+    fn new(id: usize, costs: [[u32; 4]; 4]) -> Blueprint {
+        let mut max_robots = [u32::MAX; 4];
+        for r in [Ore, Clay, Obsidian] {
+            max_robots[r as usize] = costs.iter().map(|c| c[r as usize]).max().unwrap_or(0);
+        }
+        Blueprint {
+            id,
+            costs,
+            max_robots,
+        }
+    }
+}
+
+fn parse_blueprints(input: &str) -> Result<Vec<Blueprint>, BlueprintError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, l)| {
+            let nums: Vec<u32> = l
+                .split(|c: char| !c.is_ascii_digit())
+                .filter_map(|n| n.parse().ok())
+                .collect();
+            match nums[..] {
+                [id, ore, clay, obs_ore, obs_clay, geo_ore, geo_obs] => Ok(Blueprint::new(
+                    id as usize,
+                    [
+                        [ore, 0, 0, 0],
+                        [clay, 0, 0, 0],
+                        [obs_ore, obs_clay, 0, 0],
+                        [geo_ore, 0, geo_obs, 0],
+                    ],
+                )),
+                _ => Err(BlueprintError::BadLine(i + 1)),
+            }
+        })
+        .collect()
+}
+
+/// A robot whose construction starts during `minute`, it's ready at the end of it.
+#[derive(Clone, Debug, PartialEq)]
+struct Build {
+    minute: u32,
+    robot: Resource,
+}
+
+#[derive(Debug, PartialEq)]
+struct Plan {
+    geodes: u32,
+    builds: Vec<Build>,
+}
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for b in &self.builds {
+            writeln!(f, "== Minute {} == build {} robot", b.minute, b.robot)?;
+        }
+        write!(f, "{} geodes opened", self.geodes)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct State {
+    time_left: u32,
+    resources: [u32; 4],
+    robots: [u32; 4],
+}
+
+impl Blueprint {
+    /// Finds the most geodes that can be opened in `minutes`, starting with a single ore robot.
+    fn best(&self, minutes: u32) -> Plan {
+        let start = State {
+            time_left: minutes,
+            resources: [0; 4],
+            robots: [1, 0, 0, 0],
+        };
+        let mut best = Plan {
+            geodes: 0,
+            builds: Vec::new(),
+        };
+        self.search(minutes, start, &mut Vec::new(), &mut best);
+        best
+    }
+
+    // Branch on which robot to build next, waiting as long as it takes to afford it.
+    fn search(&self, minutes: u32, s: State, builds: &mut Vec<Build>, best: &mut Plan) {
+        let g = Geode as usize;
+        let t = s.time_left;
+        let idle = s.resources[g] + s.robots[g] * t;
+        if idle > best.geodes {
+            best.geodes = idle;
+            best.builds = builds.clone();
+        }
+        // Even building a geode robot every remaining minute wouldn't beat the best so far.
+        if idle + t * t.saturating_sub(1) / 2 <= best.geodes {
+            return;
+        }
+        for robot in RESOURCES.into_iter().rev() {
+            let r = robot as usize;
+            if s.robots[r] >= self.max_robots[r] {
+                continue;
+            }
+            let Some(wait) = self.wait_for(&s, robot) else {
+                continue;
+            };
+            // A robot finished in the last minute can't collect anything.
+            if wait + 1 >= t {
+                continue;
+            }
+            let mut next = s;
+            next.time_left = t - wait - 1;
+            for (i, res) in next.resources.iter_mut().enumerate() {
+                *res = *res + s.robots[i] * (wait + 1) - self.costs[r][i];
+            }
+            next.robots[r] += 1;
+            builds.push(Build {
+                minute: minutes - t + wait + 1,
+                robot,
+            });
+            self.search(minutes, next, builds, best);
+            builds.pop();
+        }
+    }
+
+    // Minutes of collecting needed before `robot` can be afforded, None if it never can be with
+    // the current robots.
+    fn wait_for(&self, s: &State, robot: Resource) -> Option<u32> {
+        let mut wait = 0;
+        for (i, cost) in self.costs[robot as usize].iter().enumerate() {
+            let short = cost.saturating_sub(s.resources[i]);
+            if short > 0 {
+                if s.robots[i] == 0 {
+                    return None;
+                }
+                wait = wait.max(short.div_ceil(s.robots[i]));
+            }
+        }
+        Some(wait)
+    }
+
+    fn quality_level(&self, minutes: u32) -> usize {
+        self.id * self.best(minutes).geodes as usize
+    }
+}
+
 #[aoc(day19, part1)]
-fn part1(input: &str) -> usize {
-    0
+fn part1(input: &str) -> Result<usize> {
+    let blueprints = parse_blueprints(input)?;
+    Ok(blueprints.iter().map(|b| b.quality_level(24)).sum())
 }
 
-// #[aoc(day19, part2)]
-// fn part2(input: &str) -> usize { }
+#[aoc(day19, part2)]
+fn part2(input: &str) -> Result<u32> {
+    let blueprints = parse_blueprints(input)?;
+    Ok(blueprints
+        .iter()
+        .take(3)
+        .map(|b| {
+            let plan = b.best(32);
+            vprint!("Blueprint {}:\n{plan}", b.id);
+            plan.geodes
+        })
+        .product())
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const INPUT: &'static str = r#"SOMETHING
-"#;
+    const INPUT: &str = r#"Blueprint 1: Each ore robot costs 4 ore. Each clay robot costs 2 ore. Each obsidian robot costs 3 ore and 14 clay. Each geode robot costs 2 ore and 7 obsidian.
+Blueprint 2: Each ore robot costs 2 ore. Each clay robot costs 3 ore. Each obsidian robot costs 3 ore and 8 clay. Each geode robot costs 3 ore and 12 obsidian."#;
+
+    #[test]
+    fn p1() -> Result<()> {
+        assert_eq!(part1(INPUT)?, 33);
+        Ok(())
+    }
 
     #[test]
-    fn p1() {
-        assert_eq!(part1(INPUT), 42);
+    fn p2() -> Result<()> {
+        let blueprints = parse_blueprints(INPUT)?;
+        assert_eq!(blueprints[0].best(32).geodes, 56);
+        assert_eq!(blueprints[1].best(32).geodes, 62);
+        Ok(())
     }
 
-    //#[test]
-    //fn p2() {
-    //    assert_eq!(part2(INPUT), 42);
-    //}
+    #[test]
+    fn build_order() -> Result<()> {
+        let blueprints = parse_blueprints(INPUT)?;
+        let plan = blueprints[0].best(24);
+        assert_eq!(plan.geodes, 9);
+        // Replay the builds and check they were affordable and open as many geodes as promised.
+        let bp = &blueprints[0];
+        let (mut resources, mut robots) = ([0; 4], [1, 0, 0, 0]);
+        let mut builds = plan.builds.iter().peekable();
+        for minute in 1..=24 {
+            let building = builds
+                .next_if(|b| b.minute == minute)
+                .map(|b| b.robot as usize);
+            for i in 0..4 {
+                let cost = building.map_or(0, |r| bp.costs[r][i]);
+                assert!(
+                    resources[i] >= cost,
+                    "can't afford robot in minute {minute}"
+                );
+                resources[i] = resources[i] + robots[i] - cost;
+            }
+            if let Some(r) = building {
+                robots[r] += 1;
+            }
+        }
+        assert!(builds.next().is_none());
+        assert_eq!(resources[Geode as usize], 9);
+        assert_eq!(plan.to_string().lines().last(), Some("9 geodes opened"));
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_blueprints("Blueprint 1: Each ore robot costs 4 ore.").unwrap_err(),
+            BlueprintError::BadLine(1)
+        );
+    }
 }
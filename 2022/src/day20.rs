@@ -1,55 +1,223 @@
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
-#[macro_use]
-use advent::vprint;
 const VERBOSE: bool = false;
 
-#[aoc(day20, part1)]
-fn part1(input: &str) -> isize {
-    let mut ring: VecDeque<isize> = input.lines().map(|l| l.parse().expect("number")).collect();
-    vprint!("{ring:?}");
-    let orig_ring = ring.clone();
-    for o in orig_ring {
-        let src: isize = ring
+const DECRYPTION_KEY: i64 = 811589153;
+const NIL: usize = usize::MAX;
+
+#[derive(Debug, Error, PartialEq)]
+enum MixError {
+    #[error("no 0 in the file")]
+    NoZero,
+    #[error("{0} times the decryption key overflows")]
+    Overflow(i64),
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    left: usize,
+    right: usize,
+    parent: usize,
+    size: usize,
+    priority: u64,
+}
+
+/// The encrypted file as an implicit treap, an order-statistic tree where each number's place in
+/// the list is its position in an in-order walk. Nodes are indexed by where the number was in the
+/// original file, so duplicate numbers are told apart and each can be found in O(log n) by
+/// walking up to the root.
+#[derive(Debug)]
+struct Mixer {
+    values: Vec<i64>,
+    nodes: Vec<Node>,
+    root: usize,
+}
+
+impl Mixer {
+    fn new(values: Vec<i64>, key: i64) -> Result<Mixer, MixError> {
+        let values = values
+            .into_iter()
+            .map(|v| v.checked_mul(key).ok_or(MixError::Overflow(v)))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Fixed seed so mixing is deterministic.
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+        let nodes = (0..values.len())
+            .map(|_| Node {
+                left: NIL,
+                right: NIL,
+                parent: NIL,
+                size: 1,
+                priority: rng.next_u64(),
+            })
+            .collect();
+        let mut m = Mixer {
+            values,
+            nodes,
+            root: NIL,
+        };
+        for i in 0..m.values.len() {
+            m.root = m.merge(m.root, i);
+        }
+        Ok(m)
+    }
+
+    fn size(&self, t: usize) -> usize {
+        if t == NIL { 0 } else { self.nodes[t].size }
+    }
+
+    // Recomputes `t`'s size and points its children back at it.
+    fn update(&mut self, t: usize) {
+        let (l, r) = (self.nodes[t].left, self.nodes[t].right);
+        self.nodes[t].size = self.size(l) + self.size(r) + 1;
+        for c in [l, r] {
+            if c != NIL {
+                self.nodes[c].parent = t;
+            }
+        }
+    }
+
+    fn merge(&mut self, a: usize, b: usize) -> usize {
+        if a == NIL || b == NIL {
+            let t = if a == NIL { b } else { a };
+            if t != NIL {
+                self.nodes[t].parent = NIL;
+            }
+            return t;
+        }
+        if self.nodes[a].priority > self.nodes[b].priority {
+            self.nodes[a].right = self.merge(self.nodes[a].right, b);
+            self.update(a);
+            self.nodes[a].parent = NIL;
+            a
+        } else {
+            self.nodes[b].left = self.merge(a, self.nodes[b].left);
+            self.update(b);
+            self.nodes[b].parent = NIL;
+            b
+        }
+    }
+
+    // Splits `t` into its first `k` numbers and the rest.
+    fn split(&mut self, t: usize, k: usize) -> (usize, usize) {
+        if t == NIL {
+            return (NIL, NIL);
+        }
+        self.nodes[t].parent = NIL;
+        let left = self.nodes[t].left;
+        if self.size(left) < k {
+            let (a, b) = self.split(self.nodes[t].right, k - self.size(left) - 1);
+            self.nodes[t].right = a;
+            self.update(t);
+            if b != NIL {
+                self.nodes[b].parent = NIL;
+            }
+            (t, b)
+        } else {
+            let (a, b) = self.split(left, k);
+            self.nodes[t].left = b;
+            self.update(t);
+            if a != NIL {
+                self.nodes[a].parent = NIL;
+            }
+            (a, t)
+        }
+    }
+
+    /// Current position in the list of the number originally at index `i`.
+    fn position(&self, i: usize) -> usize {
+        let mut pos = self.size(self.nodes[i].left);
+        let mut cur = i;
+        while self.nodes[cur].parent != NIL {
+            let p = self.nodes[cur].parent;
+            if self.nodes[p].right == cur {
+                pos += self.size(self.nodes[p].left) + 1;
+            }
+            cur = p;
+        }
+        pos
+    }
+
+    /// Number at position `k`, wrapping around the end of the list.
+    fn nth(&self, k: usize) -> i64 {
+        let mut k = k % self.values.len();
+        let mut t = self.root;
+        loop {
+            let left = self.size(self.nodes[t].left);
+            match k.cmp(&left) {
+                Ordering::Less => t = self.nodes[t].left,
+                Ordering::Equal => return self.values[t],
+                Ordering::Greater => {
+                    k -= left + 1;
+                    t = self.nodes[t].right;
+                }
+            }
+        }
+    }
+
+    /// Moves every number, in original order, forward or back as many places as its value.
+    fn mix(&mut self) {
+        let n = self.values.len();
+        if n < 2 {
+            return;
+        }
+        for i in 0..n {
+            let pos = self.position(i);
+            let (a, b) = self.split(self.root, pos);
+            let (_, c) = self.split(b, 1);
+            let rest = self.merge(a, c);
+            // Moving n - 1 places, past every other number, ends up where it started.
+            let m = n as i64 - 1;
+            let to = (pos as i64 + self.values[i].rem_euclid(m)).rem_euclid(m) as usize;
+            let (a, c) = self.split(rest, to);
+            let a = self.merge(a, i);
+            self.root = self.merge(a, c);
+        }
+    }
+
+    fn to_vec(&self) -> Vec<i64> {
+        (0..self.values.len()).map(|k| self.nth(k)).collect()
+    }
+
+    /// Sum of the 1000th, 2000th and 3000th numbers after the 0.
+    fn grove_coordinates(&self) -> Result<i64, MixError> {
+        let zero = self
+            .values
             .iter()
-            .position(|v: &isize| *v == o)
-            .unwrap()
-            .try_into()
-            .unwrap();
-
-        ring.rotate_left(src as usize);
-        vprint!("after rotate1 {ring:?}");
-        let v = ring.pop_front().unwrap();
-        vprint!("after pop {ring:?}");
-        let shft = v.rem_euclid(ring.len().try_into().unwrap());
-        ring.rotate_left(shft.try_into().unwrap());
-        vprint!("after rotate2 {ring:?}");
-        ring.push_front(v);
-        vprint!("{o} shl {src} shr {shft}: {v}");
-
-        vprint!("**********");
-        vprint!("{ring:?}");
-    }
-
-    let off = ring.iter().position(|&v| v == 0).unwrap();
-    let v = ring[(off + 1000) % ring.len()]
-        + ring[(off + 2000) % ring.len()]
-        + ring[(off + 3000) % ring.len()];
-    assert_ne!(-9792, v);
-    assert_ne!(-9344, v);
-    assert_ne!(2832, v);
-    //assert_ne!(3, v);
-    v
+            .position(|v| *v == 0)
+            .ok_or(MixError::NoZero)?;
+        let pos = self.position(zero);
+        Ok([1000, 2000, 3000].iter().map(|k| self.nth(pos + k)).sum())
+    }
 }
 
-// #[aoc(day20, part2)]
-// fn part2(input: &str) -> usize { }
+fn decrypt(input: &str, key: i64, rounds: usize) -> Result<i64> {
+    let values = input
+        .lines()
+        .map(|l| l.trim().parse())
+        .collect::<Result<Vec<i64>, _>>()?;
+    let mut mixer = Mixer::new(values, key)?;
+    for round in 1..=rounds {
+        mixer.mix();
+        vprint!("After {round} round(s) of mixing:\n{:?}", mixer.to_vec());
+    }
+    Ok(mixer.grove_coordinates()?)
+}
+
+#[aoc(day20, part1)]
+fn part1(input: &str) -> Result<i64> {
+    decrypt(input, 1, 1)
+}
+
+#[aoc(day20, part2)]
+fn part2(input: &str) -> Result<i64> {
+    decrypt(input, DECRYPTION_KEY, 10)
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const INPUT: &'static str = r#"1
+    const INPUT: &str = r#"1
 2
 -3
 3
@@ -58,13 +226,107 @@ mod tests {
 4
 "#;
 
+    // Rotates the list to start with the number originally first, how the puzzle prints it.
+    fn from_first(m: &Mixer) -> Vec<i64> {
+        let mut v = m.to_vec();
+        v.rotate_left(m.position(0));
+        v
+    }
+
+    // Straightforward O(n^2) mixing, tracking original indices.
+    fn naive_mix(values: &[i64], rounds: usize) -> Vec<i64> {
+        let n = values.len();
+        let mut order: Vec<usize> = (0..n).collect();
+        for _ in 0..rounds {
+            for (i, v) in values.iter().enumerate() {
+                let pos = order.iter().position(|o| *o == i).unwrap();
+                order.remove(pos);
+                let to = (pos as i64 + v).rem_euclid(n as i64 - 1) as usize;
+                order.insert(to, i);
+            }
+        }
+        order.into_iter().map(|i| values[i]).collect()
+    }
+
+    #[test]
+    fn p1() -> Result<()> {
+        assert_eq!(part1(INPUT)?, 3);
+        Ok(())
+    }
+
     #[test]
-    fn p1() {
-        assert_eq!(part1(INPUT), 3);
+    fn p2() -> Result<()> {
+        assert_eq!(part2(INPUT)?, 1623178306);
+        Ok(())
     }
 
-    //#[test]
-    //fn p2() {
-    //    assert_eq!(part2(INPUT), 42);
-    //}
+    #[test]
+    fn rounds() -> Result<()> {
+        let mut m = Mixer::new(vec![1, 2, -3, 3, -2, 0, 4], 1)?;
+        m.mix();
+        assert_eq!(from_first(&m), vec![1, 2, -3, 4, 0, 3, -2]);
+
+        let mut m = Mixer::new(vec![1, 2, -3, 3, -2, 0, 4], DECRYPTION_KEY)?;
+        m.mix();
+        let mut v = m.to_vec();
+        v.rotate_left(m.position(5));
+        assert_eq!(
+            v,
+            vec![
+                0,
+                -2434767459,
+                3246356612,
+                -1623178306,
+                2434767459,
+                1623178306,
+                811589153
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn duplicates() -> Result<()> {
+        let mut rng = XorShift::new(7);
+        for n in [2, 3, 10, 50, 200] {
+            let values: Vec<i64> = (0..n)
+                .map(|_| {
+                    let seed = rng.next_u64();
+                    // Lots of repeats, and values bigger than the list.
+                    (seed % 21) as i64 - 10 + if seed.is_multiple_of(7) { 1000 } else { 0 }
+                })
+                .collect();
+            let mut m = Mixer::new(values.clone(), 1)?;
+            for rounds in 1..=3 {
+                m.mix();
+                assert_eq!(
+                    m.to_vec(),
+                    naive_mix(&values, rounds),
+                    "n {n} rounds {rounds}"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn huge_values() -> Result<()> {
+        // Values near the limits mustn't overflow when added to a position.
+        let mut m = Mixer::new(vec![0, i64::MAX, 3, -i64::MAX, i64::MAX - 1], 1)?;
+        m.mix();
+        assert_eq!(m.to_vec(), vec![-i64::MAX, i64::MAX, i64::MAX - 1, 3, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            decrypt("1\n2", 1, 1).unwrap_err().to_string(),
+            "no 0 in the file"
+        );
+        assert_eq!(
+            Mixer::new(vec![i64::MAX], 2).unwrap_err(),
+            MixError::Overflow(i64::MAX)
+        );
+    }
 }
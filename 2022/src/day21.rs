@@ -1,27 +1,413 @@
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
+const VERBOSE: bool = false;
+
+const ROOT: &str = "root";
+const HUMAN: &str = "humn";
+
+#[derive(Debug, Error, PartialEq)]
+enum MathError {
+    #[error("line {0}: expected 'name: number' or 'name: a op b'")]
+    BadLine(usize),
+    #[error("unknown monkey {0}")]
+    UnknownMonkey(String),
+    #[error("{0} depends on itself")]
+    Cycle(String),
+    #[error("{0} overflowed or divided by zero")]
+    Arithmetic(String),
+    #[error("{0} isn't a whole number")]
+    NotInteger(Ratio),
+    #[error("{0} multiplies or divides by the unknown, so isn't linear")]
+    NonLinear(String),
+    #[error("no single value balances {ROOT}")]
+    NoSolution,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Op {
+    fn symbol(self) -> char {
+        match self {
+            Op::Add => '+',
+            Op::Sub => '-',
+            Op::Mul => '*',
+            Op::Div => '/',
+        }
+    }
+
+    fn apply(self, a: Ratio, b: Ratio) -> Option<Ratio> {
+        match self {
+            Op::Add => a.checked_add(b),
+            Op::Sub => a.checked_sub(b),
+            Op::Mul => a.checked_mul(b),
+            Op::Div => a.checked_div(b),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Job {
+    Number(i64),
+    Op(Op, usize, usize),
+}
+
+/// `a * x + b` for the unknown `x`, what each monkey yells once one of them is unknown.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Linear {
+    a: Ratio,
+    b: Ratio,
+}
+
+impl Linear {
+    fn constant(b: Ratio) -> Linear {
+        Linear {
+            a: Ratio::from(0),
+            b,
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        self.a.is_zero()
+    }
+
+    fn apply(self, op: Op, rhs: Linear) -> Option<Linear> {
+        let (l, r) = (self, rhs);
+        Some(match op {
+            Op::Add | Op::Sub => Linear {
+                a: op.apply(l.a, r.a)?,
+                b: op.apply(l.b, r.b)?,
+            },
+            Op::Mul if l.is_constant() => Linear {
+                a: r.a.checked_mul(l.b)?,
+                b: r.b.checked_mul(l.b)?,
+            },
+            Op::Mul | Op::Div if r.is_constant() => Linear {
+                a: op.apply(l.a, r.b)?,
+                b: op.apply(l.b, r.b)?,
+            },
+            Op::Mul | Op::Div => return None,
+        })
+    }
+}
+
+/// Monkeys and their jobs, a DAG where each monkey waits on the two it takes numbers from.
+#[derive(Debug)]
+struct Monkeys {
+    names: Vec<String>,
+    jobs: Vec<Job>,
+    index: HashMap<String, usize>,
+}
+
+impl FromStr for Monkeys {
+    type Err = MathError;
+    fn from_str(s: &str) -> Result<Self, MathError> {
+        let mut lines = Vec::new();
+        for (i, l) in s.lines().enumerate() {
+            let (name, job) = l.split_once(": ").ok_or(MathError::BadLine(i + 1))?;
+            lines.push((i + 1, name.to_string(), job));
+        }
+        let index: HashMap<String, usize> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, (_, name, _))| (name.clone(), i))
+            .collect();
+        let lookup = |name: &str| {
+            index
+                .get(name)
+                .copied()
+                .ok_or_else(|| MathError::UnknownMonkey(name.to_string()))
+        };
+        let mut jobs = Vec::with_capacity(lines.len());
+        for (line, _, job) in &lines {
+            let job = match job.split(' ').collect::<Vec<_>>()[..] {
+                [n] => Job::Number(n.parse().map_err(|_| MathError::BadLine(*line))?),
+                [a, op, b] => {
+                    let op = match op {
+                        "+" => Op::Add,
+                        "-" => Op::Sub,
+                        "*" => Op::Mul,
+                        "/" => Op::Div,
+                        _ => return Err(MathError::BadLine(*line)),
+                    };
+                    Job::Op(op, lookup(a)?, lookup(b)?)
+                }
+                _ => return Err(MathError::BadLine(*line)),
+            };
+            jobs.push(job);
+        }
+        Ok(Monkeys {
+            names: lines.into_iter().map(|(_, name, _)| name).collect(),
+            jobs,
+            index,
+        })
+    }
+}
+
+/// Prints the root's job as a fully parenthesized infix expression.
+impl Display for Monkeys {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.index.get(ROOT) {
+            Some(root) => write!(f, "{}", self.infix(*root, None, &HashMap::new())),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Monkeys {
+    fn lookup(&self, name: &str) -> Result<usize, MathError> {
+        self.index
+            .get(name)
+            .copied()
+            .ok_or_else(|| MathError::UnknownMonkey(name.to_string()))
+    }
+
+    /// What every monkey `node` depends on yells, with `unknown` standing in for the variable.
+    fn solve_all(
+        &self,
+        node: usize,
+        unknown: Option<usize>,
+    ) -> Result<HashMap<usize, Linear>, MathError> {
+        let mut values = HashMap::new();
+        let mut visiting = HashSet::new();
+        self.eval(node, unknown, &mut values, &mut visiting)?;
+        Ok(values)
+    }
+
+    fn eval(
+        &self,
+        node: usize,
+        unknown: Option<usize>,
+        values: &mut HashMap<usize, Linear>,
+        visiting: &mut HashSet<usize>,
+    ) -> Result<Linear, MathError> {
+        if let Some(v) = values.get(&node) {
+            return Ok(*v);
+        }
+        if !visiting.insert(node) {
+            return Err(MathError::Cycle(self.names[node].clone()));
+        }
+        let v = match self.jobs[node] {
+            _ if Some(node) == unknown => Linear {
+                a: Ratio::from(1),
+                b: Ratio::from(0),
+            },
+            Job::Number(n) => Linear::constant(Ratio::from(n)),
+            Job::Op(op, a, b) => {
+                let a = self.eval(a, unknown, values, visiting)?;
+                let b = self.eval(b, unknown, values, visiting)?;
+                let name = || self.names[node].clone();
+                let nonlinear = match op {
+                    Op::Mul => !a.is_constant() && !b.is_constant(),
+                    Op::Div => !b.is_constant(),
+                    Op::Add | Op::Sub => false,
+                };
+                if nonlinear {
+                    return Err(MathError::NonLinear(name()));
+                }
+                a.apply(op, b)
+                    .ok_or_else(|| MathError::Arithmetic(name()))?
+            }
+        };
+        visiting.remove(&node);
+        values.insert(node, v);
+        Ok(v)
+    }
+
+    /// Number the monkey called `name` yells.
+    fn yell(&self, name: &str) -> Result<Ratio, MathError> {
+        let node = self.lookup(name)?;
+        Ok(self.solve_all(node, None)?[&node].b)
+    }
+
+    /// Number `unknown` must yell so both sides of `root`'s job are equal.
+    fn balance(&self, root: &str, unknown: &str) -> Result<Ratio, MathError> {
+        let (l, r, _, values) = self.sides(root, unknown)?;
+        let (l, r) = (values[&l], values[&r]);
+        // l.a * x + l.b = r.a * x + r.b
+        let a = l.a.checked_sub(r.a).ok_or(MathError::NoSolution)?;
+        let b = r.b.checked_sub(l.b).ok_or(MathError::NoSolution)?;
+        b.checked_div(a).ok_or(MathError::NoSolution)
+    }
+
+    // The two monkeys `root` listens to, the unknown, and what every monkey they depend on yells
+    // in terms of it. Root's own operation is ignored, it's really checking for equality.
+    fn sides(
+        &self,
+        root: &str,
+        unknown: &str,
+    ) -> Result<(usize, usize, usize, HashMap<usize, Linear>), MathError> {
+        let Job::Op(_, l, r) = self.jobs[self.lookup(root)?] else {
+            return Err(MathError::NoSolution);
+        };
+        let x = self.lookup(unknown)?;
+        let mut values = self.solve_all(l, Some(x))?;
+        values.extend(self.solve_all(r, Some(x))?);
+        Ok((l, r, x, values))
+    }
+
+    // Infix form of `node`'s job. With an unknown, it's printed by name and every part of the
+    // expression not involving it is folded to a number.
+    fn infix(
+        &self,
+        node: usize,
+        unknown: Option<usize>,
+        values: &HashMap<usize, Linear>,
+    ) -> String {
+        if Some(node) == unknown {
+            return self.names[node].clone();
+        }
+        if let Some(v) = values.get(&node).filter(|v| v.is_constant()) {
+            return v.b.to_string();
+        }
+        match self.jobs[node] {
+            Job::Number(n) => n.to_string(),
+            Job::Op(op, a, b) => format!(
+                "({} {} {})",
+                self.infix(a, unknown, values),
+                op.symbol(),
+                self.infix(b, unknown, values)
+            ),
+        }
+    }
+
+    /// The equation part 2 solves, `root`'s sides with everything but `unknown` folded.
+    fn equation(&self, root: &str, unknown: &str) -> Result<String, MathError> {
+        let (l, r, x, values) = self.sides(root, unknown)?;
+        Ok(format!(
+            "{} = {}",
+            self.infix(l, Some(x), &values),
+            self.infix(r, Some(x), &values)
+        ))
+    }
+}
+
+fn to_integer(r: Ratio) -> Result<i64, MathError> {
+    r.to_integer().ok_or(MathError::NotInteger(r))
+}
+
 #[aoc(day21, part1)]
-fn part1(input: &str) -> usize {
-    0
+fn part1(input: &str) -> Result<i64> {
+    let monkeys: Monkeys = input.parse()?;
+    Ok(to_integer(monkeys.yell(ROOT)?)?)
 }
 
-// #[aoc(day21, part2)]
-// fn part2(input: &str) -> usize { }
+#[aoc(day21, part2)]
+fn part2(input: &str) -> Result<i64> {
+    let monkeys: Monkeys = input.parse()?;
+    vprint!("{}", monkeys.equation(ROOT, HUMAN)?);
+    Ok(to_integer(monkeys.balance(ROOT, HUMAN)?)?)
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const INPUT: &'static str = r#"SOMETHING
-"#;
+    const INPUT: &str = r#"root: pppw + sjmn
+dbpl: 5
+cczh: sllz + lgvd
+zczc: 2
+ptdq: humn - dvpt
+dvpt: 3
+lfqf: 4
+humn: 5
+ljgn: 2
+sjmn: drzm * dbpl
+sllz: 4
+pppw: cczh / lfqf
+lgvd: ljgn * ptdq
+drzm: hmdt - zczc
+hmdt: 32"#;
+
+    #[test]
+    fn p1() -> Result<()> {
+        assert_eq!(part1(INPUT)?, 152);
+        Ok(())
+    }
 
     #[test]
-    fn p1() {
-        assert_eq!(part1(INPUT), 42);
+    fn p2() -> Result<()> {
+        assert_eq!(part2(INPUT)?, 301);
+        Ok(())
     }
 
-    //#[test]
-    //fn p2() {
-    //    assert_eq!(part2(INPUT), 42);
-    //}
+    #[test]
+    fn printing() -> Result<()> {
+        let monkeys: Monkeys = INPUT.parse()?;
+        assert_eq!(
+            monkeys.to_string(),
+            "(((4 + (2 * (5 - 3))) / 4) + ((32 - 2) * 5))"
+        );
+        assert_eq!(
+            monkeys.equation(ROOT, HUMAN)?,
+            "((4 + (2 * (humn - 3))) / 4) = 150"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn solving() -> Result<()> {
+        // The unknown on the right, under a division and subtracted from a constant.
+        let monkeys: Monkeys =
+            "root: aaaa * bbbb\naaaa: 7\nbbbb: cccc / dddd\ncccc: hund - humn\ndddd: 3\nhund: 100\nhumn: 0"
+                .parse()?;
+        assert_eq!(monkeys.balance(ROOT, HUMAN)?, Ratio::from(79));
+        // Balanced by a fraction.
+        let fraction = "root: humn + half\nhumn: 1\nhalf: one / two\none: 1\ntwo: 2";
+        assert_eq!(
+            fraction.parse::<Monkeys>()?.balance(ROOT, HUMAN)?,
+            Ratio::new(1, 2)
+        );
+        assert_eq!(
+            part2(fraction).unwrap_err().to_string(),
+            "1/2 isn't a whole number"
+        );
+        Ok(())
+    }
+    #[test]
+    fn errors() -> Result<()> {
+        let parse = |s: &str| s.parse::<Monkeys>().unwrap_err();
+        assert_eq!(
+            parse("root: aaaa + bbbb\naaaa: 1"),
+            MathError::UnknownMonkey("bbbb".into())
+        );
+        assert_eq!(parse("root: 1\nbad"), MathError::BadLine(2));
+        assert_eq!(parse("root: a % b\na: 1\nb: 2"), MathError::BadLine(1));
+
+        let yell = |s: &str| s.parse::<Monkeys>().unwrap().yell(ROOT).unwrap_err();
+        assert_eq!(
+            yell("root: a + b\na: b * b\nb: a - a"),
+            MathError::Cycle("a".into())
+        );
+        assert_eq!(
+            yell("root: a / b\na: 1\nb: 0"),
+            MathError::Arithmetic(ROOT.into())
+        );
+        assert_eq!(
+            yell("root: a * a\na: 9223372036854775807"),
+            MathError::Arithmetic(ROOT.into())
+        );
+
+        let balance = |s: &str| {
+            s.parse::<Monkeys>()
+                .unwrap()
+                .balance(ROOT, HUMAN)
+                .unwrap_err()
+        };
+        assert_eq!(
+            balance("root: a + b\na: humn * humn\nb: 1\nhumn: 1"),
+            MathError::NonLinear("a".into())
+        );
+        // humn cancels out, so every value or none balances root.
+        assert_eq!(
+            balance("root: a + b\na: humn - humn\nb: 1\nhumn: 1"),
+            MathError::NoSolution
+        );
+        Ok(())
+    }
 }
@@ -1,27 +1,506 @@
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
+const VERBOSE: bool = false;
+
+// Facing values are also the password's, clockwise from right.
+const RIGHT: usize = 0;
+const DOWN: usize = 1;
+const LEFT: usize = 2;
+const DELTAS: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const ARROWS: [char; 4] = ['>', 'v', '<', '^'];
+
+#[derive(Debug, Error, PartialEq)]
+enum MapError {
+    #[error("expected the map, a blank line and then the path")]
+    NoPath,
+    #[error("unexpected {0:?} in the path")]
+    BadStep(char),
+    #[error("the map has no open tiles")]
+    NoStart,
+    #[error("the map doesn't fold into a cube: {0}")]
+    NotACube(&'static str),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Step {
+    Move(usize),
+    Left,
+    Right,
+}
+
+fn parse_path(s: &str) -> Result<Vec<Step>, MapError> {
+    let mut steps = Vec::new();
+    let mut n = None;
+    for c in s.trim().chars() {
+        if let Some(d) = c.to_digit(10) {
+            n = Some(n.unwrap_or(0) * 10 + d as usize);
+            continue;
+        }
+        if let Some(n) = n.take() {
+            steps.push(Step::Move(n));
+        }
+        steps.push(match c {
+            'L' => Step::Left,
+            'R' => Step::Right,
+            c => return Err(MapError::BadStep(c)),
+        });
+    }
+    steps.extend(n.map(Step::Move));
+    Ok(steps)
+}
+
+/// The monkeys' map, ' ' is off the board, '.' open and '#' a wall.
+#[derive(Debug)]
+struct Board {
+    map: Image<char>,
+}
+
+impl FromStr for Board {
+    type Err = MapError;
+    fn from_str(s: &str) -> Result<Self, MapError> {
+        let lines: Vec<&str> = s.lines().collect();
+        let width = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+        let mut map = Image::new(width, lines.len(), ' ');
+        for (y, l) in lines.iter().enumerate() {
+            for (x, c) in l.chars().enumerate() {
+                map[(x, y)] = c;
+            }
+        }
+        Ok(Board { map })
+    }
+}
+
+/// How a walker stepping off the edge of the board at `pos` facing `facing` comes back on.
+trait Wrapping {
+    fn wrap(&self, board: &Board, pos: (usize, usize), facing: usize) -> ((usize, usize), usize);
+}
+
+/// Wraps around to the far side of the board's row or column.
+struct Flat;
+
+impl Wrapping for Flat {
+    fn wrap(&self, board: &Board, pos: (usize, usize), facing: usize) -> ((usize, usize), usize) {
+        let back = (facing + 2) % 4;
+        let mut pos = pos;
+        while let Some(p) = board.step(pos, back) {
+            pos = p;
+        }
+        (pos, facing)
+    }
+}
+
+type V3 = [i8; 3];
+
+fn neg(v: V3) -> V3 {
+    v.map(|c| -c)
+}
+
+// Orientation of a face once the net is folded up, as 3D unit vectors.
+#[derive(Copy, Clone, Debug)]
+struct Frame {
+    normal: V3,
+    right: V3,
+    down: V3,
+}
+
+impl Frame {
+    // Where each edge of the face leads in 3D, by facing.
+    fn edge(&self, facing: usize) -> V3 {
+        [self.right, self.down, neg(self.right), neg(self.down)][facing]
+    }
+
+    // Direction positions along an edge count up in, in 3D.
+    fn along(&self, facing: usize) -> V3 {
+        if facing == RIGHT || facing == LEFT {
+            self.down
+        } else {
+            self.right
+        }
+    }
+
+    // Frame of the neighboring face in the net once it's folded over the edge.
+    fn fold(&self, facing: usize) -> Frame {
+        let Frame {
+            normal,
+            right,
+            down,
+        } = *self;
+        match facing {
+            RIGHT => Frame {
+                normal: right,
+                right: neg(normal),
+                down,
+            },
+            DOWN => Frame {
+                normal: down,
+                right,
+                down: neg(normal),
+            },
+            LEFT => Frame {
+                normal: neg(right),
+                right: normal,
+                down,
+            },
+            _ => Frame {
+                normal: neg(down),
+                right,
+                down: normal,
+            },
+        }
+    }
+}
+
+/// Folds the board into a cube. Which edges meet is worked out by folding the net in 3D, so any
+/// of the 11 cube nets works.
+#[derive(Debug)]
+struct Cube {
+    size: usize,
+    // Face grid position in the net to its orientation.
+    faces: HashMap<(usize, usize), Frame>,
+}
+
+impl Cube {
+    fn new(board: &Board) -> Result<Cube, MapError> {
+        let map = &board.map;
+        let tiles = (0..map.height)
+            .flat_map(|y| (0..map.width).map(move |x| (x, y)))
+            .filter(|p| map[*p] != ' ')
+            .count();
+        let size = (1..=tiles).find(|s| s * s * 6 >= tiles).unwrap_or(0);
+        if size == 0 || size * size * 6 != tiles {
+            return Err(MapError::NotACube("tile count isn't six squares"));
+        }
+        let cells: HashSet<(usize, usize)> = (0..map.height.div_ceil(size))
+            .flat_map(|fy| (0..map.width.div_ceil(size)).map(move |fx| (fx, fy)))
+            .filter(|(fx, fy)| {
+                map.get((fx * size) as isize, (fy * size) as isize)
+                    .is_some_and(|c| c != ' ')
+            })
+            .collect();
+        if cells.len() != 6 {
+            return Err(MapError::NotACube("not six square faces"));
+        }
+        let first = *cells
+            .iter()
+            .min_by_key(|(x, y)| (*y, *x))
+            .expect("six faces");
+        let mut faces = HashMap::from([(
+            first,
+            Frame {
+                normal: [0, 0, -1],
+                right: [1, 0, 0],
+                down: [0, 1, 0],
+            },
+        )]);
+        let mut queue = VecDeque::from([first]);
+        while let Some(f) = queue.pop_front() {
+            let frame = faces[&f];
+            for (facing, (dx, dy)) in DELTAS.iter().enumerate() {
+                let (Some(x), Some(y)) = (f.0.checked_add_signed(*dx), f.1.checked_add_signed(*dy))
+                else {
+                    continue;
+                };
+                if cells.contains(&(x, y)) && !faces.contains_key(&(x, y)) {
+                    faces.insert((x, y), frame.fold(facing));
+                    queue.push_back((x, y));
+                }
+            }
+        }
+        let normals: HashSet<V3> = faces.values().map(|f| f.normal).collect();
+        if faces.len() != 6 || normals.len() != 6 {
+            return Err(MapError::NotACube("faces overlap when folded"));
+        }
+        Ok(Cube { size, faces })
+    }
+}
+
+impl Wrapping for Cube {
+    fn wrap(&self, _: &Board, pos: (usize, usize), facing: usize) -> ((usize, usize), usize) {
+        let s = self.size;
+        let face = (pos.0 / s, pos.1 / s);
+        let (lx, ly) = (pos.0 % s, pos.1 % s);
+        let from = self.faces[&face];
+        let to_normal = from.edge(facing);
+        let (&to, frame) = self
+            .faces
+            .iter()
+            .find(|(_, f)| f.normal == to_normal)
+            .expect("every direction has a face");
+        // The edge of the new face that's shared with this one.
+        let edge = (0..4)
+            .find(|e| frame.edge(*e) == from.normal)
+            .expect("faces share an edge");
+        let i = if facing == RIGHT || facing == LEFT {
+            ly
+        } else {
+            lx
+        };
+        let i = if frame.along(edge) == from.along(facing) {
+            i
+        } else {
+            s - 1 - i
+        };
+        let (x, y) = match edge {
+            RIGHT => (s - 1, i),
+            DOWN => (i, s - 1),
+            LEFT => (0, i),
+            _ => (i, 0),
+        };
+        ((to.0 * s + x, to.1 * s + y), (edge + 2) % 4)
+    }
+}
+
+/// Where a walk ended, and optionally every tile it passed through on the way.
+#[derive(Debug)]
+struct Walk {
+    pos: (usize, usize),
+    facing: usize,
+    trace: Vec<((usize, usize), usize)>,
+}
+
+impl Walk {
+    fn password(&self) -> usize {
+        1000 * (self.pos.1 + 1) + 4 * (self.pos.0 + 1) + self.facing
+    }
+}
+
+impl Board {
+    fn is_open(&self, p: (usize, usize)) -> bool {
+        self.map[p] == '.'
+    }
+
+    // Next tile on the board facing `facing`, None when that's off the board.
+    fn step(&self, (x, y): (usize, usize), facing: usize) -> Option<(usize, usize)> {
+        let (dx, dy) = DELTAS[facing];
+        let (x, y) = (x as isize + dx, y as isize + dy);
+        match self.map.get(x, y) {
+            Some(c) if c != ' ' => Some((x as usize, y as usize)),
+            _ => None,
+        }
+    }
+
+    fn start(&self) -> Result<(usize, usize), MapError> {
+        (0..self.map.width)
+            .map(|x| (x, 0))
+            .find(|p| self.is_open(*p))
+            .ok_or(MapError::NoStart)
+    }
+
+    /// Follows the path from the leftmost open tile of the top row, facing right.
+    fn walk(&self, path: &[Step], wrapping: &impl Wrapping, trace: bool) -> Result<Walk, MapError> {
+        let mut pos = self.start()?;
+        let mut facing = RIGHT;
+        let mut tiles = vec![(pos, facing)];
+        for step in path {
+            match step {
+                Step::Left => facing = (facing + 3) % 4,
+                Step::Right => facing = (facing + 1) % 4,
+                Step::Move(n) => {
+                    for _ in 0..*n {
+                        let (next, next_facing) = match self.step(pos, facing) {
+                            Some(p) => (p, facing),
+                            None => wrapping.wrap(self, pos, facing),
+                        };
+                        if !self.is_open(next) {
+                            break;
+                        }
+                        (pos, facing) = (next, next_facing);
+                        if trace {
+                            tiles.push((pos, facing));
+                        }
+                    }
+                }
+            }
+            if trace {
+                tiles.push((pos, facing));
+            }
+        }
+        if !trace {
+            tiles.clear();
+        }
+        Ok(Walk {
+            pos,
+            facing,
+            trace: tiles,
+        })
+    }
+
+    /// Draws the map with the traced path as arrows, like the puzzle's diagrams.
+    fn render(&self, walk: &Walk) -> String {
+        let mut map = self.map.clone();
+        for (p, facing) in &walk.trace {
+            map[*p] = ARROWS[*facing];
+        }
+        let rows: Vec<String> = (0..map.height)
+            .map(|y| {
+                let row: String = (0..map.width).map(|x| map[(x, y)]).collect();
+                row.trim_end().to_string()
+            })
+            .collect();
+        rows.join("\n")
+    }
+}
+
+fn parse(input: &str) -> Result<(Board, Vec<Step>), MapError> {
+    let (map, path) = input.split_once("\n\n").ok_or(MapError::NoPath)?;
+    Ok((map.parse()?, parse_path(path)?))
+}
+
 #[aoc(day22, part1)]
-fn part1(input: &str) -> usize {
-    0
+fn part1(input: &str) -> Result<usize> {
+    let (board, path) = parse(input)?;
+    let walk = board.walk(&path, &Flat, VERBOSE)?;
+    vprint!("{}", board.render(&walk));
+    Ok(walk.password())
 }
 
-// #[aoc(day22, part2)]
-// fn part2(input: &str) -> usize { }
+#[aoc(day22, part2)]
+fn part2(input: &str) -> Result<usize> {
+    let (board, path) = parse(input)?;
+    let cube = Cube::new(&board)?;
+    let walk = board.walk(&path, &cube, VERBOSE)?;
+    vprint!("{}", board.render(&walk));
+    Ok(walk.password())
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const INPUT: &'static str = r#"SOMETHING
-"#;
+    const INPUT: &str = r#"        ...#
+        .#..
+        #...
+        ....
+...#.......#
+........#...
+..#....#....
+..........#.
+        ...#....
+        .....#..
+        .#......
+        ......#.
+
+10R5L5R10L4R5L5"#;
+
+    // Every cube net, '#' marks a face.
+    const NETS: [&str; 11] = [
+        "#...\n####\n#...",
+        "#...\n####\n.#..",
+        "#...\n####\n..#.",
+        "#...\n####\n...#",
+        ".#..\n####\n.#..",
+        ".#..\n####\n..#.",
+        "##..\n.###\n.#..",
+        "##..\n.###\n..#.",
+        "##..\n.###\n...#",
+        "##..\n.##.\n..##",
+        "###...\n..###.",
+    ];
+
+    // Blank board with each face of the net `size` tiles square.
+    fn board_for(net: &str, size: usize) -> Board {
+        let rows: Vec<String> = net
+            .lines()
+            .flat_map(|l| {
+                let row: String = l
+                    .chars()
+                    .map(|c| {
+                        if c == '#' {
+                            ".".repeat(size)
+                        } else {
+                            " ".repeat(size)
+                        }
+                    })
+                    .collect();
+                vec![row; size]
+            })
+            .collect();
+        rows.join("\n").parse().unwrap()
+    }
 
     #[test]
-    fn p1() {
-        assert_eq!(part1(INPUT), 42);
+    fn p1() -> Result<()> {
+        assert_eq!(part1(INPUT)?, 6032);
+        Ok(())
     }
 
-    //#[test]
-    //fn p2() {
-    //    assert_eq!(part2(INPUT), 42);
-    //}
+    #[test]
+    fn p2() -> Result<()> {
+        assert_eq!(part2(INPUT)?, 5031);
+        Ok(())
+    }
+
+    #[test]
+    fn path() {
+        assert_eq!(
+            parse_path("10R5L"),
+            Ok(vec![Step::Move(10), Step::Right, Step::Move(5), Step::Left])
+        );
+        assert_eq!(parse_path("3X"), Err(MapError::BadStep('X')));
+    }
+
+    #[test]
+    fn trace() -> Result<()> {
+        let (board, path) = parse(INPUT)?;
+        let walk = board.walk(&path, &Flat, true)?;
+        assert_eq!(
+            board.render(&walk),
+            r#"        >>v#
+        .#v.
+        #.v.
+        ..v.
+...#...v..v#
+>>>v...>#.>>
+..#v...#....
+...>>>>v..#.
+        ...#....
+        .....#..
+        .#......
+        ......#."#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn every_net() -> Result<()> {
+        for net in NETS {
+            for size in [1, 3] {
+                let board = board_for(net, size);
+                let cube = Cube::new(&board)?;
+                // With no walls, walking 4 faces in a straight line gets back to the start from
+                // anywhere, facing any way.
+                for y in 0..board.map.height {
+                    for x in 0..board.map.width {
+                        if !board.is_open((x, y)) {
+                            continue;
+                        }
+                        for facing in 0..4 {
+                            let (mut pos, mut f) = ((x, y), facing);
+                            for _ in 0..4 * size {
+                                (pos, f) = match board.step(pos, f) {
+                                    Some(p) => (p, f),
+                                    None => cube.wrap(&board, pos, f),
+                                };
+                            }
+                            assert_eq!((pos, f), ((x, y), facing), "net\n{net}\nsize {size}");
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse("..\n..").unwrap_err(), MapError::NoPath);
+        assert_eq!(
+            Cube::new(&board_for("####\n####", 1)).unwrap_err(),
+            MapError::NotACube("tile count isn't six squares")
+        );
+        assert_eq!(
+            Cube::new(&board_for("#####\n#....", 2)).unwrap_err(),
+            MapError::NotACube("faces overlap when folded")
+        );
+    }
 }
@@ -1,27 +1,259 @@
+use std::hash::{BuildHasherDefault, Hasher};
+
 use advent::prelude::*;
 use aoc_runner_derive::aoc;
 
+const VERBOSE: bool = false;
+
+const NEIGHBORS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+// Each direction an elf considers moving, with a mask of the NEIGHBORS that must be empty first.
+const PROPOSALS: [((i32, i32), u8); 4] = [
+    ((0, -1), 0b0000_0111),
+    ((0, 1), 0b1110_0000),
+    ((-1, 0), 0b0010_1001),
+    ((1, 0), 0b1001_0100),
+];
+
+// Multiplicative hash for positions, SipHash otherwise dominates the run time.
+#[derive(Default)]
+struct PosHasher(u64);
+
+impl Hasher for PosHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.write_u8(*b);
+        }
+    }
+
+    fn write_u8(&mut self, n: u8) {
+        self.write_i32(n as i32);
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.0 = (self.0.rotate_left(5) ^ n as u32 as u64).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+type PosHash = BuildHasherDefault<PosHasher>;
+
+#[derive(Debug, Error, PartialEq)]
+enum GroveError {
+    #[error("line {0}: unexpected {1:?}")]
+    BadTile(usize, char),
+}
+
+/// Positions of the elves, and which direction they'll consider first next round.
+#[derive(Debug)]
+struct Grove {
+    elves: HashSet<(i32, i32), PosHash>,
+    first: usize,
+}
+
+impl FromStr for Grove {
+    type Err = GroveError;
+    fn from_str(s: &str) -> Result<Self, GroveError> {
+        let mut elves = HashSet::default();
+        for (y, l) in s.lines().enumerate() {
+            for (x, c) in l.trim().chars().enumerate() {
+                match c {
+                    '#' => {
+                        elves.insert((x as i32, y as i32));
+                    }
+                    '.' => {}
+                    c => return Err(GroveError::BadTile(y + 1, c)),
+                }
+            }
+        }
+        Ok(Grove { elves, first: 0 })
+    }
+}
+
+impl Display for Grove {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ((x0, y0), (x1, y1)) = self.bounds();
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                write!(
+                    f,
+                    "{}",
+                    if self.elves.contains(&(x, y)) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                )?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Grove {
+    // Where the elf at `(x, y)` would like to move, None if it stays put.
+    fn propose(&self, (x, y): (i32, i32)) -> Option<(i32, i32)> {
+        let occupied = NEIGHBORS
+            .iter()
+            .enumerate()
+            .filter(|(_, (dx, dy))| self.elves.contains(&(x + dx, y + dy)))
+            .fold(0u8, |mask, (i, _)| mask | 1 << i);
+        if occupied == 0 {
+            return None;
+        }
+        (0..4)
+            .map(|i| PROPOSALS[(self.first + i) % 4])
+            .find(|(_, mask)| occupied & mask == 0)
+            .map(|((dx, dy), _)| (x + dx, y + dy))
+    }
+
+    /// Runs one round, returning how many elves moved.
+    fn round(&mut self) -> usize {
+        let proposals: Vec<((i32, i32), (i32, i32))> = self
+            .elves
+            .iter()
+            .filter_map(|e| self.propose(*e).map(|to| (*e, to)))
+            .collect();
+        let mut wanted: HashMap<(i32, i32), usize, PosHash> = HashMap::default();
+        for (_, to) in &proposals {
+            *wanted.entry(*to).or_default() += 1;
+        }
+        let mut moved = 0;
+        for (from, to) in proposals {
+            if wanted[&to] == 1 {
+                self.elves.remove(&from);
+                self.elves.insert(to);
+                moved += 1;
+            }
+        }
+        self.first = (self.first + 1) % 4;
+        moved
+    }
+
+    /// Smallest rectangle containing every elf, as its top left and bottom right corners.
+    fn bounds(&self) -> ((i32, i32), (i32, i32)) {
+        let xs = self.elves.iter().map(|e| e.0);
+        let ys = self.elves.iter().map(|e| e.1);
+        (
+            (xs.clone().min().unwrap_or(0), ys.clone().min().unwrap_or(0)),
+            (xs.max().unwrap_or(-1), ys.max().unwrap_or(-1)),
+        )
+    }
+
+    fn empty_tiles(&self) -> usize {
+        let ((x0, y0), (x1, y1)) = self.bounds();
+        ((x1 - x0 + 1) * (y1 - y0 + 1)) as usize - self.elves.len()
+    }
+
+    /// Runs rounds until nobody moves, returning the number of that round.
+    fn settle(&mut self) -> usize {
+        (1..).find(|_| self.round() == 0).expect("elves settle")
+    }
+}
+
 #[aoc(day23, part1)]
-fn part1(input: &str) -> usize {
-    0
+fn part1(input: &str) -> Result<usize> {
+    let mut grove: Grove = input.parse()?;
+    for _ in 0..10 {
+        grove.round();
+    }
+    vprint!("After 10 rounds:\n{grove}");
+    Ok(grove.empty_tiles())
 }
 
-// #[aoc(day23, part2)]
-// fn part2(input: &str) -> usize { }
+#[aoc(day23, part2)]
+fn part2(input: &str) -> Result<usize> {
+    let mut grove: Grove = input.parse()?;
+    let round = grove.settle();
+    vprint!("Settled in round {round}:\n{grove}");
+    Ok(round)
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const INPUT: &'static str = r#"SOMETHING
+    const INPUT: &str = r#"....#..
+..###.#
+#...#.#
+.#...##
+#.###..
+##.#.##
+.#..#..
 "#;
 
     #[test]
-    fn p1() {
-        assert_eq!(part1(INPUT), 42);
+    fn p1() -> Result<()> {
+        assert_eq!(part1(INPUT)?, 110);
+        Ok(())
+    }
+
+    #[test]
+    fn p2() -> Result<()> {
+        assert_eq!(part2(INPUT)?, 20);
+        Ok(())
+    }
+
+    #[test]
+    fn small() -> Result<()> {
+        let mut grove: Grove = ".....\n..##.\n..#..\n.....\n..##.\n.....".parse()?;
+        let rounds = [
+            "##\n..\n#.\n.#\n#.\n",
+            ".##.\n#...\n...#\n....\n.#..\n",
+            "..#..\n....#\n#....\n....#\n.....\n..#..\n",
+        ];
+        for (i, (expected, moved)) in rounds.iter().zip([3, 5, 3]).enumerate() {
+            assert_eq!(grove.round(), moved, "round {}", i + 1);
+            assert_eq!(grove.to_string(), *expected, "round {}", i + 1);
+        }
+        assert_eq!(grove.settle(), 1);
+        assert_eq!(grove.empty_tiles(), 25);
+        Ok(())
+    }
+
+    #[test]
+    fn random() -> Result<()> {
+        // Pseudo-random grove about half full, every elf is still there once it settles.
+        let mut rng = XorShift::new(0x9e37_79b9);
+        let input: String = (0..40)
+            .map(|_| {
+                let row: String = (0..40)
+                    .map(|_| {
+                        if rng.next_u64().is_multiple_of(2) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                row + "\n"
+            })
+            .collect();
+        let mut grove: Grove = input.parse()?;
+        let elves = grove.elves.len();
+        let round = grove.settle();
+        assert!(round > 10);
+        assert_eq!(grove.elves.len(), elves);
+        Ok(())
     }
 
-    //#[test]
-    //fn p2() {
-    //    assert_eq!(part2(INPUT), 42);
-    //}
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "..#\n.x.".parse::<Grove>().unwrap_err(),
+            GroveError::BadTile(2, 'x')
+        );
+    }
 }
@@ -12,15 +12,15 @@ pub mod day6;
 pub mod day7;
 pub mod day8;
 pub mod day9;
-//pub mod day15;
-//pub mod day16;
-//pub mod day17;
-//pub mod day18;
-// pub mod day19;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day19;
 pub mod day20;
-// pub mod day21;
-// pub mod day22;
-// pub mod day23;
+pub mod day21;
+pub mod day22;
+pub mod day23;
 // pub mod day24;
 
 use aoc_runner_derive::aoc_lib;
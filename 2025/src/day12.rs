@@ -1,6 +1,8 @@
-use advent::prelude::*;
+use advent::{
+    packing::{orientations, pack},
+    prelude::*,
+};
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::iproduct;
 
 #[derive(Debug)]
 struct Command {
@@ -10,7 +12,7 @@ struct Command {
 
 #[derive(Debug)]
 struct Present {
-    // Store image in all orientations
+    // Store image in all rotations and reflections
     images: Vec<Image<char>>,
 }
 
@@ -26,14 +28,7 @@ fn parse(input: &str) -> (Vec<Present>, Vec<Command>) {
                 .split_once(":\n")
                 .map(|(_, data)| data.parse().expect("failed to parse image"))
                 .expect("failed to split region header");
-            let p1 = im;
-            let p2 = p1.rot90();
-            let p3 = p2.rot90();
-            let p4 = p3.rot90();
-            let orients: HashSet<_> = [p1, p2, p3, p4].into_iter().collect();
-            let mut images: Vec<_> = orients.into_iter().collect();
-            // Sort so things are consistent run to run
-            images.sort();
+            let images = orientations(&im);
             presents.push(Present { images });
         } else {
             for l in chunk.lines() {
@@ -62,57 +57,9 @@ fn parse(input: &str) -> (Vec<Present>, Vec<Command>) {
 }
 
 fn will_it_fit(presents: &[Present], c: &Command) -> bool {
-    //println!("c.qtys {:?}", c.qtys);
-    let mut floor = c.region.clone();
-    for (i, q) in c.qtys.iter().enumerate() {
-        if *q == 0 {
-            continue;
-        }
-        let p = &presents[i];
-        /*
-        println!("Processing {}x{} i:{i}x{q}", floor.width, floor.height);
-        for o in &p.images {
-            println!("{o:#}");
-        }
-        */
-        for n in 0..*q {
-            let mut found_fit = false;
-            for (x, y) in iproduct!(0..floor.width - 2, 0..floor.height - 2) {
-                //println!( "checking [{},{}] p{i},{n}/{q} @ {x},{y}", floor.width, floor.height);
-
-                if p.images.iter().any(|p| {
-                    /*
-                    println!("Checking @ {x},{y}:{:#}", &p);
-                    println!("{floor:#}");
-                    */
-                    if floor.can_blit((x, y), &p, '#') {
-                        floor.blit((x, y), &p, '#');
-                        true
-                    } else {
-                        false
-                    }
-                }) {
-                    /*
-                    println!("found fit");
-                    println!("{floor:#}");
-                    */
-                    found_fit = true;
-                    break;
-                };
-            }
-            if !found_fit {
-                /*
-                println!("FAILED TO FIND FIT");
-                println!("[{},{}] p{i},{n}/{q}", floor.width, floor.height);
-                println!("Present\n{:#}", &p.images[0]);
-                println!("{floor:#}");
-                */
-                return false;
-            }
-        }
-    }
-    //println!("SUCCESS");
-    true
+    let shapes: Vec<_> = presents.iter().map(|p| p.images.clone()).collect();
+    let qtys: Vec<_> = c.qtys.iter().map(|q| *q as usize).collect();
+    pack(&shapes, &qtys, &c.region).is_some()
 }
 
 #[aoc(day12, part1)]
@@ -171,6 +118,7 @@ mod tests {
     #[test]
     fn part1_example() {
         assert_eq!(part1(&parse(INPUT)), "2");
+        assert_eq!(part1(&parse(&input_for(2025, 12))), "443");
     }
 
     /*
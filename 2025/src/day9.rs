@@ -1,4 +1,4 @@
-use advent::prelude::*;
+use advent::{geom::Polygon, prelude::*};
 use aoc_runner_derive::{aoc, aoc_generator};
 #[aoc_generator(day9)]
 fn parse(input: &str) -> Vec<(isize, isize)> {
@@ -54,43 +54,18 @@ fn part1_min_max(input: &[(isize, isize)]) -> String {
     .to_string()
 }
 
-fn draw_line(im: &mut Image<char>, p1: (isize, isize), p2: (isize, isize)) {
-    let x_min = p1.0.min(p2.0) as usize;
-    let x_max = 1 + p1.0.max(p2.0) as usize;
-    let y_min = p1.1.min(p2.1) as usize;
-    let y_max = 1 + p1.1.max(p2.1) as usize;
-    println!("draw_line {x_min}..{x_max} {y_min}..{y_max}");
-    for y in y_min..y_max {
-        for x in x_min..x_max {
-            if im[(x, y)] != '#' {
-                im[(x, y)] = 'X';
-            }
-        }
-    }
-}
-
-fn draw_points(pts: &[(isize, isize)]) {
-    let (max_x, max_y) = pts.iter().fold((0isize, 0isize), |acc, (x, y)| {
-        (acc.0.max(*x), acc.1.max(*y))
-    });
-    println!("image {max_x}x{max_y}");
-    let mut im = Image::new(2 + max_x as usize, 2 + max_y as usize, '.');
-    im[(pts[0].0 as usize, pts[0].1 as usize)] = '#';
-    println!("{im:#}");
-    for idx in 1..pts.len() + 1 {
-        draw_line(&mut im, pts[idx - 1], pts[idx % pts.len()]);
-        let pt = pts[idx % pts.len()];
-        im[(pt.0 as usize, pt.1 as usize)] = '#';
-        println!("{im:#}");
-    }
-}
-
 #[aoc(day9, part2)]
 fn part2(input: &[(isize, isize)]) -> String {
-    draw_points(input);
-    // Walk points finding the nearest X neighbor and completing the rectangle
-    //
-    todo!()
+    let red: Vec<_> = input.iter().map(|(x, y)| (*x as i64, *y as i64)).collect();
+    let floor = Polygon::new(red.clone()).compress();
+    red.iter()
+        .enumerate()
+        .flat_map(|(i, p1)| red[i + 1..].iter().map(move |p2| (p1, p2)))
+        .filter(|(p1, p2)| floor.contains_rect(**p1, **p2))
+        .map(|(p1, p2)| ((p2.0 - p1.0).abs() + 1) * ((p2.1 - p1.1).abs() + 1))
+        .max()
+        .expect("couldn't find max")
+        .to_string()
 }
 
 #[cfg(test)]
@@ -115,5 +90,6 @@ mod tests {
     #[test]
     fn part2_example() {
         assert_eq!(part2(&parse(INPUT)), "24");
+        assert_eq!(part2(&parse(&input_for(2025, 9))), "1568849600");
     }
 }
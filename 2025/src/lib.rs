@@ -1,8 +1,7 @@
-mod day12;
-mod day11;
-// Disabled until part 2 works and part 1 is fixed
-// mod day10;
 mod day1;
+mod day10;
+mod day11;
+mod day12;
 mod day2;
 mod day3;
 mod day4;
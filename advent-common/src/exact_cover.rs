@@ -0,0 +1,263 @@
+//! Knuth's Algorithm X using dancing links.
+
+/// Exact cover problem: choose a set of rows such that every primary column is covered exactly
+/// once and every secondary column at most once.
+///
+/// Example:
+///
+/// ```
+/// use advent::exact_cover::ExactCover;
+///
+/// // Knuth's example from the Dancing Links paper.
+/// let mut ec = ExactCover::new(7, 0);
+/// ec.add_row(&[2, 4, 5]);
+/// ec.add_row(&[0, 3, 6]);
+/// ec.add_row(&[1, 2, 5]);
+/// ec.add_row(&[0, 3]);
+/// ec.add_row(&[1, 6]);
+/// ec.add_row(&[3, 4, 6]);
+/// let mut rows = ec.solve().unwrap();
+/// rows.sort();
+/// assert_eq!(rows, vec![0, 3, 4]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ExactCover {
+    primary: usize,
+    secondary: usize,
+    nodes: Vec<Node>,
+    // Number of rows currently linked into each column, indexed by header node.
+    size: Vec<usize>,
+    // Whether a secondary column has been covered by the partial solution.
+    covered: Vec<bool>,
+    rows: usize,
+    slack: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    left: usize,
+    right: usize,
+    up: usize,
+    down: usize,
+    col: usize,
+    row: usize,
+}
+
+// Index of the root header, column `c` has its header at `c + 1`.
+const ROOT: usize = 0;
+
+impl ExactCover {
+    /// Columns `0..primary` must be covered, columns `primary..primary + secondary` are optional.
+    pub fn new(primary: usize, secondary: usize) -> Self {
+        let n = primary + secondary + 1;
+        let mut nodes: Vec<Node> = (0..n)
+            .map(|i| Node {
+                left: i,
+                right: i,
+                up: i,
+                down: i,
+                col: i,
+                row: usize::MAX,
+            })
+            .collect();
+        // Only primary columns are linked into the header list.
+        for (i, node) in nodes.iter_mut().enumerate().take(primary + 1) {
+            node.left = if i == 0 { primary } else { i - 1 };
+            node.right = if i == primary { 0 } else { i + 1 };
+        }
+        ExactCover {
+            primary,
+            secondary,
+            nodes,
+            size: vec![0; n],
+            covered: vec![false; n],
+            rows: 0,
+            slack: None,
+        }
+    }
+    /// Allows at most `slack` secondary columns to be left uncovered. Branches where more
+    /// secondary columns than that can no longer be covered by any remaining row are pruned.
+    pub fn with_slack(mut self, slack: usize) -> Self {
+        self.slack = Some(slack);
+        self
+    }
+    pub fn num_rows(&self) -> usize {
+        self.rows
+    }
+    /// Adds a row covering `cols` and returns its index.
+    pub fn add_row(&mut self, cols: &[usize]) -> usize {
+        let row = self.rows;
+        self.rows += 1;
+        let first = self.nodes.len();
+        for (i, &c) in cols.iter().enumerate() {
+            assert!(c < self.primary + self.secondary, "column {c} out of range");
+            let header = c + 1;
+            let idx = self.nodes.len();
+            let up = self.nodes[header].up;
+            self.nodes.push(Node {
+                left: if i == 0 { idx } else { idx - 1 },
+                right: first,
+                up,
+                down: header,
+                col: header,
+                row,
+            });
+            self.nodes[up].down = idx;
+            self.nodes[header].up = idx;
+            self.nodes[first].left = idx;
+            if i > 0 {
+                self.nodes[idx - 1].right = idx;
+            }
+            self.size[header] += 1;
+        }
+        row
+    }
+    /// Finds any exact cover, returning the chosen row indices.
+    pub fn solve(&mut self) -> Option<Vec<usize>> {
+        self.solve_with(|_, _| true)
+    }
+    /// Finds an exact cover only trying rows for which `accept(partial_solution, row)` is true.
+    /// This can be used to break symmetries between interchangeable rows.
+    pub fn solve_with<F>(&mut self, mut accept: F) -> Option<Vec<usize>>
+    where
+        F: FnMut(&[usize], usize) -> bool,
+    {
+        let mut solution = Vec::new();
+        self.search(&mut solution, &mut accept).then_some(solution)
+    }
+
+    fn search<F>(&mut self, solution: &mut Vec<usize>, accept: &mut F) -> bool
+    where
+        F: FnMut(&[usize], usize) -> bool,
+    {
+        if self.nodes[ROOT].right == ROOT {
+            return true;
+        }
+        if let Some(slack) = self.slack {
+            let dead = (self.primary + 1..=self.primary + self.secondary)
+                .filter(|&c| !self.covered[c] && self.size[c] == 0)
+                .count();
+            if dead > slack {
+                return false;
+            }
+        }
+        // Branch on the column with the fewest remaining rows.
+        let mut col = self.nodes[ROOT].right;
+        let mut c = self.nodes[col].right;
+        while c != ROOT {
+            if self.size[c] < self.size[col] {
+                col = c;
+            }
+            c = self.nodes[c].right;
+        }
+        if self.size[col] == 0 {
+            return false;
+        }
+
+        self.cover(col);
+        let mut r = self.nodes[col].down;
+        while r != col {
+            let row = self.nodes[r].row;
+            if accept(solution, row) {
+                solution.push(row);
+                let mut j = self.nodes[r].right;
+                while j != r {
+                    self.cover(self.nodes[j].col);
+                    j = self.nodes[j].right;
+                }
+                let found = self.search(solution, accept);
+                let mut j = self.nodes[r].left;
+                while j != r {
+                    self.uncover(self.nodes[j].col);
+                    j = self.nodes[j].left;
+                }
+                if found {
+                    // Links are restored on the way out so the problem can be solved again.
+                    self.uncover(col);
+                    return true;
+                }
+                solution.pop();
+            }
+            r = self.nodes[r].down;
+        }
+        self.uncover(col);
+        false
+    }
+
+    fn cover(&mut self, col: usize) {
+        let Node { left, right, .. } = self.nodes[col];
+        self.nodes[left].right = right;
+        self.nodes[right].left = left;
+        self.covered[col] = true;
+        let mut i = self.nodes[col].down;
+        while i != col {
+            let mut j = self.nodes[i].right;
+            while j != i {
+                let Node { up, down, col, .. } = self.nodes[j];
+                self.nodes[up].down = down;
+                self.nodes[down].up = up;
+                self.size[col] -= 1;
+                j = self.nodes[j].right;
+            }
+            i = self.nodes[i].down;
+        }
+    }
+
+    fn uncover(&mut self, col: usize) {
+        let mut i = self.nodes[col].up;
+        while i != col {
+            let mut j = self.nodes[i].left;
+            while j != i {
+                let Node { up, down, col, .. } = self.nodes[j];
+                self.nodes[up].down = j;
+                self.nodes[down].up = j;
+                self.size[col] += 1;
+                j = self.nodes[j].left;
+            }
+            i = self.nodes[i].up;
+        }
+        self.covered[col] = false;
+        let Node { left, right, .. } = self.nodes[col];
+        self.nodes[left].right = col;
+        self.nodes[right].left = col;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_solution() {
+        let mut ec = ExactCover::new(3, 0);
+        ec.add_row(&[0, 1]);
+        ec.add_row(&[1, 2]);
+        assert_eq!(ec.solve(), None);
+    }
+
+    #[test]
+    fn test_secondary() {
+        // Column 2 is optional, so rows 0 and 1 can't both be used.
+        let mut ec = ExactCover::new(2, 1);
+        ec.add_row(&[0, 2]);
+        ec.add_row(&[1, 2]);
+        ec.add_row(&[1]);
+        let mut rows = ec.solve().unwrap();
+        rows.sort();
+        assert_eq!(rows, vec![0, 2]);
+
+        let mut ec = ExactCover::new(2, 1).with_slack(0);
+        ec.add_row(&[0]);
+        ec.add_row(&[1]);
+        assert_eq!(ec.solve(), None);
+    }
+
+    #[test]
+    fn test_solve_with() {
+        let mut ec = ExactCover::new(1, 0);
+        ec.add_row(&[0]);
+        ec.add_row(&[0]);
+        assert_eq!(ec.solve_with(|_, row| row != 0), Some(vec![1]));
+        assert_eq!(ec.solve_with(|_, _| false), None);
+    }
+}
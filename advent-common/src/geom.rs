@@ -0,0 +1,328 @@
+//! Integer polygon geometry.
+//!
+//! Points are `(x, y)` pairs. Where it matters, a point is treated as a unit tile, so a polygon
+//! traced through tiles includes the tiles its edges pass through.
+
+use crate::gcd;
+
+pub type Point = (i64, i64);
+
+/// Sign of the cross product of `b - a` and `c - a`: positive when `a`, `b`, `c` turn counter
+/// clockwise (with y up), negative when clockwise, zero when collinear.
+pub fn orientation(a: Point, b: Point, c: Point) -> i64 {
+    ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)).signum()
+}
+
+/// Returns true if `p` lies on the closed segment `a`-`b`.
+pub fn on_segment(p: Point, (a, b): (Point, Point)) -> bool {
+    orientation(a, b, p) == 0
+        && a.0.min(b.0) <= p.0
+        && p.0 <= a.0.max(b.0)
+        && a.1.min(b.1) <= p.1
+        && p.1 <= a.1.max(b.1)
+}
+
+/// Returns true if the closed segments share at least one point.
+///
+/// ```
+/// use advent::geom::segments_intersect;
+///
+/// assert!(segments_intersect(((0, 0), (4, 4)), ((0, 4), (4, 0))));
+/// assert!(segments_intersect(((0, 0), (4, 0)), ((4, 0), (4, 3))));
+/// assert!(segments_intersect(((0, 0), (4, 0)), ((2, 0), (6, 0))));
+/// assert!(!segments_intersect(((0, 0), (4, 0)), ((5, 0), (6, 0))));
+/// assert!(!segments_intersect(((0, 0), (4, 4)), ((1, 0), (5, 4))));
+/// ```
+pub fn segments_intersect(s1: (Point, Point), s2: (Point, Point)) -> bool {
+    let (a, b) = s1;
+    let (c, d) = s2;
+    let (o1, o2) = (orientation(a, b, c), orientation(a, b, d));
+    let (o3, o4) = (orientation(c, d, a), orientation(c, d, b));
+    if o1 != o2 && o3 != o4 && o1 * o2 <= 0 && o3 * o4 <= 0 {
+        return true;
+    }
+    on_segment(c, s1) || on_segment(d, s1) || on_segment(a, s2) || on_segment(b, s2)
+}
+
+/// Simple polygon given by its vertices in order, either direction. The last vertex connects
+/// back to the first.
+///
+/// Example:
+///
+/// ```
+/// use advent::geom::Polygon;
+///
+/// let p = Polygon::new(vec![(0, 0), (4, 0), (4, 3), (0, 3)]);
+/// assert_eq!(p.area2(), 24);
+/// assert_eq!(p.boundary_points(), 14);
+/// assert_eq!(p.interior_points(), 6);
+/// assert!(p.contains((4, 1)));
+/// assert!(!p.contains((5, 1)));
+/// assert!(p.contains_rect((1, 1), (4, 3)));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<Point>) -> Self {
+        assert!(vertices.len() >= 3, "a polygon needs at least 3 vertices");
+        Polygon { vertices }
+    }
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+    /// Iterates over the edges, including the closing one from the last vertex to the first.
+    pub fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        self.vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+            .map(|(a, b)| (*a, *b))
+    }
+    /// True if every edge is horizontal or vertical.
+    pub fn is_rectilinear(&self) -> bool {
+        self.edges().all(|(a, b)| a.0 == b.0 || a.1 == b.1)
+    }
+    /// Twice the enclosed area, computed with the shoelace formula. Kept doubled so it stays
+    /// an integer.
+    pub fn area2(&self) -> i64 {
+        self.edges()
+            .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+            .sum::<i64>()
+            .abs()
+    }
+    /// Number of lattice points on the edges.
+    pub fn boundary_points(&self) -> i64 {
+        self.edges()
+            .map(|(a, b)| gcd((b.0 - a.0).into(), (b.1 - a.1).into()) as i64)
+            .sum()
+    }
+    /// Number of lattice points strictly inside, from Pick's theorem: A = I + B/2 - 1.
+    pub fn interior_points(&self) -> i64 {
+        (self.area2() - self.boundary_points()) / 2 + 1
+    }
+    /// Returns true if `p` is inside the polygon or on its boundary.
+    pub fn contains(&self, p: Point) -> bool {
+        if self.edges().any(|e| on_segment(p, e)) {
+            return true;
+        }
+        // Count edges crossing a ray from p towards +x.
+        self.edges()
+            .filter(|&(a, b)| {
+                (a.1 > p.1) != (b.1 > p.1) && {
+                    // x coordinate where the edge crosses y = p.1, compared without division.
+                    let lhs = (p.0 - a.0) * (b.1 - a.1);
+                    let rhs = (b.0 - a.0) * (p.1 - a.1);
+                    if b.1 > a.1 {
+                        lhs < rhs
+                    } else {
+                        lhs > rhs
+                    }
+                }
+            })
+            .count()
+            % 2
+            == 1
+    }
+    /// Returns true if every tile of the rectangle with opposite corners `c1` and `c2` is inside
+    /// the polygon or on its boundary. Builds a [`CompressedGrid`] each call, use
+    /// [`Polygon::compress`] when checking many rectangles.
+    pub fn contains_rect(&self, c1: Point, c2: Point) -> bool {
+        self.compress().contains_rect(c1, c2)
+    }
+    /// Builds a coordinate compressed grid for fast rectangle queries. Only supported for
+    /// rectilinear polygons.
+    pub fn compress(&self) -> CompressedGrid {
+        CompressedGrid::new(self)
+    }
+}
+
+/// Inside/outside map of a rectilinear polygon over compressed coordinates, independent of how
+/// large the coordinates are.
+///
+/// Each axis keeps the distinct vertex coordinates, and cells alternate between a single vertex
+/// coordinate and the open gap up to the next one. Every tile within a cell shares the same
+/// status, so the grid is only `(2n - 1)²` for `n` distinct coordinates.
+///
+/// Example:
+///
+/// ```
+/// use advent::geom::Polygon;
+///
+/// // An L shape with huge coordinates.
+/// let p = Polygon::new(vec![
+///     (0, 0),
+///     (1_000_000, 0),
+///     (1_000_000, 10),
+///     (10, 10),
+///     (10, 1_000_000),
+///     (0, 1_000_000),
+/// ]);
+/// let grid = p.compress();
+/// assert!(grid.contains_rect((0, 0), (1_000_000, 10)));
+/// assert!(grid.contains_rect((0, 1_000_000), (10, 0)));
+/// assert!(!grid.contains_rect((0, 0), (11, 11)));
+/// assert!(!grid.contains_rect((0, 0), (1_000_001, 1)));
+/// ```
+#[derive(Clone, Debug)]
+pub struct CompressedGrid {
+    xs: Vec<i64>,
+    ys: Vec<i64>,
+    width: usize,
+    // Prefix sums of outside cells, (width + 1) x (height + 1).
+    outside: Vec<usize>,
+}
+
+impl CompressedGrid {
+    pub fn new(polygon: &Polygon) -> Self {
+        assert!(
+            polygon.is_rectilinear(),
+            "coordinate compression requires a rectilinear polygon"
+        );
+        let axis = |f: fn(&Point) -> i64| {
+            let mut v: Vec<i64> = polygon.vertices.iter().map(f).collect();
+            v.sort_unstable();
+            v.dedup();
+            v
+        };
+        let xs = axis(|p| p.0);
+        let ys = axis(|p| p.1);
+        // Probe each cell at its center, in doubled coordinates so gap centers stay integral.
+        let doubled = Polygon::new(
+            polygon
+                .vertices
+                .iter()
+                .map(|(x, y)| (2 * x, 2 * y))
+                .collect(),
+        );
+        let centers = |v: &[i64]| -> Vec<i64> {
+            (0..2 * v.len() - 1)
+                .map(|i| {
+                    if i % 2 == 0 {
+                        2 * v[i / 2]
+                    } else {
+                        v[i / 2] + v[i / 2 + 1]
+                    }
+                })
+                .collect()
+        };
+        let (cx, cy) = (centers(&xs), centers(&ys));
+        let (w, h) = (cx.len(), cy.len());
+        let mut outside = vec![0; (w + 1) * (h + 1)];
+        for j in 0..h {
+            for i in 0..w {
+                // Gaps between adjacent coordinates hold no tiles, so they never block.
+                let empty = (i % 2 == 1 && xs[i / 2] + 1 == xs[i / 2 + 1])
+                    || (j % 2 == 1 && ys[j / 2] + 1 == ys[j / 2 + 1]);
+                let out = !empty && !doubled.contains((cx[i], cy[j]));
+                outside[(i + 1) + (j + 1) * (w + 1)] =
+                    out as usize + outside[i + (j + 1) * (w + 1)] + outside[(i + 1) + j * (w + 1)]
+                        - outside[i + j * (w + 1)];
+            }
+        }
+        CompressedGrid {
+            xs,
+            ys,
+            width: w,
+            outside,
+        }
+    }
+    /// Returns true if every tile of the rectangle with opposite corners `c1` and `c2` is inside
+    /// the polygon or on its boundary.
+    pub fn contains_rect(&self, c1: Point, c2: Point) -> bool {
+        let (x1, x2) = (c1.0.min(c2.0), c1.0.max(c2.0));
+        let (y1, y2) = (c1.1.min(c2.1), c1.1.max(c2.1));
+        let (Some((i1, i2)), Some((j1, j2))) =
+            (Self::span(&self.xs, x1, x2), Self::span(&self.ys, y1, y2))
+        else {
+            return false;
+        };
+        let w = self.width + 1;
+        let s = &self.outside;
+        s[(i2 + 1) + (j2 + 1) * w] + s[i1 + j1 * w] - s[i1 + (j2 + 1) * w] - s[(i2 + 1) + j1 * w]
+            == 0
+    }
+    /// Range of compressed cells covering `lo..=hi`, or None if it extends past the polygon.
+    fn span(v: &[i64], lo: i64, hi: i64) -> Option<(usize, usize)> {
+        let cell = |c: i64| match v.binary_search(&c) {
+            Ok(i) => Some(2 * i),
+            Err(0) => None,
+            Err(i) if i == v.len() => None,
+            Err(i) => Some(2 * i - 1),
+        };
+        Some((cell(lo)?, cell(hi)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2025 day9 example
+    const RED_TILES: [Point; 8] = [
+        (7, 1),
+        (11, 1),
+        (11, 7),
+        (9, 7),
+        (9, 5),
+        (2, 5),
+        (2, 3),
+        (7, 3),
+    ];
+
+    #[test]
+    fn test_picks_theorem() {
+        let p = Polygon::new(RED_TILES.to_vec());
+        assert!(p.is_rectilinear());
+        assert_eq!(p.area2(), 2 * 30);
+        assert_eq!(p.boundary_points(), 30);
+        // Every tile inside or on the boundary.
+        assert_eq!(p.interior_points() + p.boundary_points(), 46);
+    }
+
+    #[test]
+    fn test_contains_rect() {
+        let p = Polygon::new(RED_TILES.to_vec());
+        let grid = p.compress();
+        // Brute force every rectangle against per tile containment.
+        for (a, b) in RED_TILES
+            .iter()
+            .flat_map(|a| RED_TILES.iter().map(move |b| (a, b)))
+        {
+            let want = (a.0.min(b.0)..=a.0.max(b.0))
+                .all(|x| (a.1.min(b.1)..=a.1.max(b.1)).all(|y| p.contains((x, y))));
+            assert_eq!(grid.contains_rect(*a, *b), want, "{a:?} {b:?}");
+        }
+        assert!(grid.contains_rect((9, 5), (2, 3)));
+        assert!(!grid.contains_rect((7, 1), (2, 5)));
+    }
+
+    #[test]
+    fn test_notch() {
+        // A U shape whose arms are adjacent columns of tiles, no tile lies between them.
+        let p = Polygon::new(vec![
+            (0, 0),
+            (3, 0),
+            (3, 5),
+            (2, 5),
+            (2, 1),
+            (1, 1),
+            (1, 5),
+            (0, 5),
+        ]);
+        assert!(p.contains_rect((0, 0), (3, 5)));
+        let p = Polygon::new(vec![
+            (0, 0),
+            (4, 0),
+            (4, 5),
+            (3, 5),
+            (3, 1),
+            (1, 1),
+            (1, 5),
+            (0, 5),
+        ]);
+        assert!(!p.contains_rect((0, 0), (4, 5)));
+        assert!(p.contains_rect((0, 0), (4, 1)));
+    }
+}
@@ -69,6 +69,16 @@ where
         }
         im
     }
+    /// Mirrors left to right and returns new Image
+    pub fn flip_x(&self) -> Self {
+        let mut im = Image::new(self.width, self.height, T::default());
+        for x in 0..self.width {
+            for y in 0..self.height {
+                im[(x, y)] = self[(self.width - x - 1, y)];
+            }
+        }
+        im
+    }
     /// Visits up to 8 neighbors, ignoring cells out of bounds
     pub fn visit_neighbors<MAP, REDUCE, U, V>(
         &self,
@@ -0,0 +1,320 @@
+use std::collections::BinaryHeap;
+
+use crate::Vec3;
+
+/// A point that can be stored in a [`KdTree`].
+pub trait KdPoint: Copy {
+    /// Number of axes the tree splits on.
+    const DIMS: usize;
+    fn coord(&self, axis: usize) -> i64;
+    fn distance_squared(&self, rhs: &Self) -> i64 {
+        (0..Self::DIMS)
+            .map(|axis| {
+                let d = self.coord(axis) - rhs.coord(axis);
+                d * d
+            })
+            .sum()
+    }
+}
+
+impl KdPoint for Vec3 {
+    const DIMS: usize = 3;
+    fn coord(&self, axis: usize) -> i64 {
+        self.0[axis]
+    }
+}
+
+impl KdPoint for (i64, i64) {
+    const DIMS: usize = 2;
+    fn coord(&self, axis: usize) -> i64 {
+        match axis {
+            0 => self.0,
+            _ => self.1,
+        }
+    }
+}
+
+/// Static k-d tree over a set of points. Queries return the index of the point in the slice the
+/// tree was built from, along with its squared distance to the query. Results are ordered by
+/// distance, ties broken by index.
+///
+/// Example:
+///
+/// ```
+/// use advent::KdTree;
+///
+/// let points = [(0, 0), (10, 10), (3, 4), (-2, 1)];
+/// let tree = KdTree::new(&points);
+///
+/// assert_eq!(tree.nearest(&(9, 9)), Some((1, 2)));
+/// assert_eq!(tree.k_nearest(&(0, 0), 3), vec![(0, 0), (3, 5), (2, 25)]);
+/// assert_eq!(tree.within(&(0, 0), 25), vec![(0, 0), (3, 5), (2, 25)]);
+///
+/// let pairs: Vec<_> = tree.closest_pairs().take(2).collect();
+/// assert_eq!(pairs, vec![(0, 3, 5), (0, 2, 25)]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct KdTree<P> {
+    points: Vec<P>,
+    // Points laid out as an implicit tree, the root of each subslice is at its midpoint.
+    nodes: Vec<(P, usize)>,
+}
+
+impl<P: KdPoint> KdTree<P> {
+    pub fn new(points: &[P]) -> Self {
+        let mut nodes: Vec<_> = points.iter().copied().zip(0..).collect();
+        build(&mut nodes, 0);
+        KdTree {
+            points: points.to_vec(),
+            nodes,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+    /// Returns the point at `idx` in the slice the tree was built from.
+    pub fn point(&self, idx: usize) -> P {
+        self.points[idx]
+    }
+    /// Closest point to `q`, which may be `q` itself if it is in the tree.
+    pub fn nearest(&self, q: &P) -> Option<(usize, i64)> {
+        self.k_nearest(q, 1).pop()
+    }
+    /// Up to `k` closest points to `q`.
+    pub fn k_nearest(&self, q: &P, k: usize) -> Vec<(usize, i64)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut v = KNearest {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        };
+        search(&self.nodes, 0, q, &mut v);
+        v.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(d, i)| (i, d))
+            .collect()
+    }
+    /// All points whose squared distance to `q` is at most `radius_squared`.
+    pub fn within(&self, q: &P, radius_squared: i64) -> Vec<(usize, i64)> {
+        let mut v = Within {
+            radius_squared,
+            found: Vec::new(),
+        };
+        search(&self.nodes, 0, q, &mut v);
+        v.found.sort_unstable();
+        v.found.into_iter().map(|(d, i)| (i, d)).collect()
+    }
+    /// Lazily yields every unordered pair of points as `(i, j, distance_squared)` with `i < j`,
+    /// ordered by distance then by `(i, j)`. Only as many neighbors per point as needed are
+    /// computed, so taking a prefix of the stream avoids materializing all n² pairs.
+    pub fn closest_pairs(&self) -> ClosestPairs<'_, P> {
+        let mut pairs = ClosestPairs {
+            tree: self,
+            neighbors: vec![Neighbors::default(); self.len()],
+            heap: BinaryHeap::with_capacity(self.len()),
+        };
+        for i in 0..self.len() {
+            pairs.advance(i);
+        }
+        pairs
+    }
+}
+
+fn build<P: KdPoint>(nodes: &mut [(P, usize)], depth: usize) {
+    if nodes.len() <= 1 {
+        return;
+    }
+    let axis = depth % P::DIMS;
+    let mid = nodes.len() / 2;
+    nodes.select_nth_unstable_by_key(mid, |(p, _)| p.coord(axis));
+    let (left, right) = nodes.split_at_mut(mid);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+
+trait Visitor {
+    /// Subtrees further away than this can be skipped.
+    fn bound(&self) -> Option<i64>;
+    fn visit(&mut self, distance_squared: i64, idx: usize);
+}
+
+struct KNearest {
+    k: usize,
+    // Max-heap, the worst of the current best k is on top.
+    heap: BinaryHeap<(i64, usize)>,
+}
+
+impl Visitor for KNearest {
+    fn bound(&self) -> Option<i64> {
+        if self.heap.len() < self.k {
+            None
+        } else {
+            self.heap.peek().map(|(d, _)| *d)
+        }
+    }
+    fn visit(&mut self, distance_squared: i64, idx: usize) {
+        self.heap.push((distance_squared, idx));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+}
+
+struct Within {
+    radius_squared: i64,
+    found: Vec<(i64, usize)>,
+}
+
+impl Visitor for Within {
+    fn bound(&self) -> Option<i64> {
+        Some(self.radius_squared)
+    }
+    fn visit(&mut self, distance_squared: i64, idx: usize) {
+        if distance_squared <= self.radius_squared {
+            self.found.push((distance_squared, idx));
+        }
+    }
+}
+
+fn search<P: KdPoint, V: Visitor>(nodes: &[(P, usize)], depth: usize, q: &P, v: &mut V) {
+    if nodes.is_empty() {
+        return;
+    }
+    let axis = depth % P::DIMS;
+    let mid = nodes.len() / 2;
+    let (p, idx) = &nodes[mid];
+    v.visit(p.distance_squared(q), *idx);
+
+    let diff = q.coord(axis) - p.coord(axis);
+    let (near, far) = if diff < 0 {
+        (&nodes[..mid], &nodes[mid + 1..])
+    } else {
+        (&nodes[mid + 1..], &nodes[..mid])
+    };
+    search(near, depth + 1, q, v);
+    // Ties are broken by index, so subtrees exactly on the bound must still be visited.
+    if v.bound().is_none_or(|b| diff * diff <= b) {
+        search(far, depth + 1, q, v);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Neighbors {
+    // Neighbors already fetched for this point, consumed from `next`.
+    found: Vec<(usize, i64)>,
+    next: usize,
+}
+
+/// Iterator returned by [`KdTree::closest_pairs`].
+pub struct ClosestPairs<'a, P> {
+    tree: &'a KdTree<P>,
+    neighbors: Vec<Neighbors>,
+    // Min-heap of each point's next unvisited neighbor, as (distance, point, neighbor).
+    heap: BinaryHeap<std::cmp::Reverse<(i64, usize, usize)>>,
+}
+
+impl<P: KdPoint> ClosestPairs<'_, P> {
+    /// Pushes the next neighbor of point `i` onto the heap, fetching more neighbors (doubling
+    /// each time) when the current batch is exhausted.
+    fn advance(&mut self, i: usize) {
+        let p = self.tree.point(i);
+        loop {
+            let n = &mut self.neighbors[i];
+            if n.next == n.found.len() {
+                if n.found.len() == self.tree.len() {
+                    return;
+                }
+                let k = (2 * n.found.len()).max(8).min(self.tree.len());
+                n.found = self.tree.k_nearest(&p, k);
+            }
+            let (j, d) = n.found[n.next];
+            n.next += 1;
+            if j != i {
+                self.heap.push(std::cmp::Reverse((d, i, j)));
+                return;
+            }
+        }
+    }
+}
+
+impl<P: KdPoint> Iterator for ClosestPairs<'_, P> {
+    type Item = (usize, usize, i64);
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(std::cmp::Reverse((d, i, j))) = self.heap.pop() {
+            self.advance(i);
+            // Each pair shows up in both points' streams, only report it from the lower index.
+            if i < j {
+                return Some((i, j, d));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::XorShift;
+
+    fn random_points(n: usize) -> Vec<Vec3> {
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+        let mut rand = || rng.below(200) as i64 - 100;
+        (0..n).map(|_| Vec3([rand(), rand(), rand()])).collect()
+    }
+
+    fn brute_force(points: &[Vec3], q: &Vec3) -> Vec<(usize, i64)> {
+        let mut all: Vec<_> = points
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.distance_squared(q), i))
+            .collect();
+        all.sort();
+        all.into_iter().map(|(d, i)| (i, d)).collect()
+    }
+
+    #[test]
+    fn test_queries() {
+        let points = random_points(500);
+        let tree = KdTree::new(&points);
+        for q in random_points(50) {
+            let want = brute_force(&points, &q);
+            assert_eq!(tree.nearest(&q), Some(want[0]));
+            assert_eq!(tree.k_nearest(&q, 17), want[..17]);
+            let within: Vec<_> = want.iter().copied().filter(|(_, d)| *d <= 900).collect();
+            assert_eq!(tree.within(&q, 900), within);
+        }
+        assert_eq!(tree.k_nearest(&points[0], 1000).len(), 500);
+    }
+
+    #[test]
+    fn test_closest_pairs() {
+        let points = random_points(300);
+        let mut want = Vec::new();
+        for i in 0..points.len() {
+            for j in i + 1..points.len() {
+                want.push((points[i].distance_squared(&points[j]), i, j));
+            }
+        }
+        want.sort();
+        let want: Vec<_> = want.into_iter().map(|(d, i, j)| (i, j, d)).collect();
+        let tree = KdTree::new(&points);
+        assert_eq!(
+            tree.closest_pairs().take(1000).collect::<Vec<_>>(),
+            want[..1000]
+        );
+        assert_eq!(tree.closest_pairs().collect::<Vec<_>>(), want);
+    }
+
+    #[test]
+    fn test_empty() {
+        let tree: KdTree<(i64, i64)> = KdTree::new(&[]);
+        assert!(tree.is_empty());
+        assert_eq!(tree.nearest(&(0, 0)), None);
+        assert_eq!(tree.closest_pairs().next(), None);
+    }
+}
@@ -14,7 +14,10 @@ pub mod prelude {
     pub use anyhow::Result;
     pub use thiserror::Error;
 
-    pub use crate::{image::Image, input_for, parsers::range_inclusive, vprint, BitSet, Vec3};
+    pub use crate::{
+        image::Image, input_for, parsers::range_inclusive, vprint, BitSet, KdTree, Ratio,
+        UnionFind, Vec3, XorShift,
+    };
 }
 
 use std::{
@@ -23,14 +26,36 @@ use std::{
     str::FromStr,
 };
 
+pub mod exact_cover;
+pub mod geom;
 mod image;
+mod kdtree;
+pub mod linear;
+pub mod ocr;
+pub mod packing;
 pub mod parsers;
+mod ratio;
+mod rng;
+mod union_find;
+
+pub use kdtree::{ClosestPairs, KdPoint, KdTree};
+pub use ratio::Ratio;
+pub use rng::XorShift;
+pub use union_find::UnionFind;
 
 #[macro_export]
 macro_rules! vprint {
     ($($x:tt)*) => { if VERBOSE { println!($($x)*); } }
 }
 
+/// Greatest common divisor, always non-negative.
+pub(crate) fn gcd(mut a: i128, mut b: i128) -> i128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.abs()
+}
+
 pub fn input_for(year: u16, day: u16) -> String {
     let path = format!("input/{year}/day{day}.txt");
     let mut s =
@@ -127,6 +152,9 @@ impl fmt::Display for BitSet {
 pub struct Vec3([i64; 3]);
 
 impl Vec3 {
+    pub fn new(x: i64, y: i64, z: i64) -> Vec3 {
+        Vec3([x, y, z])
+    }
     pub fn distance_squared(&self, rhs: &Vec3) -> i64 {
         ((rhs.0[0] - self.0[0]) * (rhs.0[0] - self.0[0])
             + (rhs.0[1] - self.0[1]) * (rhs.0[1] - self.0[1])
@@ -0,0 +1,327 @@
+//! Exact solvers for "which combination of these vectors adds up to the target" puzzles.
+
+use crate::{gcd, Ratio};
+
+/// Finds non-negative integer coefficients `c` minimizing `c.sum()` such that
+/// `sum(c[j] * vectors[j]) == target`, or `None` if the target can't be reached.
+///
+/// The system is put in reduced row echelon form over the rationals, then the free variables are
+/// searched exhaustively. Because every entry is non-negative, no coefficient can exceed the
+/// smallest target component its vector contributes to, which bounds that search.
+///
+/// Like [`Ratio`]'s operators, panics if the reduced system's numbers overflow `i64`.
+///
+/// Example:
+///
+/// ```
+/// use advent::linear::min_combination;
+///
+/// let vectors = [vec![1, 0], vec![0, 1], vec![1, 1]];
+/// assert_eq!(min_combination(&vectors, &[3, 5]), Some(vec![0, 2, 3]));
+/// assert_eq!(min_combination(&[vec![2]], &[3]), None);
+/// ```
+pub fn min_combination(vectors: &[Vec<u64>], target: &[u64]) -> Option<Vec<u64>> {
+    let n = vectors.len();
+    let m = target.len();
+    assert!(
+        vectors.iter().all(|v| v.len() == m),
+        "vectors must have the same length as target"
+    );
+
+    // Augmented matrix, one row per component, one column per vector then the target.
+    let mut rows: Vec<Vec<Ratio>> = (0..m)
+        .map(|i| {
+            vectors
+                .iter()
+                .map(|v| v[i])
+                .chain([target[i]])
+                .map(|x| Ratio::from(x as i64))
+                .collect()
+        })
+        .collect();
+    let pivots = reduce(&mut rows, n);
+    // Any remaining row is all zero coefficients, it must have a zero target too.
+    if rows[pivots.len()..].iter().any(|r| !r[n].is_zero()) {
+        return None;
+    }
+
+    let free: Vec<usize> = (0..n).filter(|j| !pivots.contains(j)).collect();
+    let bounds: Vec<u64> = free
+        .iter()
+        .map(|&j| {
+            (0..m)
+                .filter(|&i| vectors[j][i] > 0)
+                .map(|i| target[i] / vectors[j][i])
+                .min()
+                .unwrap_or(0)
+        })
+        .collect();
+    // Scale each pivot row to integers: den * x_pivot = rhs - sum(coefs[k] * x_free[k]).
+    let equations: Vec<Equation> = pivots
+        .iter()
+        .enumerate()
+        .map(|(r, &col)| {
+            let row = &rows[r];
+            let lcm = free
+                .iter()
+                .map(|&j| row[j].denom())
+                .chain([row[col].denom(), row[n].denom()])
+                .try_fold(1i64, |l, d| {
+                    (l / gcd(l.into(), d.into()) as i64).checked_mul(d)
+                })
+                .expect("denominator LCM overflows i64");
+            let scale = |x: Ratio| (x * Ratio::from(lcm)).numer();
+            Equation {
+                col,
+                den: scale(row[col]),
+                rhs: scale(row[n]),
+                coefs: free.iter().map(|&j| scale(row[j])).collect(),
+            }
+        })
+        .collect();
+
+    let mut search = Search {
+        bounds,
+        equations,
+        free_values: vec![0; free.len()],
+        best: None,
+    };
+    search.run(0, 0);
+    let (_, free_values, pivot_values) = search.best?;
+    let mut res = vec![0; n];
+    for (j, v) in free.iter().zip(free_values) {
+        res[*j] = v;
+    }
+    for (e, v) in search.equations.iter().zip(pivot_values) {
+        res[e.col] = v;
+    }
+    Some(res)
+}
+
+/// Like [`min_combination`] but over GF(2): finds the fewest `vectors` whose XOR is `target`.
+/// Vectors are bitmasks with one bit per component. Returns which vectors were chosen.
+///
+/// Example:
+///
+/// ```
+/// use advent::linear::min_combination_gf2;
+///
+/// let vectors = [0b011, 0b110, 0b100];
+/// assert_eq!(
+///     min_combination_gf2(&vectors, 0b101),
+///     Some(vec![true, true, false])
+/// );
+/// assert_eq!(min_combination_gf2(&[0b11], 0b01), None);
+/// ```
+pub fn min_combination_gf2(vectors: &[u64], target: u64) -> Option<Vec<bool>> {
+    let n = vectors.len();
+    assert!(n <= 64, "at most 64 vectors supported");
+    // One row per bit of the target: a mask of which vectors touch that bit, and the target bit.
+    let mut rows: Vec<(u64, bool)> = (0..64)
+        .map(|bit| {
+            let mask = vectors
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| *v & 1 << bit != 0)
+                .fold(0u64, |m, (j, _)| m | 1 << j);
+            (mask, target & 1 << bit != 0)
+        })
+        .filter(|(mask, rhs)| *mask != 0 || *rhs)
+        .collect();
+
+    let mut pivots = Vec::new();
+    for col in 0..n {
+        let r = pivots.len();
+        let Some(p) = (r..rows.len()).find(|&i| rows[i].0 & 1 << col != 0) else {
+            continue;
+        };
+        rows.swap(r, p);
+        let pivot = rows[r];
+        for (i, row) in rows.iter_mut().enumerate() {
+            if i != r && row.0 & 1 << col != 0 {
+                row.0 ^= pivot.0;
+                row.1 ^= pivot.1;
+            }
+        }
+        pivots.push(col);
+    }
+    if rows[pivots.len()..].iter().any(|(_, rhs)| *rhs) {
+        return None;
+    }
+
+    let free: Vec<usize> = (0..n).filter(|j| !pivots.contains(j)).collect();
+    let free_mask = free.iter().fold(0u64, |m, j| m | 1 << j);
+    (0..1u64 << free.len())
+        .map(|assignment| {
+            // Scatter the assignment bits onto the free columns.
+            let mut chosen = free
+                .iter()
+                .enumerate()
+                .filter(|(k, _)| assignment & 1 << k != 0)
+                .fold(0u64, |m, (_, j)| m | 1 << j);
+            for (r, col) in pivots.iter().enumerate() {
+                let (mask, rhs) = rows[r];
+                if rhs ^ ((mask & free_mask & chosen).count_ones() % 2 == 1) {
+                    chosen |= 1 << col;
+                }
+            }
+            chosen
+        })
+        .min_by_key(|chosen| chosen.count_ones())
+        .map(|chosen| (0..n).map(|j| chosen & 1 << j != 0).collect())
+}
+
+/// Reduces `rows` to reduced row echelon form considering only the first `cols` columns.
+/// Returns the pivot column of each leading row.
+fn reduce(rows: &mut [Vec<Ratio>], cols: usize) -> Vec<usize> {
+    let mut pivots = Vec::new();
+    for col in 0..cols {
+        let r = pivots.len();
+        let Some(p) = (r..rows.len()).find(|&i| !rows[i][col].is_zero()) else {
+            continue;
+        };
+        rows.swap(r, p);
+        let inv = Ratio::ONE / rows[r][col];
+        rows[r].iter_mut().for_each(|x| *x = *x * inv);
+        let pivot = rows[r].clone();
+        for (i, row) in rows.iter_mut().enumerate() {
+            let factor = row[col];
+            if i != r && !factor.is_zero() {
+                row.iter_mut()
+                    .zip(&pivot)
+                    .for_each(|(x, p)| *x = *x - factor * *p);
+            }
+        }
+        pivots.push(col);
+    }
+    pivots
+}
+
+struct Equation {
+    col: usize,
+    den: i64,
+    rhs: i64,
+    coefs: Vec<i64>,
+}
+
+struct Search {
+    bounds: Vec<u64>,
+    equations: Vec<Equation>,
+    free_values: Vec<u64>,
+    // (total, free values, pivot values)
+    best: Option<(u64, Vec<u64>, Vec<u64>)>,
+}
+
+impl Search {
+    fn run(&mut self, k: usize, sum: u64) {
+        if self.best.as_ref().is_some_and(|(b, _, _)| sum >= *b) {
+            return;
+        }
+        if k == self.free_values.len() {
+            let mut total = sum;
+            let mut pivot_values = Vec::with_capacity(self.equations.len());
+            for e in &self.equations {
+                let num = e.rhs
+                    - e.coefs
+                        .iter()
+                        .zip(&self.free_values)
+                        .map(|(c, v)| c * *v as i64)
+                        .sum::<i64>();
+                if num % e.den != 0 || num / e.den < 0 {
+                    return;
+                }
+                let v = (num / e.den) as u64;
+                total += v;
+                pivot_values.push(v);
+            }
+            if self.best.as_ref().is_none_or(|(b, _, _)| total < *b) {
+                self.best = Some((total, self.free_values.clone(), pivot_values));
+            }
+            return;
+        }
+        for v in 0..=self.bounds[k] {
+            self.free_values[k] = v;
+            self.run(k + 1, sum + v);
+        }
+        self.free_values[k] = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::XorShift;
+
+    // Exhaustive search over every coefficient up to `max`.
+    fn brute_force(vectors: &[Vec<u64>], target: &[u64], max: u64) -> Option<u64> {
+        let mut best = None;
+        let mut coefs = vec![0; vectors.len()];
+        loop {
+            let sum: Vec<u64> = (0..target.len())
+                .map(|i| coefs.iter().zip(vectors).map(|(c, v)| c * v[i]).sum())
+                .collect();
+            if sum == target {
+                let total: u64 = coefs.iter().sum();
+                best = Some(best.map_or(total, |b: u64| b.min(total)));
+            }
+            let Some(k) = coefs.iter().position(|c| *c < max) else {
+                return best;
+            };
+            coefs[..k].fill(0);
+            coefs[k] += 1;
+        }
+    }
+
+    #[test]
+    fn test_min_combination() {
+        // 2025 day10 example, buttons as columns.
+        let buttons = [
+            vec![0, 0, 0, 1],
+            vec![0, 1, 0, 1],
+            vec![0, 0, 1, 0],
+            vec![0, 0, 1, 1],
+            vec![1, 0, 1, 0],
+            vec![1, 1, 0, 0],
+        ];
+        let c = min_combination(&buttons, &[3, 5, 4, 7]).unwrap();
+        assert_eq!(c.iter().sum::<u64>(), 10);
+        let reached: Vec<u64> = (0..4)
+            .map(|i| c.iter().zip(&buttons).map(|(c, v)| c * v[i]).sum())
+            .collect();
+        assert_eq!(reached, [3, 5, 4, 7]);
+    }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+        let mut rand = |n: u64| rng.below(n);
+        for _ in 0..200 {
+            let n = 1 + rand(4) as usize;
+            let m = 1 + rand(3) as usize;
+            let vectors: Vec<Vec<u64>> =
+                (0..n).map(|_| (0..m).map(|_| rand(3)).collect()).collect();
+            let target: Vec<u64> = (0..m).map(|_| rand(7)).collect();
+            let got = min_combination(&vectors, &target);
+            assert_eq!(
+                got.map(|c| c.iter().sum::<u64>()),
+                brute_force(&vectors, &target, 7),
+                "vectors {vectors:?} target {target:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_min_combination_gf2() {
+        // 2025 day10 example lights, with each button as a bitmask.
+        let buttons = [0b1000, 0b1010, 0b0100, 0b1100, 0b0101, 0b0011];
+        let chosen = min_combination_gf2(&buttons, 0b0110).unwrap();
+        assert_eq!(chosen.iter().filter(|c| **c).count(), 2);
+        let lights = buttons
+            .iter()
+            .zip(&chosen)
+            .filter(|(_, c)| **c)
+            .fold(0, |l, (b, _)| l ^ b);
+        assert_eq!(lights, 0b0110);
+        assert_eq!(min_combination_gf2(&buttons, 0), Some(vec![false; 6]));
+    }
+}
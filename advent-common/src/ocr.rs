@@ -0,0 +1,241 @@
+//! Reads the block letters puzzles draw their answers with.
+
+use std::hash::Hash;
+
+use thiserror::Error;
+
+use crate::image::Image;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum OcrError {
+    #[error("unsupported glyph height {0}, expected 6 or 10")]
+    UnsupportedHeight(usize),
+    #[error("unrecognized glyphs at x offsets {0:?}")]
+    UnrecognizedGlyphs(Vec<usize>),
+}
+
+// Each font is (glyph width, spacing between glyphs, glyphs).
+const FONT_6: (usize, usize, &[(char, &str)]) = (
+    4,
+    1,
+    &[
+        ('A', ".##.#..##..######..##..#"),
+        ('B', "###.#..####.#..##..####."),
+        ('C', ".##.#..##...#...#..#.##."),
+        ('E', "#####...###.#...#...####"),
+        ('F', "#####...###.#...#...#..."),
+        ('G', ".##.#..##...#.###..#.###"),
+        ('H', "#..##..######..##..##..#"),
+        ('J', "..##...#...#...##..#.##."),
+        ('K', "#..##.#.##..#.#.#.#.#..#"),
+        ('L', "#...#...#...#...#...####"),
+        ('O', ".##.#..##..##..##..#.##."),
+        ('P', "###.#..##..####.#...#..."),
+        ('R', "###.#..##..####.#.#.#..#"),
+        ('S', ".####...#....##....####."),
+        ('U', "#..##..##..##..##..#.##."),
+        ('Z', "####...#..#..#..#...####"),
+    ],
+);
+
+const FONT_10: (usize, usize, &[(char, &str)]) = (
+    6,
+    2,
+    &[
+        (
+            'A',
+            "..##...#..#.#....##....##....########....##....##....##....#",
+        ),
+        (
+            'B',
+            "#####.#....##....##....######.#....##....##....##....######.",
+        ),
+        (
+            'C',
+            ".####.#....##.....#.....#.....#.....#.....#.....#....#.####.",
+        ),
+        (
+            'E',
+            "#######.....#.....#.....#####.#.....#.....#.....#.....######",
+        ),
+        (
+            'F',
+            "#######.....#.....#.....#####.#.....#.....#.....#.....#.....",
+        ),
+        (
+            'G',
+            ".####.#....##.....#.....#.....#..####....##....##...##.###.#",
+        ),
+        (
+            'H',
+            "#....##....##....##....########....##....##....##....##....#",
+        ),
+        (
+            'J',
+            "...###....#.....#.....#.....#.....#.....#.#...#.#...#..###..",
+        ),
+        (
+            'K',
+            "#....##...#.#..#..#.#...##....##....#.#...#..#..#...#.#....#",
+        ),
+        (
+            'L',
+            "#.....#.....#.....#.....#.....#.....#.....#.....#.....######",
+        ),
+        (
+            'N',
+            "#....###...###...##.#..##.#..##..#.##..#.##...###...###....#",
+        ),
+        (
+            'P',
+            "#####.#....##....##....######.#.....#.....#.....#.....#.....",
+        ),
+        (
+            'R',
+            "#####.#....##....##....######.#..#..#...#.#...#.#....##....#",
+        ),
+        (
+            'X',
+            "#....##....#.#..#..#..#...##....##...#..#..#..#.#....##....#",
+        ),
+        (
+            'Z',
+            "######.....#.....#....#....#....#....#....#.....#.....######",
+        ),
+    ],
+);
+
+/// Recognizes the letters drawn in `im`, where every pixel that isn't `T::default()` is lit.
+/// Both the 4x6 and the 6x10 fonts are supported, chosen by the height of the lit area.
+///
+/// Example:
+///
+/// ```
+/// use advent::{ocr::recognize, prelude::*};
+///
+/// let raster = [
+///     ".##..###..",
+///     "#..#.#..#.",
+///     "#..#.###..",
+///     "####.#..#.",
+///     "#..#.#..#.",
+///     "#..#.###..",
+/// ];
+/// let mut im = Image::new(10, 6, false);
+/// for (y, row) in raster.iter().enumerate() {
+///     for (x, c) in row.chars().enumerate() {
+///         im[(x, y)] = c == '#';
+///     }
+/// }
+/// assert_eq!(recognize(&im).unwrap(), "AB");
+/// ```
+pub fn recognize<T>(im: &Image<T>) -> Result<String, OcrError>
+where
+    T: Copy + Default + Eq + Hash + Ord,
+{
+    let lit = |x: usize, y: usize| im[(x, y)] != T::default();
+    let row_lit = |y: usize| (0..im.width).any(|x| lit(x, y));
+    let col_lit = |x: usize| (0..im.height).any(|y| lit(x, y));
+    let (Some(top), Some(bottom), Some(left), Some(right)) = (
+        (0..im.height).find(|y| row_lit(*y)),
+        (0..im.height).rev().find(|y| row_lit(*y)),
+        (0..im.width).find(|x| col_lit(*x)),
+        (0..im.width).rev().find(|x| col_lit(*x)),
+    ) else {
+        return Ok(String::new());
+    };
+    let height = bottom - top + 1;
+    let (width, spacing, glyphs) = match height {
+        6 => FONT_6,
+        10 => FONT_10,
+        h => return Err(OcrError::UnsupportedHeight(h)),
+    };
+
+    let mut res = String::new();
+    let mut unknown = Vec::new();
+    for x0 in (left..=right).step_by(width + spacing) {
+        let pattern: String = (top..=bottom)
+            .flat_map(|y| (x0..x0 + width).map(move |x| (x, y)))
+            .map(|(x, y)| if x < im.width && lit(x, y) { '#' } else { '.' })
+            .collect();
+        match glyphs.iter().find(|(_, g)| *g == pattern) {
+            Some((c, _)) => res.push(*c),
+            None => unknown.push(x0),
+        }
+    }
+    if unknown.is_empty() {
+        Ok(res)
+    } else {
+        Err(OcrError::UnrecognizedGlyphs(unknown))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(raster: &str) -> Image<u8> {
+        let im: Image<u8> = raster.parse().unwrap();
+        let mut res = Image::new(im.width, im.height, 0);
+        for y in 0..im.height {
+            for x in 0..im.width {
+                res[(x, y)] = (im[(x, y)] == b'#') as u8;
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn test_font_6() {
+        // 2022 day10
+        let im = image(
+            "\
+###..#..#.#....#..#...##..##..####..##..
+#..#.#..#.#....#..#....#.#..#....#.#..#.
+#..#.####.#....####....#.#......#..#..#.
+###..#..#.#....#..#....#.#.##..#...####.
+#....#..#.#....#..#.#..#.#..#.#....#..#.
+#....#..#.####.#..#..##...###.####.#..#.",
+        );
+        assert_eq!(recognize(&im), Ok("PHLHJGZA".to_string()));
+    }
+
+    #[test]
+    fn test_font_10() {
+        let im = image(
+            "\
+#....#..######..#####.
+#....#..#.......#....#
+#....#..#.......#....#
+#....#..#.......#....#
+######..#####...#####.
+#....#..#.......#.....
+#....#..#.......#.....
+#....#..#.......#.....
+#....#..#.......#.....
+#....#..######..#.....",
+        );
+        assert_eq!(recognize(&im), Ok("HEP".to_string()));
+    }
+
+    #[test]
+    fn test_errors() {
+        let im = image("#\n#\n#");
+        assert_eq!(recognize(&im), Err(OcrError::UnsupportedHeight(3)));
+        // A reflected F and a reflected E.
+        let im = image(
+            "\
+.##..####..####
+#..#....#.....#
+#..#..###...###
+####....#.....#
+#..#....#.....#
+#..#....#..####",
+        );
+        assert_eq!(
+            recognize(&im),
+            Err(OcrError::UnrecognizedGlyphs(vec![5, 10]))
+        );
+        assert_eq!(recognize(&image("...\n...")), Ok(String::new()));
+    }
+}
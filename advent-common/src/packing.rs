@@ -0,0 +1,235 @@
+//! Polyomino packing: fit a number of copies of each shape into a region.
+
+use std::collections::HashSet;
+
+use crate::{exact_cover::ExactCover, image::Image};
+
+/// Cell value marking a filled square of a shape.
+pub const FILLED: char = '#';
+/// Cell value marking a free square of a region.
+pub const EMPTY: char = '.';
+
+/// Where one copy of a shape was placed, `(x, y)` is the top left of the oriented shape's image.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Ord, PartialOrd)]
+pub struct Placement {
+    pub shape: usize,
+    pub orientation: usize,
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Returns the distinct rotations and reflections of `shape`, in a stable order.
+pub fn orientations(shape: &Image<char>) -> Vec<Image<char>> {
+    let mut seen = HashSet::new();
+    let mut res = Vec::new();
+    for mut im in [shape.clone(), shape.flip_x()] {
+        for _ in 0..4 {
+            if seen.insert(im.clone()) {
+                res.push(im.clone());
+            }
+            im = im.rot90();
+        }
+    }
+    res.sort();
+    res
+}
+
+/// Tries to place `quantities[i]` copies of every shape in `region`. Each shape is given as the
+/// list of orientations it may be placed in, with cells marked [`FILLED`]. Only [`EMPTY`] cells
+/// of the region may be covered.
+///
+/// Returns a placement for every copy, or `None` if they can't all fit.
+///
+/// Example:
+///
+/// ```
+/// use advent::{packing::{orientations, pack, render}, prelude::*};
+///
+/// let l: Image<char> = "#.\n##".parse().unwrap();
+/// let shapes = vec![orientations(&l)];
+/// let region = Image::new(3, 2, '.');
+///
+/// let placements = pack(&shapes, &[2], &region).unwrap();
+/// assert_eq!(placements.len(), 2);
+/// // Two L trominoes fill the region completely.
+/// let im = render(&shapes, &placements, &region);
+/// assert!(!format!("{im:#}").contains('.'));
+/// assert_eq!(pack(&shapes, &[3], &region), None);
+/// ```
+pub fn pack(
+    shapes: &[Vec<Image<char>>],
+    quantities: &[usize],
+    region: &Image<char>,
+) -> Option<Vec<Placement>> {
+    assert_eq!(shapes.len(), quantities.len(), "one quantity per shape");
+    let free: Vec<(usize, usize)> = (0..region.height)
+        .flat_map(|y| (0..region.width).map(move |x| (x, y)))
+        .filter(|&xy| region[xy] == EMPTY)
+        .collect();
+    let area: usize = shapes
+        .iter()
+        .zip(quantities)
+        .map(|(orients, q)| q * filled(&orients[0]).count())
+        .sum();
+    if area > free.len() {
+        return None;
+    }
+    if let Some(placements) = pack_grid(shapes, quantities, region, free.len()) {
+        return Some(placements);
+    }
+
+    // Primary columns are the copies of each shape, secondary columns are the region's free
+    // cells.
+    let copies: usize = quantities.iter().sum();
+    let mut cell_col = vec![None; region.width * region.height];
+    for (i, (x, y)) in free.iter().enumerate() {
+        cell_col[x + y * region.width] = Some(copies + i);
+    }
+    let mut ec = ExactCover::new(copies, free.len()).with_slack(free.len() - area);
+    // For each row: (shape, copy, index among this shape's placements, placement)
+    let mut rows = Vec::new();
+    let mut first_copy = 0;
+    for (shape, (orients, q)) in shapes.iter().zip(quantities).enumerate() {
+        let mut candidates = Vec::new();
+        for (orientation, im) in orients.iter().enumerate() {
+            if im.width > region.width || im.height > region.height {
+                continue;
+            }
+            for y in 0..=region.height - im.height {
+                for x in 0..=region.width - im.width {
+                    let cols: Option<Vec<usize>> = filled(im)
+                        .map(|(i, j)| cell_col[x + i + (y + j) * region.width])
+                        .collect();
+                    if let Some(cols) = cols {
+                        let p = Placement {
+                            shape,
+                            orientation,
+                            x,
+                            y,
+                        };
+                        candidates.push((p, cols));
+                    }
+                }
+            }
+        }
+        for copy in 0..*q {
+            for (idx, (p, cols)) in candidates.iter().enumerate() {
+                let mut row = vec![first_copy + copy];
+                row.extend(cols);
+                ec.add_row(&row);
+                rows.push((shape, copy, idx, *p));
+            }
+        }
+        first_copy += q;
+    }
+
+    // Copies of a shape are interchangeable, so only accept solutions where copy k's placement
+    // comes before copy k + 1's.
+    let solution = ec.solve_with(|partial, row| {
+        let (shape, copy, idx, _) = rows[row];
+        partial.iter().all(|&r| {
+            let (s, c, i, _) = rows[r];
+            s != shape || (c < copy && i < idx) || (c > copy && i > idx)
+        })
+    })?;
+    let mut placements: Vec<_> = solution.into_iter().map(|r| rows[r].3).collect();
+    placements.sort();
+    Some(placements)
+}
+
+/// Fast path for loosely packed regions: if every copy fits in its own bounding box cell of a
+/// grid laid over a fully free region, no search is needed.
+fn pack_grid(
+    shapes: &[Vec<Image<char>>],
+    quantities: &[usize],
+    region: &Image<char>,
+    free: usize,
+) -> Option<Vec<Placement>> {
+    if free != region.width * region.height {
+        return None;
+    }
+    let w = shapes.iter().map(|o| o[0].width).max()?;
+    let h = shapes.iter().map(|o| o[0].height).max()?;
+    let cols = region.width / w;
+    let slots = cols * (region.height / h);
+    if quantities.iter().sum::<usize>() > slots {
+        return None;
+    }
+    let mut slot = 0;
+    let mut placements = Vec::new();
+    for (shape, q) in quantities.iter().enumerate() {
+        for _ in 0..*q {
+            placements.push(Placement {
+                shape,
+                orientation: 0,
+                x: (slot % cols) * w,
+                y: (slot / cols) * h,
+            });
+            slot += 1;
+        }
+    }
+    Some(placements)
+}
+
+/// Draws `placements` over `region`, labeling each placed copy with a letter, `A` through `Z`
+/// then wrapping around.
+pub fn render(
+    shapes: &[Vec<Image<char>>],
+    placements: &[Placement],
+    region: &Image<char>,
+) -> Image<char> {
+    let mut im = region.clone();
+    for (n, p) in placements.iter().enumerate() {
+        let label = (b'A' + (n % 26) as u8) as char;
+        for (i, j) in filled(&shapes[p.shape][p.orientation]) {
+            im[(p.x + i, p.y + j)] = label;
+        }
+    }
+    im
+}
+
+fn filled(im: &Image<char>) -> impl Iterator<Item = (usize, usize)> + '_ {
+    (0..im.height)
+        .flat_map(move |y| (0..im.width).map(move |x| (x, y)))
+        .filter(|&xy| im[xy] == FILLED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orientations() {
+        let l: Image<char> = "#.\n#.\n##".parse().unwrap();
+        assert_eq!(orientations(&l).len(), 8);
+        let square: Image<char> = "##\n##".parse().unwrap();
+        assert_eq!(orientations(&square).len(), 1);
+    }
+
+    #[test]
+    fn test_pack_tetrominoes() {
+        let shapes: Vec<_> = ["##\n##", "#.\n#.\n##", ".#\n##\n#."]
+            .iter()
+            .map(|s| orientations(&s.parse().unwrap()))
+            .collect();
+        let region = Image::new(4, 4, EMPTY);
+        let placements = pack(&shapes, &[2, 2, 0], &region).unwrap();
+        assert_eq!(placements.len(), 4);
+        let im = render(&shapes, &placements, &region);
+        assert_eq!(format!("{im:#}").matches(EMPTY).count(), 0);
+        // Two S pieces can't tile a 4x2 box.
+        assert_eq!(pack(&shapes, &[0, 0, 2], &Image::new(4, 2, EMPTY)), None);
+    }
+
+    #[test]
+    fn test_pack_blocked() {
+        let shapes = vec![orientations(&"###".parse().unwrap())];
+        let region: Image<char> = "...\n.#.\n...".parse().unwrap();
+        let placements = pack(&shapes, &[2], &region).unwrap();
+        let im = format!("{:#}", render(&shapes, &placements, &region));
+        assert_eq!(im.matches('A').count(), 3);
+        assert_eq!(im.matches('B').count(), 3);
+        assert_eq!(im.matches('#').count(), 1);
+        assert_eq!(pack(&shapes, &[3], &region), None);
+    }
+}
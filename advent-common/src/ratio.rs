@@ -0,0 +1,154 @@
+use std::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+use crate::gcd;
+
+/// Exact fraction in lowest terms with a positive denominator. The operators panic on overflow or
+/// division by zero, the `checked_*` methods return `None` instead.
+///
+/// Example:
+///
+/// ```
+/// use advent::Ratio;
+///
+/// let a = Ratio::new(1, 3);
+/// let b = Ratio::new(-2, 4);
+/// assert_eq!(a + b, Ratio::new(-1, 6));
+/// assert_eq!((a / b).to_string(), "-2/3");
+/// assert_eq!(Ratio::from(4) * a, Ratio::new(4, 3));
+/// assert_eq!(Ratio::new(6, 3).to_integer(), Some(2));
+/// assert_eq!(a.checked_div(Ratio::from(0)), None);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Ratio {
+    num: i64,
+    den: i64,
+}
+
+impl Ratio {
+    pub const ZERO: Ratio = Ratio { num: 0, den: 1 };
+    pub const ONE: Ratio = Ratio { num: 1, den: 1 };
+
+    /// Panics if `den` is zero.
+    pub fn new(num: i64, den: i64) -> Self {
+        Self::reduce(num as i128, den as i128).expect("invalid ratio")
+    }
+    fn reduce(num: i128, den: i128) -> Option<Self> {
+        if den == 0 {
+            return None;
+        }
+        let g = gcd(num, den) * den.signum();
+        Some(Ratio {
+            num: (num / g).try_into().ok()?,
+            den: (den / g).try_into().ok()?,
+        })
+    }
+    pub fn numer(&self) -> i64 {
+        self.num
+    }
+    pub fn denom(&self) -> i64 {
+        self.den
+    }
+    pub fn is_zero(&self) -> bool {
+        self.num == 0
+    }
+    pub fn is_integer(&self) -> bool {
+        self.den == 1
+    }
+    /// Returns the value if it is a whole number.
+    pub fn to_integer(&self) -> Option<i64> {
+        self.is_integer().then_some(self.num)
+    }
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        let (a, b, c, d) = self.wide(rhs);
+        Self::reduce(a * d + c * b, b * d)
+    }
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        let (a, b, c, d) = self.wide(rhs);
+        Self::reduce(a * d - c * b, b * d)
+    }
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        let (a, b, c, d) = self.wide(rhs);
+        Self::reduce(a * c, b * d)
+    }
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        let (a, b, c, d) = self.wide(rhs);
+        Self::reduce(a * d, b * c)
+    }
+    fn wide(self, rhs: Self) -> (i128, i128, i128, i128) {
+        (
+            self.num as i128,
+            self.den as i128,
+            rhs.num as i128,
+            rhs.den as i128,
+        )
+    }
+}
+
+impl From<i64> for Ratio {
+    fn from(v: i64) -> Self {
+        Ratio { num: v, den: 1 }
+    }
+}
+
+impl Ord for Ratio {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b, c, d) = self.wide(*other);
+        (a * d).cmp(&(c * b))
+    }
+}
+
+impl PartialOrd for Ratio {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for Ratio {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        self.checked_add(rhs).expect("ratio overflow")
+    }
+}
+
+impl Sub for Ratio {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.checked_sub(rhs).expect("ratio overflow")
+    }
+}
+
+impl Mul for Ratio {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.checked_mul(rhs).expect("ratio overflow")
+    }
+}
+
+impl Div for Ratio {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(rhs)
+            .expect("ratio overflow or division by zero")
+    }
+}
+
+impl Neg for Ratio {
+    type Output = Self;
+    fn neg(self) -> Self::Output {
+        Ratio::ZERO - self
+    }
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
@@ -0,0 +1,35 @@
+/// Small deterministic xorshift64 generator, for tests and anything else that needs repeatable
+/// pseudo-random numbers.
+///
+/// Example:
+///
+/// ```
+/// use advent::XorShift;
+///
+/// let mut a = XorShift::new(7);
+/// let mut b = XorShift::new(7);
+/// assert_eq!(a.next_u64(), b.next_u64());
+/// assert!(a.below(10) < 10);
+/// ```
+#[derive(Clone, Debug)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    /// Starts the sequence from `seed`, which must not be 0 since xorshift never leaves it.
+    pub fn new(seed: u64) -> Self {
+        assert_ne!(seed, 0, "xorshift seed must be non-zero");
+        XorShift(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Next number in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
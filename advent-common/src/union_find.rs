@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+/// Disjoint-set forest over the elements `0..n`, using path compression and union by size.
+///
+/// Example:
+///
+/// ```
+/// use advent::UnionFind;
+///
+/// let mut uf = UnionFind::new(5);
+/// assert_eq!(uf.num_components(), 5);
+///
+/// assert!(uf.union(0, 1));
+/// assert!(uf.union(3, 4));
+/// assert!(!uf.union(1, 0));
+/// assert_eq!(uf.num_components(), 3);
+///
+/// assert!(uf.connected(0, 1));
+/// assert!(!uf.connected(1, 3));
+/// assert_eq!(uf.component_size(4), 2);
+/// assert_eq!(uf.component_size(2), 1);
+///
+/// let mut comps: Vec<_> = uf.components().collect();
+/// comps.sort();
+/// assert_eq!(comps, vec![vec![0, 1], vec![2], vec![3, 4]]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    num_components: usize,
+}
+
+impl UnionFind {
+    /// Creates `n` singleton components.
+    pub fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+            num_components: n,
+        }
+    }
+    /// Number of elements tracked.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+    /// Returns the representative of the component containing `x`, compressing the path walked.
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut cur = x;
+        while self.parent[cur] != root {
+            let next = self.parent[cur];
+            self.parent[cur] = root;
+            cur = next;
+        }
+        root
+    }
+    /// Merges the components containing `a` and `b`. Returns false if they were already joined.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        self.num_components -= 1;
+        true
+    }
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+    /// Number of elements in the component containing `x`.
+    pub fn component_size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+    /// Number of disjoint components remaining.
+    pub fn num_components(&self) -> usize {
+        self.num_components
+    }
+    /// Iterates over every component as a list of its members, in ascending order. Components are
+    /// ordered by their smallest member.
+    pub fn components(&mut self) -> impl Iterator<Item = Vec<usize>> {
+        let mut order = Vec::with_capacity(self.num_components);
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for x in 0..self.len() {
+            let root = self.find(x);
+            groups
+                .entry(root)
+                .or_insert_with(|| {
+                    order.push(root);
+                    Vec::new()
+                })
+                .push(x);
+        }
+        order
+            .into_iter()
+            .map(move |root| groups.remove(&root).expect("missing component"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vec3;
+
+    // 2025 day8 example
+    const INPUT: &str = r#"162,817,812
+57,618,57
+906,360,560
+592,479,940
+352,342,300
+466,668,158
+542,29,236
+431,825,988
+739,650,466
+52,470,668
+216,146,977
+819,987,18
+117,168,530
+805,96,715
+346,949,466
+970,615,88
+941,993,340
+862,61,35
+984,92,344
+425,690,689"#;
+
+    fn closest_pairs(boxes: &[Vec3]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for i in 0..boxes.len() {
+            for j in i + 1..boxes.len() {
+                pairs.push((boxes[i].distance_squared(&boxes[j]), i, j));
+            }
+        }
+        pairs.sort();
+        pairs.into_iter().map(|(_, i, j)| (i, j)).collect()
+    }
+
+    #[test]
+    fn test_circuits() {
+        let boxes: Vec<Vec3> = INPUT.lines().map(|l| l.parse().unwrap()).collect();
+        let mut uf = UnionFind::new(boxes.len());
+        for (i, j) in closest_pairs(&boxes).into_iter().take(10) {
+            uf.union(i, j);
+        }
+        assert_eq!(uf.num_components(), 11);
+        let mut sizes: Vec<_> = uf.components().map(|c| c.len()).collect();
+        sizes.sort_by(|a, b| b.cmp(a));
+        assert_eq!(sizes[..3], [5, 4, 2]);
+        assert_eq!(sizes.iter().sum::<usize>(), boxes.len());
+        assert_eq!(sizes[..3].iter().product::<usize>(), 40);
+    }
+
+    #[test]
+    fn test_single_circuit() {
+        let boxes: Vec<Vec3> = INPUT.lines().map(|l| l.parse().unwrap()).collect();
+        let mut uf = UnionFind::new(boxes.len());
+        let (i, j) = closest_pairs(&boxes)
+            .into_iter()
+            .find(|&(i, j)| uf.union(i, j) && uf.num_components() == 1)
+            .expect("never formed a single circuit");
+        assert_eq!(uf.component_size(0), boxes.len());
+        assert_eq!(boxes[i].x() * boxes[j].x(), 25272);
+    }
+}